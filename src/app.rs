@@ -1,12 +1,28 @@
-use crate::port::PortInfo;
-use crate::tunnel::{TunnelConfig, TunnelManager};
+use crate::cli::Options;
+use crate::clipboard::Clipboard;
+use crate::notify::Notifier;
+use crate::port::{BindScope, PortBackend, PortInfo, ProcessDetails};
+use crate::settings::{
+    ExposedService, Settings, SortKey, ThemeName, TunnelColorMode, TunnelSort,
+};
+use crate::state::UiState;
+use crate::tunnel::{ForwardKind, HostResolver, TestReport, TunnelConfig, TunnelManager};
 use anyhow::Result;
-use std::process::Command;
+use ratatui::layout::Rect;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::{Duration, Instant};
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
 pub enum AppTab {
+    #[default]
     Ports,
     Tunnels,
+    /// Established connections only, with both ends
+    Connections,
 }
 
 #[derive(Clone, Copy, PartialEq)]
@@ -14,8 +30,397 @@ pub enum InputMode {
     None,
     TunnelName,
     TunnelHost,
+    TunnelKind,
     TunnelLocalPort,
+    TunnelBindAddress,
     TunnelRemotePort,
+    TunnelIdentityFile,
+    TunnelSshPort,
+    TunnelJumpHost,
+    ProfileName,
+    KillPid,
+    ExportFormat,
+}
+
+/// Fields of the add-tunnel form, in focus order
+pub const TUNNEL_FORM_FIELDS: [&str; 9] = [
+    "Name",
+    "SSH host",
+    "Kind",
+    "Local port",
+    "Bind address",
+    "Remote target",
+    "Identity file",
+    "SSH port",
+    "Jump host",
+];
+/// Shown dimmed in empty fields that may be left empty
+pub const TUNNEL_FORM_PLACEHOLDERS: [&str; 9] = [
+    "",
+    "",
+    "local, remote or dynamic",
+    "",
+    "optional, loopback if empty",
+    "",
+    "optional",
+    "optional",
+    "optional, [user@]host[:port]",
+];
+const FORM_SSH_HOST: usize = 1;
+const FORM_KIND: usize = 2;
+const FORM_LOCAL_PORT: usize = 3;
+const FORM_BIND_ADDRESS: usize = 4;
+const FORM_REMOTE_TARGET: usize = 5;
+const FORM_IDENTITY_FILE: usize = 6;
+const FORM_SSH_PORT: usize = 7;
+const FORM_JUMP_HOST: usize = 8;
+
+/// How long rows that appeared or changed state stay highlighted after a refresh
+const CHANGE_FLASH: Duration = Duration::from_secs(1);
+
+/// How many SSH hosts are remembered for the add-tunnel prompt
+const RECENT_HOSTS_MAX: usize = 10;
+
+/// How often tunnels are checked for state changes to notify about
+const TUNNEL_WATCH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Terminals narrower than this get the one-field-at-a-time wizard instead of the form
+const FORM_MIN_WIDTH: u16 = 60;
+
+/// Identifies a socket across scans: port, PID, protocol and both endpoints
+type PortKey = (u16, i32, String, String, String);
+
+fn port_key(port: &PortInfo) -> PortKey {
+    (
+        port.port,
+        port.pid,
+        port.protocol.clone(),
+        port.local_address.clone(),
+        port.foreign_address.clone(),
+    )
+}
+
+/// Index of the socket `key` names, or failing that another socket of the same port and PID.
+fn find_port<'a>(
+    ports: impl Iterator<Item = &'a PortInfo> + Clone,
+    key: &PortKey,
+) -> Option<usize> {
+    ports
+        .clone()
+        .position(|p| port_key(p) == *key)
+        .or_else(|| ports.into_iter().position(|p| p.port == key.0 && p.pid == key.1))
+}
+
+/// An optional port field: empty is `None`, anything else must be a port number.
+fn parse_optional_port(input: &str) -> Result<Option<u16>, std::num::ParseIntError> {
+    if input.is_empty() {
+        Ok(None)
+    } else {
+        input.parse().map(Some)
+    }
+}
+
+/// Whether a socket's port, process name or PID contains `needle` (already lowercase).
+fn port_matches(port: &PortInfo, needle: &str) -> bool {
+    port.port.to_string().contains(needle)
+        || port.process_name.to_lowercase().contains(needle)
+        || port.pid.to_string().contains(needle)
+}
+
+/// Whether a tunnel's name, host, local port or target contains `needle` (already lowercase).
+fn tunnel_matches(tunnel: &TunnelConfig, needle: &str) -> bool {
+    tunnel.name.to_lowercase().contains(needle)
+        || tunnel.ssh_host.to_lowercase().contains(needle)
+        || tunnel.local_port.to_string().contains(needle)
+        || tunnel.remote_target.to_lowercase().contains(needle)
+}
+
+/// What must be typed to kill or restart a core system process, or several protected ones
+const PROTECTED_CONFIRMATION: &str = "yes";
+
+/// Why a process is protected, and what must be typed to act on it anyway
+struct Protection {
+    reason: String,
+    answer: String,
+}
+
+/// Longest gap between two clicks on a row that still counts as a double-click
+const DOUBLE_CLICK: Duration = Duration::from_millis(400);
+
+/// The value after `current` in `values`, wrapping round to no filter after the last.
+fn next_filter(current: Option<String>, values: &[&str]) -> Option<String> {
+    let next = match current {
+        None => 0,
+        Some(current) => values.iter().position(|v| *v == current)? + 1,
+    };
+    values.get(next).map(|v| v.to_string())
+}
+
+/// A process about to be killed, with what the user saw it as
+#[derive(Clone)]
+pub struct KillTarget {
+    pub pid: i32,
+    pub process_name: String,
+    pub port: Option<u16>,
+}
+
+/// A kill attempted this session, kept as an audit trail
+pub struct KillRecord {
+    pub pid: i32,
+    pub process_name: String,
+    pub port: Option<u16>,
+    pub signal: &'static str,
+    pub at: Instant,
+    /// `None` on success, otherwise kill's error output
+    pub error: Option<String>,
+}
+
+/// One line of the ports table, indexing into `filtered_ports`
+#[derive(Clone, PartialEq)]
+pub enum PortRow {
+    Socket(usize),
+    /// Header for every socket of one process in the grouped view
+    Process { pid: i32, sockets: Vec<usize> },
+}
+
+/// Context printed and exported when dropping into a shell for the selected row
+pub struct ShellHandoff {
+    pub lines: Vec<String>,
+    pub env: Vec<(&'static str, String)>,
+}
+
+/// Lines kept in the ssh log viewer
+const LOG_VIEW_LINES: usize = 1000;
+
+/// The ssh log of one tunnel, reread every tick while its popup is open
+pub struct LogView {
+    pub tunnel: String,
+    pub path: PathBuf,
+    pub lines: Vec<String>,
+    /// Lines scrolled back from the end; 0 follows new output
+    pub scroll_back: usize,
+}
+
+/// A pre-flight test of one tunnel, shown in a popup while it runs and after
+pub struct TunnelTest {
+    pub tunnel: String,
+    pub started: Instant,
+    /// `None` until the worker thread answers
+    pub result: Option<Result<TestReport, String>>,
+    results: Receiver<Result<TestReport>>,
+}
+
+/// A port scan running on a worker thread
+struct PendingScan {
+    results: Receiver<Result<crate::port::Scan>>,
+    /// Started by auto-refresh, so the status bar keeps whatever it last said
+    quiet: bool,
+    started: Instant,
+}
+
+/// Scan sockets, adding listen queues when they're shown; also run on the scan worker.
+fn scan_ports(
+    backend: PortBackend,
+    privileged: bool,
+    socket_stats: bool,
+) -> Result<crate::port::Scan> {
+    let mut scan = crate::port::scan_ports_with(backend, privileged)?;
+    if socket_stats {
+        crate::port::fill_listen_queues(&mut scan.ports);
+    }
+    Ok(scan)
+}
+
+/// Kills running on a worker thread, which waits out the grace period before any SIGKILL
+struct PendingKill {
+    targets: Vec<KillTarget>,
+    graceful: bool,
+    signal: Option<&'static str>,
+    outcomes: Receiver<Result<Vec<crate::port::KillOutcome>>>,
+}
+
+/// A process restart running on a worker thread, which reports the status line to show
+struct PendingRestart {
+    pid: i32,
+    result: Receiver<String>,
+}
+
+/// Stop `pid`, wait for it to exit, then launch `argv` again and wait for it to bind `port`.
+///
+/// Runs on a worker thread, which stays behind to reap the new process once it has reported.
+fn restart_worker(
+    pid: i32,
+    port: u16,
+    backend: PortBackend,
+    argv: Vec<String>,
+    cwd: Option<PathBuf>,
+    result: Sender<String>,
+) {
+    let report = |message: String| {
+        let _ = result.send(message);
+    };
+    let output = match Command::new("kill").arg(pid.to_string()).output() {
+        Ok(output) => output,
+        Err(e) => return report(format!("Failed to stop process: {}", e)),
+    };
+    if !output.status.success() {
+        return report(format!(
+            "Failed to stop process: {}",
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+
+    // The old process must release the port before the new one can bind it
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while crate::port::process_alive(pid) {
+        if Instant::now() >= deadline {
+            return report(format!("Process {} did not exit in time; not relaunching", pid));
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+
+    let mut command = Command::new(&argv[0]);
+    command
+        .args(&argv[1..])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    if let Some(cwd) = &cwd {
+        command.current_dir(cwd);
+    }
+
+    let mut child = match command.spawn() {
+        Ok(child) => child,
+        Err(e) => return report(format!("Stopped {} but failed to relaunch: {}", pid, e)),
+    };
+
+    // Give the new process a moment to come back up on its port
+    let deadline = Instant::now() + Duration::from_secs(3);
+    let mut listening = false;
+    while Instant::now() < deadline {
+        if crate::port::sockets_on_port(backend, port).is_ok_and(|sockets| !sockets.is_empty()) {
+            listening = true;
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(250));
+    }
+
+    report(if listening {
+        format!("Restarted as PID {}, listening on port {}", child.id(), port)
+    } else {
+        format!("Restarted as PID {}, not yet listening on port {}", child.id(), port)
+    });
+    // Reap it when it exits rather than leave a zombie behind
+    let _ = child.wait();
+}
+
+/// Auto-start connections running in the background, and their results so far
+struct AutoStart {
+    /// Each connected copy of a tunnel, with the error if it failed
+    results: Receiver<(TunnelConfig, Option<String>)>,
+    pending: usize,
+    started: usize,
+    failed: Vec<String>,
+}
+
+/// Reconnects of dropped auto-reconnect tunnels running in the background
+struct Reconnects {
+    sender: Sender<(TunnelConfig, Option<String>)>,
+    /// Each reconnected copy of a tunnel, with the error if it failed
+    results: Receiver<(TunnelConfig, Option<String>)>,
+    /// Names of tunnels with an attempt still running
+    in_flight: HashSet<String>,
+}
+
+impl Reconnects {
+    fn new() -> Self {
+        let (sender, results) = mpsc::channel();
+        Self {
+            sender,
+            results,
+            in_flight: HashSet::new(),
+        }
+    }
+}
+
+/// What a tunnel's worker thread is doing
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TunnelJob {
+    Connect,
+    Disconnect,
+    Probe,
+}
+
+/// Connects, disconnects and tests started by hand, running in the background so waiting on
+/// ssh or the forward can't stall the UI
+struct TunnelJobs {
+    sender: Sender<(TunnelJob, TunnelConfig, Option<String>)>,
+    /// Each worked-on copy of a tunnel, with the error if the job failed
+    results: Receiver<(TunnelJob, TunnelConfig, Option<String>)>,
+    /// Tunnels with a job still running, and which
+    in_flight: HashMap<String, TunnelJob>,
+}
+
+impl TunnelJobs {
+    fn new() -> Self {
+        let (sender, results) = mpsc::channel();
+        Self {
+            sender,
+            results,
+            in_flight: HashMap::new(),
+        }
+    }
+}
+
+/// Stop a tunnel copy nothing tracks any more, such as one connected after its tunnel was
+/// deleted, without waiting for ssh to go.
+fn disconnect_in_background(mut stray: TunnelConfig) {
+    std::thread::spawn(move || {
+        let _ = stray.disconnect();
+    });
+}
+
+/// All add-tunnel fields edited at once, one of them focused
+#[derive(Clone, Default)]
+pub struct TunnelForm {
+    pub values: [String; TUNNEL_FORM_FIELDS.len()],
+    pub focus: usize,
+}
+
+impl TunnelForm {
+    /// The form filled in from a saved tunnel.
+    fn from_tunnel(tunnel: &TunnelConfig, focus: usize) -> Self {
+        Self {
+            values: [
+                tunnel.name.clone(),
+                tunnel.ssh_host.clone(),
+                tunnel.kind.label().to_string(),
+                tunnel.local_port.to_string(),
+                tunnel.bind_address.clone().unwrap_or_default(),
+                tunnel.remote_target.clone(),
+                tunnel.identity_file.clone().unwrap_or_default(),
+                tunnel.ssh_port.map(|port| port.to_string()).unwrap_or_default(),
+                tunnel.jump_host.clone().unwrap_or_default(),
+            ],
+            focus,
+        }
+    }
+
+    pub fn focus_next(&mut self) {
+        self.focus = (self.focus + 1) % self.values.len();
+    }
+
+    pub fn focus_prev(&mut self) {
+        self.focus = (self.focus + self.values.len() - 1) % self.values.len();
+    }
+
+    pub fn push(&mut self, c: char) {
+        self.values[self.focus].push(c);
+    }
+
+    pub fn pop(&mut self) {
+        self.values[self.focus].pop();
+    }
 }
 
 pub struct App {
@@ -23,325 +428,3170 @@ pub struct App {
 
     // Port list
     pub ports: Vec<PortInfo>,
-    pub filtered_ports: Vec<PortInfo>,
+    /// Indices into `ports` of the sockets shown, filtered and sorted
+    pub filtered_ports: Vec<usize>,
+    /// IPv4 listeners mapped to their IPv6 twin, as indices into `ports`
+    dual_stack_twins: HashMap<usize, usize>,
+    /// IPv4 listeners currently shown with their twin folded in
+    dual_stack_merged: HashSet<usize>,
+    /// Rows actually displayed; `port_selected` indexes into these
+    pub port_rows: Vec<PortRow>,
     pub port_selected: usize,
+    /// Indices into `ports` of established sockets matching the filter, for the Connections tab
+    pub connections: Vec<usize>,
+    pub connection_selected: usize,
+    /// Last scan's state per socket, and when each socket last appeared or changed
+    port_states: HashMap<PortKey, String>,
+    port_changed_at: HashMap<PortKey, Instant>,
+    /// First visible row of the ports table, kept in step by the renderer
+    pub port_offset: std::cell::Cell<usize>,
+    /// First visible row of the tunnels table, kept in step by the renderer
+    pub tunnel_offset: std::cell::Cell<usize>,
+    /// First visible row of the connections table, kept in step by the renderer
+    pub connection_offset: std::cell::Cell<usize>,
+    /// Rows the last drawn table had room for, so PageUp/PageDown move a screenful
+    pub page_rows: std::cell::Cell<usize>,
+    /// Where the tab headers and the current table were last drawn, for mouse clicks
+    pub tabs_area: std::cell::Cell<Rect>,
+    pub table_area: std::cell::Cell<Rect>,
+    /// Row and time of the last click, to recognise a double-click
+    last_click: Option<(usize, Instant)>,
+    /// Detail pane under the ports table, toggled with i
+    pub show_port_details: bool,
+    /// Details of the last PID shown in the pane (`None` once it has exited), so the renderer
+    /// doesn't run ps on every frame
+    port_details: std::cell::RefCell<Option<(i32, Option<ProcessDetails>)>>,
+    pub socket_stats: bool,
+    pub socket_count_column: bool,
+    pub connection_count_column: bool,
+    pub age_column: bool,
+    pub merge_dual_stack: bool,
+    /// Hide sockets owned by other users
+    pub mine_only: bool,
+    /// Show only this protocol ("TCP" or "UDP"), cycled with `t`
+    pub proto_filter: Option<String>,
+    /// Show only this state ("LISTEN" or "ESTABLISHED"), cycled with `l`
+    pub state_filter: Option<String>,
+    /// Show only sockets bound with this scope, cycled with `b`
+    pub scope_filter: Option<BindScope>,
+    /// Show one expandable row per process instead of one per socket
+    pub group_by_process: bool,
+    /// (PID, port) pairs marked with Space for a bulk kill
+    pub selected_ports: HashSet<(i32, u16)>,
+    /// (PID, port) pairs of processes sharing a port with another process, from the full scan
+    pub conflicting_ports: HashSet<(i32, u16)>,
+    /// How many ports have more than one process bound
+    pub conflict_count: usize,
+    pub sort_key: SortKey,
+    pub sort_ascending: bool,
+    pub expanded_processes: HashSet<i32>,
+    pub current_user: String,
+    /// Scans run through sudo once the user has opted in
+    pub privileged: bool,
+    /// Set when the scan reported errors, so the list may be missing sockets
+    pub scan_problem: Option<String>,
+    /// When `ports` was last scanned, in seconds since the Unix epoch
+    scanned_at: u64,
+    /// lsof, ss or /proc/net, whichever this machine has
+    pub port_backend: PortBackend,
+
+    // Sensitive services listening on all interfaces
+    pub exposed_patterns: Vec<ExposedService>,
+    pub exposed: Vec<String>,
+    pub exposed_dismissed: bool,
+
+    // Desktop notifications
+    pub notifier: Notifier,
+    clipboard: Clipboard,
+    /// Last seen connected state per tunnel name
+    tunnel_up: HashMap<String, bool>,
+    last_tunnel_watch: Option<Instant>,
 
     // Tunnel management
     pub tunnel_manager: TunnelManager,
+    pub profile: String,
+    /// Indices into `tunnel_manager.tunnels` that pass the tunnel filter
+    pub filtered_tunnels: Vec<usize>,
+    /// Position within `filtered_tunnels`
     pub tunnel_selected: usize,
+    pub show_tunnel_details: bool,
+    pub host_resolver: HostResolver,
+    pub ssh_binary: String,
+    pub tunnel_colors: TunnelColorMode,
+
+    // Markers instead of colors
+    pub monochrome: bool,
+    pub theme: ThemeName,
 
     // Filter
     pub show_filter: bool,
+    /// Search prompt open: matches are highlighted and jumped to without hiding other rows
+    pub show_search: bool,
+    pub search_text: String,
+    /// Row selected when the search prompt opened, where each keystroke searches from
+    search_origin: usize,
     pub filter_text: String,
+    pub tunnel_filter_text: String,
+    /// One filter for both tabs instead of one per tab
+    pub shared_filter: bool,
 
     // Input dialog
     pub show_input: bool,
     pub input_mode: InputMode,
     pub input_prompt: String,
     pub input_buffer: String,
+    /// Cursor position in characters; may be stale after the buffer changes, so read it
+    /// through `input_cursor_position`
+    pub input_cursor: usize,
 
     // Confirmation dialog
     pub show_confirm: bool,
     pub confirm_message: String,
     pub pending_action: Option<PendingAction>,
+    /// Text that must be typed to confirm: `yes`, a protected process name, or a batch kill's
+    /// count
+    pub confirm_expected: Option<String>,
+    /// Why typing is required, shown above the input
+    pub confirm_reason: String,
+    pub confirm_input: String,
+    /// Whether Yes (rather than No) has focus, moved with ←/→ or Tab and pressed with Enter
+    pub confirm_focus_yes: bool,
+    pub protected_processes: Vec<String>,
+    pub protect_system_processes: bool,
+    /// Kills affecting more processes than this need the count typed
+    pub batch_confirm_threshold: usize,
+    /// How long a graceful kill waits after SIGTERM before sending SIGKILL
+    pub kill_grace: Duration,
+
+    // Kills attempted this session, oldest first
+    pub kill_log: Vec<KillRecord>,
+    pub show_kill_log: bool,
+    /// ssh command line of the selected tunnel, shown in a popup before connecting
+    pub command_preview: Option<String>,
+    pub log_view: Option<LogView>,
+    pub tunnel_test: Option<TunnelTest>,
+    /// Highlighted row of the signal picker while it's open
+    pub signal_picker: Option<usize>,
 
     // New tunnel being created
     pub new_tunnel: Option<TunnelConfig>,
+    pub tunnel_form: Option<TunnelForm>,
+    /// Original name of the tunnel the form or prompts are editing; `None` when adding
+    pub editing_tunnel: Option<String>,
+    /// Name of the tunnel the form or prompts are adding a copy of
+    pub duplicating_tunnel: Option<String>,
+    pub tunnel_sort: TunnelSort,
+    /// SSH hosts of recently saved tunnels, most recent first
+    pub recent_hosts: Vec<String>,
+    /// Recent host highlighted in the SSH host prompt's suggestions
+    pub host_suggestion: Option<usize>,
+    auto_start: Option<AutoStart>,
+    reconnects: Reconnects,
+    tunnel_jobs: TunnelJobs,
+
+    /// Milliseconds between background ticks of the event loop
+    pub tick_ms: u64,
+
+    // Rescan ports on a timer, toggled with A
+    pub auto_refresh: bool,
+    pub refresh_interval: Duration,
+    last_refresh: Instant,
+    pending_scan: Option<PendingScan>,
+    pending_kill: Option<PendingKill>,
+    pending_restart: Option<PendingRestart>,
+
+    // Help overlay
+    pub show_help: bool,
+    pub help_scroll: u16,
 
     // Status message
     pub status_message: String,
+
+    // Destructive actions disabled
+    pub read_only: bool,
+    /// Released whenever a shell or sudo borrows the terminal
+    pub mouse_capture: bool,
 }
 
 #[derive(Clone)]
 pub enum PendingAction {
-    KillProcess(i32),
+    /// Graceful kills send SIGTERM first and escalate to SIGKILL after the grace period;
+    /// a `signal` chosen in the picker is sent once instead
+    KillProcesses {
+        targets: Vec<KillTarget>,
+        graceful: bool,
+        signal: Option<&'static str>,
+    },
+    RestartProcess {
+        pid: i32,
+        port: u16,
+        argv: Vec<String>,
+        cwd: Option<PathBuf>,
+    },
     DeleteTunnel(String),
 }
 
 impl App {
-    pub fn new() -> Result<Self> {
-        let tunnel_manager = TunnelManager::load()?;
+    pub fn new(options: &Options, settings: Settings) -> Result<Self> {
+        let tunnel_manager = TunnelManager::load(&options.profile)?;
+        Ok(Self::with_tunnels(options, settings, tunnel_manager))
+    }
 
-        Ok(Self {
+    /// Build the app around an already loaded tunnel set.
+    fn with_tunnels(options: &Options, settings: Settings, tunnel_manager: TunnelManager) -> Self {
+        let mut app = Self {
             current_tab: AppTab::Ports,
             ports: Vec::new(),
             filtered_ports: Vec::new(),
+            dual_stack_twins: HashMap::new(),
+            dual_stack_merged: HashSet::new(),
+            port_rows: Vec::new(),
+            port_states: HashMap::new(),
+            port_changed_at: HashMap::new(),
             port_selected: 0,
+            connections: Vec::new(),
+            connection_selected: 0,
+            port_offset: std::cell::Cell::new(0),
+            tunnel_offset: std::cell::Cell::new(0),
+            connection_offset: std::cell::Cell::new(0),
+            page_rows: std::cell::Cell::new(1),
+            tabs_area: std::cell::Cell::new(Rect::default()),
+            table_area: std::cell::Cell::new(Rect::default()),
+            last_click: None,
+            show_port_details: false,
+            port_details: std::cell::RefCell::new(None),
+            socket_stats: settings.socket_stats,
+            socket_count_column: settings.socket_count_column,
+            connection_count_column: settings.connection_count_column,
+            age_column: settings.age_column,
+            merge_dual_stack: settings.merge_dual_stack,
+            mine_only: false,
+            proto_filter: None,
+            state_filter: None,
+            scope_filter: None,
+            group_by_process: false,
+            selected_ports: HashSet::new(),
+            conflicting_ports: HashSet::new(),
+            conflict_count: 0,
+            sort_key: settings.port_sort,
+            sort_ascending: settings.port_sort_ascending,
+            expanded_processes: HashSet::new(),
+            current_user: crate::port::current_user(),
+            privileged: options.sudo,
+            scan_problem: None,
+            scanned_at: 0,
+            port_backend: settings.port_backend.unwrap_or_else(PortBackend::detect),
+            exposed_patterns: settings.exposed_services,
+            exposed: Vec::new(),
+            exposed_dismissed: false,
+            notifier: Notifier::new(settings.notifications),
+            clipboard: Clipboard::default(),
+            tunnel_up: HashMap::new(),
+            last_tunnel_watch: None,
             tunnel_manager,
+            profile: options.profile.clone(),
+            filtered_tunnels: Vec::new(),
             tunnel_selected: 0,
+            show_tunnel_details: false,
+            host_resolver: HostResolver::new(),
+            ssh_binary: settings.ssh_binary,
+            tunnel_colors: settings.tunnel_colors,
+            monochrome: settings.monochrome || std::env::var_os("NO_COLOR").is_some(),
+            theme: settings.theme,
             show_filter: false,
+            show_search: false,
+            search_text: String::new(),
+            search_origin: 0,
             filter_text: String::new(),
+            tunnel_filter_text: String::new(),
+            shared_filter: settings.shared_filter,
             show_input: false,
             input_mode: InputMode::None,
             input_prompt: String::new(),
             input_buffer: String::new(),
+            input_cursor: 0,
             show_confirm: false,
             confirm_message: String::new(),
             pending_action: None,
+            confirm_expected: None,
+            confirm_reason: String::new(),
+            confirm_focus_yes: false,
+            confirm_input: String::new(),
+            protected_processes: settings.protected_processes,
+            protect_system_processes: settings.protect_system_processes,
+            batch_confirm_threshold: settings.batch_confirm_threshold,
+            kill_grace: Duration::from_secs(settings.kill_grace_secs),
+            kill_log: Vec::new(),
+            show_kill_log: false,
+            command_preview: None,
+            tunnel_test: None,
+            log_view: None,
+            signal_picker: None,
             new_tunnel: None,
+            tunnel_form: None,
+            editing_tunnel: None,
+            duplicating_tunnel: None,
+            tunnel_sort: settings.tunnel_sort,
+            recent_hosts: settings.recent_hosts,
+            host_suggestion: None,
+            auto_start: None,
+            reconnects: Reconnects::new(),
+            tunnel_jobs: TunnelJobs::new(),
+            // Below ~10ms the loop would mostly spin on lsof calls
+            tick_ms: options.tick_ms.unwrap_or(settings.tick_ms).max(10),
+            auto_refresh: settings.auto_refresh,
+            refresh_interval: Duration::from_secs(settings.refresh_interval_secs.max(1)),
+            last_refresh: Instant::now(),
+            pending_scan: None,
+            pending_kill: None,
+            pending_restart: None,
+            show_help: false,
+            help_scroll: 0,
             status_message: String::from("Press ? for help"),
-        })
+            read_only: options.read_only || settings.read_only,
+            mouse_capture: settings.mouse_capture && !options.no_mouse,
+        };
+        app.apply_tunnel_filter();
+        app
     }
 
-    pub fn refresh_ports(&mut self) -> Result<()> {
-        self.ports = crate::port::get_listening_ports()?;
-        self.apply_filter();
-        self.status_message = format!("Found {} ports", self.ports.len());
-        Ok(())
-    }
+    /// Return to the tab and rows saved by a previous session, skipping anything that is gone.
+    pub fn restore_state(&mut self, state: &UiState) {
+        self.set_tab(state.tab);
 
-    pub fn apply_filter(&mut self) {
-        if self.filter_text.is_empty() {
-            self.filtered_ports = self.ports.clone();
-        } else {
-            let filter_lower = self.filter_text.to_lowercase();
-            self.filtered_ports = self.ports
-                .iter()
-                .filter(|p| {
-                    p.port.to_string().contains(&filter_lower)
-                        || p.process_name.to_lowercase().contains(&filter_lower)
-                        || p.pid.to_string().contains(&filter_lower)
+        // The same process if it's still running, else whatever now runs under its name
+        let port_index = state.selected_port.as_ref().and_then(|(port, process_name)| {
+            self.shown_ports()
+                .position(|p| p.port == *port && Some(p.pid) == state.selected_pid)
+                .or_else(|| {
+                    self.shown_ports()
+                        .position(|p| p.port == *port && &p.process_name == process_name)
                 })
-                .cloned()
-                .collect();
+        });
+        if let Some(index) = port_index {
+            self.port_selected = self.row_for_port(index);
         }
 
-        // Adjust selection
-        if self.port_selected >= self.filtered_ports.len() && !self.filtered_ports.is_empty() {
-            self.port_selected = self.filtered_ports.len() - 1;
+        if let Some(name) = &state.selected_tunnel
+            && let Some(index) = self
+                .filtered_tunnels
+                .iter()
+                .position(|&i| &self.tunnel_manager.tunnels[i].name == name)
+        {
+            self.tunnel_selected = index;
         }
     }
 
-    pub fn next_tab(&mut self) {
-        self.current_tab = match self.current_tab {
-            AppTab::Ports => AppTab::Tunnels,
-            AppTab::Tunnels => AppTab::Ports,
-        };
+    pub fn ui_state(&self) -> UiState {
+        UiState {
+            tab: self.current_tab,
+            selected_port: self
+                .selected_port()
+                .map(|p| (p.port, p.process_name.clone())),
+            selected_pid: self.selected_port().map(|p| p.pid),
+            selected_tunnel: self.selected_tunnel().map(|t| t.name.clone()),
+        }
     }
 
-    pub fn prev_tab(&mut self) {
-        self.next_tab();
+    /// Scan now, waiting for the result; for actions whose next step needs fresh ports.
+    pub fn refresh_ports(&mut self) -> Result<()> {
+        // Anything still running started earlier and would overwrite this with older data
+        self.pending_scan = None;
+        self.last_refresh = Instant::now();
+        let scan = scan_ports(self.port_backend, self.privileged, self.socket_stats)?;
+        let selected = self.selected_port().map(port_key);
+        self.apply_scan(scan);
+        self.reselect_port(selected);
+        Ok(())
     }
 
-    pub fn select_next(&mut self) {
-        match self.current_tab {
-            AppTab::Ports => {
-                if !self.filtered_ports.is_empty() {
-                    self.port_selected = (self.port_selected + 1) % self.filtered_ports.len();
-                }
-            }
-            AppTab::Tunnels => {
-                let len = self.tunnel_manager.tunnels.len();
-                if len > 0 {
-                    self.tunnel_selected = (self.tunnel_selected + 1) % len;
-                }
-            }
+    /// Scan on a worker thread so the UI keeps drawing; joins a scan already running.
+    pub fn start_refresh(&mut self, quiet: bool) {
+        if let Some(pending) = &mut self.pending_scan {
+            // Coalesce: the scan in flight answers this request too
+            pending.quiet &= quiet;
+            return;
         }
+
+        self.last_refresh = Instant::now();
+        let (tx, rx) = mpsc::channel();
+        let (backend, privileged, socket_stats) =
+            (self.port_backend, self.privileged, self.socket_stats);
+        std::thread::spawn(move || {
+            let _ = tx.send(scan_ports(backend, privileged, socket_stats));
+        });
+        self.pending_scan = Some(PendingScan {
+            results: rx,
+            quiet,
+            started: Instant::now(),
+        });
     }
 
-    pub fn select_prev(&mut self) {
-        match self.current_tab {
-            AppTab::Ports => {
-                if !self.filtered_ports.is_empty() {
-                    self.port_selected = if self.port_selected == 0 {
-                        self.filtered_ports.len() - 1
-                    } else {
-                        self.port_selected - 1
-                    };
-                }
-            }
-            AppTab::Tunnels => {
-                let len = self.tunnel_manager.tunnels.len();
-                if len > 0 {
-                    self.tunnel_selected = if self.tunnel_selected == 0 {
-                        len - 1
-                    } else {
-                        self.tunnel_selected - 1
-                    };
-                }
+    /// Whether a background scan is running, and for how long.
+    pub fn scanning(&self) -> Option<Duration> {
+        self.pending_scan.as_ref().map(|pending| pending.started.elapsed())
+    }
+
+    /// Apply a finished background scan, keeping the cursor on the same socket.
+    pub fn collect_scan(&mut self) {
+        // Rows shifting under a dialog would change what it acts on
+        if self.dialog_open() {
+            return;
+        }
+        let Some(pending) = &self.pending_scan else {
+            return;
+        };
+        let result = match pending.results.try_recv() {
+            Ok(result) => result,
+            Err(mpsc::TryRecvError::Empty) => return,
+            Err(mpsc::TryRecvError::Disconnected) => Err(anyhow::anyhow!("scan worker stopped")),
+        };
+        let quiet = pending.quiet;
+        self.pending_scan = None;
+
+        let scan = match result {
+            Ok(scan) => scan,
+            Err(e) => {
+                self.status_message = format!("Scan failed: {:#}", e);
+                return;
             }
+        };
+        // Keep whatever the user was last told rather than "Found N ports" every few seconds
+        let status = std::mem::take(&mut self.status_message);
+        let selected = self.selected_port().map(port_key);
+        let privileged = self.privileged;
+        self.apply_scan(scan);
+        self.reselect_port(selected);
+        // Dropping out of privileged mode is always worth saying
+        if quiet && self.privileged == privileged {
+            self.status_message = status;
         }
     }
 
-    pub fn select_first(&mut self) {
-        match self.current_tab {
-            AppTab::Ports => self.port_selected = 0,
-            AppTab::Tunnels => self.tunnel_selected = 0,
+    /// Move the cursor back to the socket it was on before a rescan re-sorted the rows.
+    ///
+    /// When that socket is gone the cursor stays clamped where it was, so the next
+    /// keypress never lands on whatever process slid into its row.
+    fn reselect_port(&mut self, selected: Option<PortKey>) {
+        let Some(key) = selected else {
+            return;
+        };
+        if self.current_tab == AppTab::Connections {
+            if let Some(index) = find_port(self.shown_connections(), &key) {
+                self.connection_selected = index;
+            }
+        } else if let Some(index) = find_port(self.shown_ports(), &key) {
+            self.port_selected = self.row_for_port(index);
         }
     }
 
-    pub fn select_last(&mut self) {
-        match self.current_tab {
-            AppTab::Ports => {
-                if !self.filtered_ports.is_empty() {
-                    self.port_selected = self.filtered_ports.len() - 1;
-                }
-            }
-            AppTab::Tunnels => {
-                let len = self.tunnel_manager.tunnels.len();
-                if len > 0 {
-                    self.tunnel_selected = len - 1;
-                }
+    /// Replace the port list with a finished scan and update everything derived from it.
+    fn apply_scan(&mut self, scan: crate::port::Scan) {
+        // The process may have exited or its PID been reused since it was looked up
+        self.port_details.get_mut().take();
+        let first_scan = self.ports.is_empty();
+        self.ports = scan.ports;
+        self.dual_stack_twins = crate::port::dual_stack_twins(&self.ports);
+        self.scan_problem = scan.problem;
+        self.scanned_at = crate::port::unix_now();
+        self.track_port_changes(first_scan);
+        // Marks on sockets that have gone away would inflate a bulk kill
+        let live: HashSet<(i32, u16)> = self.ports.iter().map(|p| (p.pid, p.port)).collect();
+        self.selected_ports.retain(|key| live.contains(key));
+        // From the whole scan, so a conflict stays flagged when the filter hides one side
+        let conflicts = crate::port::find_conflicts(&self.ports);
+        self.conflict_count = conflicts.len();
+        self.conflicting_ports = conflicts
+            .values()
+            .flatten()
+            .map(|&i| (self.ports[i].pid, self.ports[i].port))
+            .collect();
+        self.apply_filter();
+
+        // A dismissed warning comes back only if the set of exposed services changes
+        let exposed = crate::port::exposed_services(&self.ports, &self.exposed_patterns);
+        if exposed != self.exposed {
+            // Services already exposed at startup were never a transition
+            let appeared = exposed.iter().filter(|s| !first_scan && !self.exposed.contains(s));
+            for service in appeared {
+                self.notifier
+                    .send(service, "Service exposed on all interfaces", service);
             }
+            self.exposed = exposed;
+            self.exposed_dismissed = false;
         }
-    }
 
-    pub fn request_kill(&mut self) -> Result<()> {
-        if let Some(port) = self.filtered_ports.get(self.port_selected) {
-            self.confirm_message = format!(
-                "Kill process '{}' (PID {}) on port {}?",
-                port.process_name, port.pid, port.port
+        self.status_message = match &self.scan_problem {
+            // First line only; the rest is usually more of the same
+            Some(problem) => format!(
+                "Found {} ports (partial scan: {})",
+                self.ports.len(),
+                problem.lines().next().unwrap_or_default()
+            ),
+            None => format!("Found {} ports", self.ports.len()),
+        };
+        if let Some(reason) = scan.sudo_refused {
+            self.privileged = false;
+            self.status_message = format!(
+                "{}; scanning without sudo. Run 'sudo -v' before starting anchor, or press U",
+                reason
             );
-            self.pending_action = Some(PendingAction::KillProcess(port.pid));
-            self.show_confirm = true;
         }
-        Ok(())
     }
 
-    pub fn confirm_action(&mut self) -> Result<()> {
-        self.show_confirm = false;
+    /// Whether a prompt, confirmation or popup is over the tables.
+    pub fn dialog_open(&self) -> bool {
+        self.show_input
+            || self.show_filter
+            || self.show_search
+            || self.show_confirm
+            || self.tunnel_form.is_some()
+            || self.show_help
+            || self.show_kill_log
+            || self.command_preview.is_some()
+            || self.tunnel_test.is_some()
+            || self.log_view.is_some()
+            || self.signal_picker.is_some()
+    }
 
-        if let Some(action) = self.pending_action.take() {
-            match action {
-                PendingAction::KillProcess(pid) => {
-                    let output = Command::new("kill").arg("-9").arg(pid.to_string()).output()?;
+    /// Start a rescan once the interval has passed, unless auto-refresh is off or a dialog is open.
+    pub fn auto_refresh_ports(&mut self) {
+        if self.auto_refresh
+            && !self.dialog_open()
+            && self.last_refresh.elapsed() >= self.refresh_interval
+        {
+            self.start_refresh(true);
+        }
+    }
 
-                    if output.status.success() {
-                        self.status_message = format!("Killed process {}", pid);
-                        self.refresh_ports()?;
-                    } else {
-                        self.status_message = format!(
-                            "Failed to kill process: {}",
-                            String::from_utf8_lossy(&output.stderr)
-                        );
-                    }
-                }
-                PendingAction::DeleteTunnel(name) => {
-                    self.tunnel_manager.remove(&name);
-                    self.tunnel_manager.save()?;
-                    self.status_message = format!("Deleted tunnel '{}'", name);
+    pub fn toggle_auto_refresh(&mut self) {
+        self.auto_refresh = !self.auto_refresh;
+        self.last_refresh = Instant::now();
+        self.status_message = if self.auto_refresh {
+            format!("Refreshing ports every {}s", self.refresh_interval.as_secs())
+        } else {
+            String::from("Auto-refresh off; r refreshes")
+        };
+        self.save_setting("auto_refresh", serde_json::json!(self.auto_refresh));
+    }
 
-                    if self.tunnel_selected >= self.tunnel_manager.tunnels.len()
-                        && !self.tunnel_manager.tunnels.is_empty()
-                    {
-                        self.tunnel_selected = self.tunnel_manager.tunnels.len() - 1;
-                    }
-                }
-            }
-        }
+    /// Copy the selected socket (`port pid process address`) or tunnel's ssh command.
+    pub fn copy_selection(&mut self) {
+        let (what, text) = match self.current_tab {
+            AppTab::Ports | AppTab::Connections => match self.selected_port() {
+                Some(p) => (
+                    format!("port {}", p.port),
+                    format!("{} {} {} {}", p.port, p.pid, p.process_name, p.local_address),
+                ),
+                None => return,
+            },
+            AppTab::Tunnels => match self.selected_tunnel() {
+                Some(t) => (format!("ssh command for '{}'", t.name), t.shell_command()),
+                None => return,
+            },
+        };
+        self.status_message = match self.clipboard.copy_to_clipboard(&text) {
+            Ok(()) => format!("Copied {} to the clipboard", what),
+            Err(e) => format!("Clipboard unavailable: {:#}", e),
+        };
+    }
 
-        Ok(())
+    /// Open a popup with the exact ssh command `connect` would run for the selected tunnel.
+    pub fn show_command_preview(&mut self) {
+        self.command_preview = self
+            .selected_tunnel()
+            .map(|tunnel| tunnel.command_preview(&self.ssh_binary));
     }
 
-    pub fn start_add_tunnel(&mut self) {
-        self.new_tunnel = Some(TunnelConfig::default());
-        self.input_mode = InputMode::TunnelName;
-        self.input_prompt = String::from("Tunnel name:");
-        self.input_buffer.clear();
-        self.show_input = true;
+    /// Check the selected tunnel's login and remote target on a worker thread, without
+    /// bringing its forward up.
+    pub fn start_tunnel_test(&mut self) {
+        let Some(tunnel) = self.selected_tunnel().cloned() else {
+            return;
+        };
+        let ssh_binary = self.ssh_binary.clone();
+        let (tx, rx) = mpsc::channel();
+        self.tunnel_test = Some(TunnelTest {
+            tunnel: tunnel.name.clone(),
+            started: Instant::now(),
+            result: None,
+            results: rx,
+        });
+        std::thread::spawn(move || {
+            let _ = tx.send(tunnel.test(&ssh_binary));
+        });
     }
 
-    pub fn submit_input(&mut self) -> Result<()> {
-        let input = self.input_buffer.trim().to_string();
+    /// Pick up the result of a running tunnel test.
+    fn collect_tunnel_test(&mut self) {
+        let Some(test) = &mut self.tunnel_test else {
+            return;
+        };
+        if test.result.is_some() {
+            return;
+        }
+        test.result = match test.results.try_recv() {
+            Ok(result) => Some(result.map_err(|e| format!("{:#}", e))),
+            Err(mpsc::TryRecvError::Empty) => return,
+            Err(mpsc::TryRecvError::Disconnected) => Some(Err(String::from("test stopped"))),
+        };
+    }
 
-        if input.is_empty() {
-            self.status_message = String::from("Input cannot be empty");
-            return Ok(());
+    /// Open the selected tunnel's ssh log, following new output.
+    pub fn show_tunnel_log(&mut self) {
+        let Some(tunnel) = self.selected_tunnel() else {
+            return;
+        };
+        let path = match tunnel.ssh_log_path() {
+            Ok(path) => path,
+            Err(e) => {
+                self.status_message = format!("{:#}", e);
+                return;
+            }
+        };
+        self.log_view = Some(LogView {
+            tunnel: tunnel.name.clone(),
+            lines: crate::tunnel::read_ssh_log(&path, LOG_VIEW_LINES),
+            path,
+            scroll_back: 0,
+        });
+    }
+
+    /// Scroll the log viewer by `delta` lines, positive towards the end.
+    pub fn scroll_tunnel_log(&mut self, delta: i32) {
+        if let Some(view) = &mut self.log_view {
+            let back = (view.scroll_back as i64 - delta as i64)
+                .clamp(0, view.lines.len().saturating_sub(1) as i64);
+            view.scroll_back = back as usize;
         }
+    }
 
-        if let Some(ref mut tunnel) = self.new_tunnel {
-            match self.input_mode {
-                InputMode::TunnelName => {
-                    tunnel.name = input;
-                    self.input_mode = InputMode::TunnelHost;
-                    self.input_prompt = String::from("SSH host (user@host):");
-                    self.input_buffer.clear();
-                }
-                InputMode::TunnelHost => {
-                    tunnel.ssh_host = input;
-                    self.input_mode = InputMode::TunnelLocalPort;
-                    self.input_prompt = String::from("Local port:");
-                    self.input_buffer.clear();
-                }
-                InputMode::TunnelLocalPort => {
-                    if let Ok(port) = input.parse::<u16>() {
-                        tunnel.local_port = port;
-                        self.input_mode = InputMode::TunnelRemotePort;
-                        self.input_prompt = String::from("Remote port (host:port):");
-                        self.input_buffer.clear();
-                    } else {
-                        self.status_message = String::from("Invalid port number");
-                    }
-                }
-                InputMode::TunnelRemotePort => {
-                    tunnel.remote_target = input;
-                    // Save the tunnel
-                    let tunnel_clone = tunnel.clone();
-                    self.tunnel_manager.add(tunnel_clone);
-                    self.tunnel_manager.save()?;
-                    self.status_message = format!("Added tunnel '{}'", tunnel.name);
-                    self.new_tunnel = None;
-                    self.show_input = false;
-                    self.input_mode = InputMode::None;
-                }
-                InputMode::None => {}
+    /// Pick up whatever ssh has written since the last tick.
+    fn reload_tunnel_log(&mut self) {
+        if let Some(view) = &mut self.log_view {
+            let lines = crate::tunnel::read_ssh_log(&view.path, LOG_VIEW_LINES);
+            // Stay on the same text while scrolled back
+            if view.scroll_back > 0 {
+                let added = lines.len().saturating_sub(view.lines.len());
+                view.scroll_back = (view.scroll_back + added).min(lines.len().saturating_sub(1));
             }
+            view.lines = lines;
         }
+    }
 
-        Ok(())
+    /// Copy the previewed ssh command and close the popup.
+    pub fn copy_command_preview(&mut self) {
+        let Some(command) = self.command_preview.take() else {
+            return;
+        };
+        self.status_message = match self.clipboard.copy_to_clipboard(&command) {
+            Ok(()) => String::from("Copied the ssh command to the clipboard"),
+            Err(e) => format!("Clipboard unavailable: {:#}", e),
+        };
     }
 
-    pub fn cancel_input(&mut self) {
-        self.show_input = false;
-        self.input_mode = InputMode::None;
-        self.new_tunnel = None;
-        self.input_buffer.clear();
+    /// Remember a toggled preference in settings.json, noting in the status bar if that failed.
+    fn save_setting(&mut self, key: &str, value: serde_json::Value) {
+        if let Err(e) = Settings::save_changes(&[(key, value)]) {
+            self.status_message.push_str(&format!(" (not saved: {:#})", e));
+        }
     }
 
-    pub fn connect_tunnel(&mut self) -> Result<()> {
-        if let Some(tunnel) = self.tunnel_manager.tunnels.get_mut(self.tunnel_selected) {
-            if tunnel.is_connected() {
-                self.status_message = format!("Tunnel '{}' is already connected", tunnel.name);
-                return Ok(());
-            }
+    /// Stamp sockets that are new or changed state since the previous scan.
+    fn track_port_changes(&mut self, first_scan: bool) {
+        let now = Instant::now();
+        let states: HashMap<PortKey, String> = self
+            .ports
+            .iter()
+            .map(|p| (port_key(p), p.state.clone()))
+            .collect();
 
-            match tunnel.connect() {
-                Ok(()) => {
-                    self.status_message = format!("Connected tunnel '{}'", tunnel.name);
-                }
-                Err(e) => {
-                    self.status_message = format!("Failed to connect: {}", e);
+        if !first_scan {
+            for (key, state) in &states {
+                if self.port_states.get(key) != Some(state) {
+                    self.port_changed_at.insert(key.clone(), now);
                 }
             }
         }
-        Ok(())
+        self.port_changed_at
+            .retain(|_, changed| now.duration_since(*changed) < CHANGE_FLASH);
+        self.port_states = states;
     }
 
-    pub fn disconnect_tunnel(&mut self) -> Result<()> {
-        if let Some(tunnel) = self.tunnel_manager.tunnels.get_mut(self.tunnel_selected) {
-            if !tunnel.is_connected() {
-                self.status_message = format!("Tunnel '{}' is not connected", tunnel.name);
-                return Ok(());
-            }
+    /// Whether the socket appeared or changed state within the flash interval.
+    pub fn port_changed_recently(&self, port: &PortInfo) -> bool {
+        self.port_changed_at
+            .get(&port_key(port))
+            .is_some_and(|changed| changed.elapsed() < CHANGE_FLASH)
+    }
 
-            match tunnel.disconnect() {
-                Ok(()) => {
-                    self.status_message = format!("Disconnected tunnel '{}'", tunnel.name);
-                }
-                Err(e) => {
-                    self.status_message = format!("Failed to disconnect: {}", e);
-                }
-            }
+    /// After the first scan, suggest sudo if so few sockets showed up that most are likely hidden.
+    pub fn offer_privileged_scan(&mut self) {
+        const FEW_PORTS: usize = 5;
+
+        if !self.privileged && self.ports.len() < FEW_PORTS && !crate::port::running_as_root() {
+            self.status_message = format!(
+                "Only {} sockets visible; root-owned ones may be hidden. Press U to rescan with sudo",
+                self.ports.len()
+            );
+        }
+    }
+
+    /// Go back to scanning as the current user.
+    pub fn disable_privileged_scan(&mut self) -> Result<()> {
+        self.privileged = false;
+        self.refresh_ports()?;
+        self.status_message = format!("Found {} ports (scanning without sudo)", self.ports.len());
+        Ok(())
+    }
+
+    /// Switch to sudo scans once `sudo -v` has cached credentials.
+    pub fn enable_privileged_scan(&mut self, authorized: bool) -> Result<()> {
+        if !authorized {
+            self.status_message = String::from("sudo was not authorized; scan is unchanged");
+            return Ok(());
+        }
+
+        self.privileged = true;
+        self.refresh_ports()?;
+        // A refused sudo has already explained itself
+        if self.privileged {
+            self.status_message = format!("Found {} ports (privileged scan)", self.ports.len());
+        }
+        Ok(())
+    }
+
+    /// Save every known socket, ignoring the filter, for a point-in-time record.
+    pub fn snapshot(&mut self) -> Result<()> {
+        self.refresh_ports()?;
+
+        let path = crate::port::default_snapshot_name();
+        self.status_message = match crate::port::write_snapshot(path.as_ref(), &self.ports) {
+            Ok(()) => format!("Saved {} connections to {}", self.ports.len(), path),
+            Err(e) => format!("Failed to save snapshot: {}", e),
+        };
+        Ok(())
+    }
+
+    /// What to show and export when dropping into a shell for the selected port or tunnel.
+    pub fn shell_handoff(&self) -> Option<ShellHandoff> {
+        match self.current_tab {
+            AppTab::Ports | AppTab::Connections => {
+                let port = self.selected_port()?;
+                let mut lines = vec![format!(
+                    "Process '{}' (PID {}) on port {}",
+                    port.process_name, port.pid, port.port
+                )];
+                if let Some(command) = crate::port::process_command_line(port.pid) {
+                    lines.push(format!("Command: {}", command));
+                }
+                lines.push(format!("Try: lsof -p $ANCHOR_PID  (PID {})", port.pid));
+                Some(ShellHandoff {
+                    lines,
+                    env: vec![
+                        ("ANCHOR_PID", port.pid.to_string()),
+                        ("ANCHOR_PORT", port.port.to_string()),
+                    ],
+                })
+            }
+            AppTab::Tunnels => {
+                let tunnel = self.selected_tunnel()?;
+                Some(ShellHandoff {
+                    lines: vec![
+                        format!("Tunnel '{}' {}", tunnel.name, tunnel.forward_display()),
+                        format!("Start it by hand: {}", tunnel.shell_command()),
+                    ],
+                    env: vec![
+                        ("ANCHOR_TUNNEL", tunnel.name.clone()),
+                        ("ANCHOR_SSH_HOST", tunnel.ssh_host.clone()),
+                    ],
+                })
+            }
+        }
+    }
+
+    pub fn apply_filter(&mut self) {
+        let filter_lower = self.filter_text.to_lowercase();
+        let mut shown: Vec<bool> = self
+            .ports
+            .iter()
+            .map(|p| {
+                (filter_lower.is_empty() || port_matches(p, &filter_lower))
+                    && (!self.mine_only || p.user == self.current_user)
+                    && self.proto_filter.as_ref().is_none_or(|protocol| &p.protocol == protocol)
+                    && self.state_filter.as_ref().is_none_or(|state| &p.state == state)
+                    && self.scope_filter.is_none_or(|scope| p.scope == scope)
+            })
+            .collect();
+
+        // Only twins that both survive the filter fold into one row
+        self.dual_stack_merged.clear();
+        if self.merge_dual_stack {
+            for (&v4, &v6) in &self.dual_stack_twins {
+                if shown[v4] && shown[v6] {
+                    shown[v6] = false;
+                    self.dual_stack_merged.insert(v4);
+                }
+            }
+        }
+        self.filtered_ports.clear();
+        self.filtered_ports
+            .extend(shown.iter().enumerate().filter(|(_, shown)| **shown).map(|(i, _)| i));
+
+        // Stable, so ties stay in port order from the scan
+        let (key, ascending) = (self.sort_key, self.sort_ascending);
+        let ports = &self.ports;
+        self.filtered_ports.sort_by(|&a, &b| {
+            if ascending {
+                key.compare(&ports[a], &ports[b])
+            } else {
+                key.compare(&ports[b], &ports[a])
+            }
+        });
+
+        self.build_port_rows();
+
+        // Adjust selection, back to the top when nothing matches
+        self.port_selected = self
+            .port_selected
+            .min(self.port_rows.len().saturating_sub(1));
+
+        // The Connections tab shares the text filter and mine-only, but not the quick toggles
+        let filter_lower = self.filter_text.to_lowercase();
+        self.connections = self
+            .ports
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| p.state == "ESTABLISHED" && port_matches(p, &filter_lower))
+            .filter(|(_, p)| !self.mine_only || p.user == self.current_user)
+            .map(|(i, _)| i)
+            .collect();
+        self.connection_selected = self
+            .connection_selected
+            .min(self.connections.len().saturating_sub(1));
+    }
+
+    /// Lay out `filtered_ports` as flat rows, or grouped under one header per process.
+    fn build_port_rows(&mut self) {
+        if !self.group_by_process {
+            self.port_rows = (0..self.filtered_ports.len()).map(PortRow::Socket).collect();
+            return;
+        }
+
+        self.port_rows.clear();
+        for group in crate::port::group_by_process(self.shown_ports()) {
+            let children = if self.expanded_processes.contains(&group.pid) {
+                group.sockets.clone()
+            } else {
+                Vec::new()
+            };
+            self.port_rows.push(PortRow::Process {
+                pid: group.pid,
+                sockets: group.sockets,
+            });
+            self.port_rows.extend(children.into_iter().map(PortRow::Socket));
+        }
+    }
+
+    /// The socket under the cursor, or the connection on that tab; a process header stands
+    /// for its first socket.
+    pub fn selected_port(&self) -> Option<&PortInfo> {
+        if self.current_tab == AppTab::Connections {
+            return self.connections.get(self.connection_selected).map(|&i| &self.ports[i]);
+        }
+        self.filtered_ports
+            .get(self.selected_port_index()?)
+            .map(|&i| &self.ports[i])
+    }
+
+    /// The socket on the `index`th line of the flat ports table.
+    pub fn filtered_port(&self, index: usize) -> &PortInfo {
+        &self.ports[self.filtered_ports[index]]
+    }
+
+    /// Sockets shown in the ports table, in display order.
+    pub fn shown_ports(&self) -> impl Iterator<Item = &PortInfo> + Clone {
+        self.filtered_ports.iter().map(|&i| &self.ports[i])
+    }
+
+    /// Established sockets shown in the Connections tab, in display order.
+    pub fn shown_connections(&self) -> impl Iterator<Item = &PortInfo> + Clone {
+        self.connections.iter().map(|&i| &self.ports[i])
+    }
+
+    /// IPv6 address folded into the `index`th line by the dual-stack merge, if any.
+    pub fn dual_stack_peer(&self, index: usize) -> Option<&str> {
+        let v4 = self.filtered_ports[index];
+        if !self.dual_stack_merged.contains(&v4) {
+            return None;
+        }
+        Some(&self.ports[*self.dual_stack_twins.get(&v4)?].local_address)
+    }
+
+    /// The displayed row showing `filtered_ports[index]`, or its process header if collapsed.
+    fn row_for_port(&self, index: usize) -> usize {
+        self.port_rows
+            .iter()
+            .position(|row| *row == PortRow::Socket(index))
+            .or_else(|| {
+                self.port_rows.iter().position(|row| {
+                    matches!(row, PortRow::Process { sockets, .. } if sockets.contains(&index))
+                })
+            })
+            .unwrap_or(0)
+    }
+
+    /// Index into `filtered_ports` of the socket under the cursor.
+    fn selected_port_index(&self) -> Option<usize> {
+        match self.port_rows.get(self.port_selected)? {
+            PortRow::Socket(i) => Some(*i),
+            PortRow::Process { sockets, .. } => sockets.first().copied(),
+        }
+    }
+
+    pub fn toggle_group_by_process(&mut self) {
+        let selected = self.selected_port_index();
+        self.group_by_process = !self.group_by_process;
+        self.build_port_rows();
+        self.port_selected = selected.map_or(0, |i| self.row_for_port(i));
+        self.status_message = if self.group_by_process {
+            String::from("Grouping sockets by process (Enter expands)")
+        } else {
+            String::from("Showing one row per socket")
+        };
+    }
+
+    /// Command line, cwd and user of the selected row's process, looked up once per PID.
+    ///
+    /// The inner `None` means the process exited after the scan listed it.
+    pub fn selected_process_details(&self) -> Option<(i32, Option<ProcessDetails>)> {
+        let pid = self.selected_port()?.pid;
+        let mut cached = self.port_details.borrow_mut();
+        if cached.as_ref().is_none_or(|(cached_pid, _)| *cached_pid != pid) {
+            *cached = Some((pid, crate::port::process_details(pid)));
+        }
+        cached.clone()
+    }
+
+    /// Expand or collapse the process under the cursor in the grouped view.
+    pub fn toggle_process_expanded(&mut self) {
+        if !self.group_by_process {
+            return;
+        }
+        let Some(pid) = self.selected_port().map(|p| p.pid) else {
+            return;
+        };
+
+        if !self.expanded_processes.remove(&pid) {
+            self.expanded_processes.insert(pid);
+        }
+        self.build_port_rows();
+        self.port_selected = self
+            .port_rows
+            .iter()
+            .position(|row| matches!(row, PortRow::Process { pid: p, .. } if *p == pid))
+            .unwrap_or(0);
+    }
+
+    pub fn apply_tunnel_filter(&mut self) {
+        let filter_lower = self.tunnel_filter_text.to_lowercase();
+        self.filtered_tunnels = self
+            .tunnel_manager
+            .tunnels
+            .iter()
+            .enumerate()
+            .filter(|(_, t)| tunnel_matches(t, &filter_lower))
+            .map(|(i, _)| i)
+            .collect();
+
+        let tunnels = &self.tunnel_manager.tunnels;
+        match self.tunnel_sort {
+            TunnelSort::Saved => {}
+            TunnelSort::Status => {
+                // Checking status may spawn `kill -0`, so do it once per tunnel rather than per comparison
+                self.filtered_tunnels
+                    .sort_by_cached_key(|&i| !tunnels[i].is_connected());
+            }
+            TunnelSort::Name => {
+                self.filtered_tunnels
+                    .sort_by_cached_key(|&i| tunnels[i].name.to_lowercase());
+            }
+        }
+
+        // Adjust selection
+        if self.tunnel_selected >= self.filtered_tunnels.len() {
+            self.tunnel_selected = self.filtered_tunnels.len().saturating_sub(1);
+        }
+    }
+
+    /// Re-apply filter and sort, keeping the same tunnel selected.
+    fn resort_tunnels(&mut self) {
+        let selected = self.selected_tunnel_index();
+        self.apply_tunnel_filter();
+        if let Some(position) = selected
+            .and_then(|index| self.filtered_tunnels.iter().position(|&i| i == index))
+        {
+            self.tunnel_selected = position;
+        }
+    }
+
+    pub fn cycle_theme(&mut self) {
+        self.theme = self.theme.next();
+        self.status_message = format!("Theme: {}", self.theme.label());
+        self.save_setting("theme", serde_json::json!(self.theme));
+    }
+
+    pub fn cycle_tunnel_sort(&mut self) {
+        self.tunnel_sort = self.tunnel_sort.next();
+        self.resort_tunnels();
+        self.status_message = format!("Sorting tunnels by {}", self.tunnel_sort.label());
+        self.save_setting("tunnel_sort", serde_json::json!(self.tunnel_sort));
+    }
+
+    /// Index into `tunnel_manager.tunnels` of the selected row.
+    fn selected_tunnel_index(&self) -> Option<usize> {
+        self.filtered_tunnels.get(self.tunnel_selected).copied()
+    }
+
+    pub fn selected_tunnel(&self) -> Option<&TunnelConfig> {
+        self.selected_tunnel_index()
+            .map(|i| &self.tunnel_manager.tunnels[i])
+    }
+
+    /// Filter text of the current tab.
+    pub fn active_filter(&self) -> &str {
+        match self.current_tab {
+            AppTab::Ports | AppTab::Connections => &self.filter_text,
+            AppTab::Tunnels => &self.tunnel_filter_text,
+        }
+    }
+
+    fn edit_filter(&mut self, edit: impl FnOnce(&mut String)) {
+        match self.current_tab {
+            AppTab::Ports | AppTab::Connections => {
+                edit(&mut self.filter_text);
+                self.apply_filter();
+            }
+            AppTab::Tunnels => {
+                edit(&mut self.tunnel_filter_text);
+                self.apply_tunnel_filter();
+            }
+        }
+    }
+
+    pub fn start_filter(&mut self) {
+        self.show_filter = true;
+        self.edit_filter(String::clear);
+    }
+
+    pub fn filter_push(&mut self, c: char) {
+        self.edit_filter(|text| text.push(c));
+    }
+
+    pub fn filter_pop(&mut self) {
+        self.edit_filter(|text| {
+            text.pop();
+        });
+    }
+
+    pub fn clear_filter(&mut self) {
+        self.edit_filter(String::clear);
+    }
+
+    pub fn start_search(&mut self) {
+        self.show_search = true;
+        self.search_text.clear();
+        self.search_origin = self.selected_row();
+    }
+
+    pub fn search_push(&mut self, c: char) {
+        self.search_text.push(c);
+        self.search_incrementally();
+    }
+
+    pub fn search_pop(&mut self) {
+        self.search_text.pop();
+        self.search_incrementally();
+    }
+
+    /// Re-run the search from where the prompt opened, as the query changes.
+    fn search_incrementally(&mut self) {
+        if self.search_text.is_empty() {
+            self.set_selected_row(self.search_origin);
+            return;
+        }
+        let found = self.find_match(self.search_origin, true, true);
+        self.report_search(found);
+    }
+
+    /// Close the search prompt; cancelling returns to where it started and drops the highlight.
+    pub fn close_search(&mut self, keep: bool) {
+        self.show_search = false;
+        if !keep {
+            self.search_text.clear();
+            self.set_selected_row(self.search_origin);
+        }
+    }
+
+    /// Jump to the next match after the selection, or the previous one before it (`n`/`N`).
+    pub fn search_next(&mut self, forward: bool) {
+        if self.search_text.is_empty() {
+            self.status_message = String::from("No search; / starts one");
+            return;
+        }
+        let found = self.find_match(self.selected_row(), forward, false);
+        self.report_search(found);
+    }
+
+    /// Whether a search is highlighting and `port` matches it.
+    pub fn search_hit_port(&self, port: &PortInfo) -> bool {
+        !self.search_text.is_empty() && port_matches(port, &self.search_text.to_lowercase())
+    }
+
+    /// Whether a search is highlighting and `tunnel` matches it.
+    pub fn search_hit_tunnel(&self, tunnel: &TunnelConfig) -> bool {
+        !self.search_text.is_empty() && tunnel_matches(tunnel, &self.search_text.to_lowercase())
+    }
+
+    /// Whether a row of the current tab matches the search; a process header matches
+    /// when any of its sockets does.
+    fn row_matches_search(&self, row: usize) -> bool {
+        match self.current_tab {
+            AppTab::Ports => match &self.port_rows[row] {
+                PortRow::Socket(i) => self.search_hit_port(self.filtered_port(*i)),
+                PortRow::Process { sockets, .. } => sockets
+                    .iter()
+                    .any(|&i| self.search_hit_port(self.filtered_port(i))),
+            },
+            AppTab::Tunnels => {
+                self.search_hit_tunnel(&self.tunnel_manager.tunnels[self.filtered_tunnels[row]])
+            }
+            AppTab::Connections => self.search_hit_port(&self.ports[self.connections[row]]),
+        }
+    }
+
+    /// The nearest matching row from `start` in either direction, wrapping round the ends;
+    /// `start` itself only counts when `inclusive`.
+    fn find_match(&self, start: usize, forward: bool, inclusive: bool) -> Option<usize> {
+        let len = self.row_count();
+        if len == 0 {
+            return None;
+        }
+        let start = start.min(len - 1);
+        let first = if inclusive { 0 } else { 1 };
+        (first..len + first)
+            .map(|step| {
+                if forward {
+                    (start + step) % len
+                } else {
+                    (start + len - step % len) % len
+                }
+            })
+            .find(|&row| self.row_matches_search(row))
+    }
+
+    fn report_search(&mut self, found: Option<usize>) {
+        match found {
+            Some(row) => {
+                self.set_selected_row(row);
+                self.status_message = format!("/{}", self.search_text);
+            }
+            None => self.status_message = format!("No match for '{}'", self.search_text),
+        }
+    }
+
+    fn row_count(&self) -> usize {
+        match self.current_tab {
+            AppTab::Ports => self.port_rows.len(),
+            AppTab::Tunnels => self.filtered_tunnels.len(),
+            AppTab::Connections => self.connections.len(),
+        }
+    }
+
+    fn selected_row(&self) -> usize {
+        match self.current_tab {
+            AppTab::Ports => self.port_selected,
+            AppTab::Tunnels => self.tunnel_selected,
+            AppTab::Connections => self.connection_selected,
+        }
+    }
+
+    fn set_selected_row(&mut self, row: usize) {
+        let row = row.min(self.row_count().saturating_sub(1));
+        match self.current_tab {
+            AppTab::Ports => self.port_selected = row,
+            AppTab::Tunnels => self.tunnel_selected = row,
+            AppTab::Connections => self.connection_selected = row,
+        }
+    }
+
+    /// Switch tabs, carrying the filter over when it's shared between them.
+    pub fn set_tab(&mut self, tab: AppTab) {
+        if self.shared_filter && tab != self.current_tab {
+            let text = self.active_filter().to_string();
+            self.current_tab = tab;
+            self.edit_filter(|filter| *filter = text);
+        } else {
+            self.current_tab = tab;
+        }
+    }
+
+    pub fn toggle_help(&mut self) {
+        self.show_help = !self.show_help;
+        self.help_scroll = 0;
+    }
+
+    /// Scroll the help overlay by `delta` lines, staying within `max_scroll`.
+    pub fn scroll_help(&mut self, delta: i32, max_scroll: u16) {
+        let scrolled = (self.help_scroll as i32 + delta).clamp(0, max_scroll as i32);
+        self.help_scroll = scrolled as u16;
+    }
+
+    pub fn toggle_kill_log(&mut self) {
+        self.show_kill_log = !self.show_kill_log;
+    }
+
+    pub fn toggle_mine_only(&mut self) {
+        self.mine_only = !self.mine_only;
+        self.apply_filter();
+        self.status_message = if self.mine_only {
+            format!("Showing only processes owned by {}", self.current_user)
+        } else {
+            String::from("Showing processes of all users")
+        };
+    }
+
+    /// Whether this socket's process shares its port with another process.
+    pub fn port_conflicted(&self, port: &PortInfo) -> bool {
+        port.state != "ESTABLISHED" && self.conflicting_ports.contains(&(port.pid, port.port))
+    }
+
+    /// Cycle the protocol filter: TCP, UDP, then all.
+    pub fn cycle_proto_filter(&mut self) {
+        self.proto_filter = next_filter(self.proto_filter.take(), &["TCP", "UDP"]);
+        self.resort_ports();
+        self.status_message = match &self.proto_filter {
+            Some(protocol) => format!("Showing only {} sockets", protocol),
+            None => String::from("Showing all protocols"),
+        };
+    }
+
+    /// Cycle the state filter: LISTEN, ESTABLISHED, then all.
+    pub fn cycle_state_filter(&mut self) {
+        self.state_filter = next_filter(self.state_filter.take(), &["LISTEN", "ESTABLISHED"]);
+        self.resort_ports();
+        self.status_message = match &self.state_filter {
+            Some(state) => format!("Showing only {} sockets", state),
+            None => String::from("Showing all states"),
+        };
+    }
+
+    /// Cycle the bind scope filter: all interfaces, loopback, specific addresses, then all.
+    pub fn cycle_scope_filter(&mut self) {
+        self.scope_filter = match self.scope_filter {
+            None => Some(BindScope::AllInterfaces),
+            Some(BindScope::AllInterfaces) => Some(BindScope::Loopback),
+            Some(BindScope::Loopback) => Some(BindScope::Specific),
+            Some(BindScope::Specific) => None,
+        };
+        self.resort_ports();
+        self.status_message = match self.scope_filter {
+            Some(scope) => format!("Showing only sockets bound to {}", scope.label()),
+            None => String::from("Showing all bind addresses"),
+        };
+    }
+
+    /// Re-apply filter and sort, keeping the same socket selected.
+    fn resort_ports(&mut self) {
+        let selected = self.selected_port().map(port_key);
+        self.apply_filter();
+        if let Some(index) =
+            selected.and_then(|key| self.shown_ports().position(|p| port_key(p) == key))
+        {
+            self.port_selected = self.row_for_port(index);
+        }
+    }
+
+    pub fn cycle_port_sort(&mut self) {
+        self.sort_key = self.sort_key.next();
+        self.resort_ports();
+        self.status_message = format!("Sorting ports by {}", self.sort_key.column());
+        self.save_setting("port_sort", serde_json::json!(self.sort_key));
+    }
+
+    pub fn toggle_port_sort_direction(&mut self) {
+        self.sort_ascending = !self.sort_ascending;
+        self.resort_ports();
+        self.status_message = format!(
+            "Sorting ports by {} ({})",
+            self.sort_key.column(),
+            if self.sort_ascending { "ascending" } else { "descending" }
+        );
+        self.save_setting("port_sort_ascending", serde_json::json!(self.sort_ascending));
+    }
+
+    pub fn toggle_dual_stack_merge(&mut self) {
+        self.merge_dual_stack = !self.merge_dual_stack;
+        self.apply_filter();
+        self.status_message = if self.merge_dual_stack {
+            String::from("Merging IPv4/IPv6 listeners into dual-stack rows")
+        } else {
+            String::from("Showing IPv4 and IPv6 listeners separately")
+        };
+        self.save_setting("merge_dual_stack", serde_json::json!(self.merge_dual_stack));
+    }
+
+    pub fn next_tab(&mut self) {
+        self.set_tab(match self.current_tab {
+            AppTab::Ports => AppTab::Tunnels,
+            AppTab::Tunnels => AppTab::Connections,
+            AppTab::Connections => AppTab::Ports,
+        });
+    }
+
+    pub fn prev_tab(&mut self) {
+        self.set_tab(match self.current_tab {
+            AppTab::Ports => AppTab::Connections,
+            AppTab::Tunnels => AppTab::Ports,
+            AppTab::Connections => AppTab::Tunnels,
+        });
+    }
+
+    pub fn select_next(&mut self) {
+        match self.current_tab {
+            AppTab::Ports => {
+                if !self.port_rows.is_empty() {
+                    self.port_selected = (self.port_selected + 1) % self.port_rows.len();
+                }
+            }
+            AppTab::Tunnels => {
+                let len = self.filtered_tunnels.len();
+                if len > 0 {
+                    self.tunnel_selected = (self.tunnel_selected + 1) % len;
+                }
+            }
+            AppTab::Connections => {
+                let len = self.connections.len();
+                if len > 0 {
+                    self.connection_selected = (self.connection_selected + 1) % len;
+                }
+            }
+        }
+    }
+
+    pub fn select_prev(&mut self) {
+        match self.current_tab {
+            AppTab::Ports => {
+                if !self.port_rows.is_empty() {
+                    self.port_selected = if self.port_selected == 0 {
+                        self.port_rows.len() - 1
+                    } else {
+                        self.port_selected - 1
+                    };
+                }
+            }
+            AppTab::Tunnels => {
+                let len = self.filtered_tunnels.len();
+                if len > 0 {
+                    self.tunnel_selected = if self.tunnel_selected == 0 {
+                        len - 1
+                    } else {
+                        self.tunnel_selected - 1
+                    };
+                }
+            }
+            AppTab::Connections => {
+                let len = self.connections.len();
+                if len > 0 {
+                    self.connection_selected = if self.connection_selected == 0 {
+                        len - 1
+                    } else {
+                        self.connection_selected - 1
+                    };
+                }
+            }
+        }
+    }
+
+    pub fn select_first(&mut self) {
+        match self.current_tab {
+            AppTab::Ports => self.port_selected = 0,
+            AppTab::Tunnels => self.tunnel_selected = 0,
+            AppTab::Connections => self.connection_selected = 0,
+        }
+    }
+
+    pub fn select_last(&mut self) {
+        match self.current_tab {
+            AppTab::Ports => {
+                if !self.port_rows.is_empty() {
+                    self.port_selected = self.port_rows.len() - 1;
+                }
+            }
+            AppTab::Tunnels => {
+                let len = self.filtered_tunnels.len();
+                if len > 0 {
+                    self.tunnel_selected = len - 1;
+                }
+            }
+            AppTab::Connections => {
+                self.connection_selected = self.connections.len().saturating_sub(1);
+            }
+        }
+    }
+
+    /// Select the clicked row of the current table; double-clicking a port asks to kill it.
+    pub fn click_row(&mut self, visible_row: usize) -> Result<()> {
+        let (offset, len) = match self.current_tab {
+            AppTab::Ports => (self.port_offset.get(), self.port_rows.len()),
+            AppTab::Tunnels => (self.tunnel_offset.get(), self.filtered_tunnels.len()),
+            AppTab::Connections => (self.connection_offset.get(), self.connections.len()),
+        };
+        let row = offset + visible_row;
+        if row >= len {
+            return Ok(());
+        }
+
+        let double_click = self
+            .last_click
+            .is_some_and(|(last, at)| last == row && at.elapsed() < DOUBLE_CLICK);
+        self.last_click = (!double_click).then(|| (row, Instant::now()));
+        match self.current_tab {
+            AppTab::Ports => {
+                self.port_selected = row;
+                if double_click {
+                    self.request_kill(true)?;
+                }
+            }
+            AppTab::Tunnels => self.tunnel_selected = row,
+            AppTab::Connections => {
+                self.connection_selected = row;
+                if double_click {
+                    self.request_kill(true)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Move the selection a screenful down (positive `pages`) or up, stopping at the ends.
+    pub fn select_page(&mut self, pages: i32) {
+        let step = self.page_rows.get().max(1) as i64 * pages as i64;
+        let (selected, len) = match self.current_tab {
+            AppTab::Ports => (&mut self.port_selected, self.port_rows.len()),
+            AppTab::Tunnels => (&mut self.tunnel_selected, self.filtered_tunnels.len()),
+            AppTab::Connections => (&mut self.connection_selected, self.connections.len()),
+        };
+        if len > 0 {
+            *selected = (*selected as i64 + step).clamp(0, len as i64 - 1) as usize;
+        }
+    }
+
+    /// Refuse a destructive action in read-only mode, explaining why in the status bar.
+    fn blocked_by_read_only(&mut self, action: &str) -> bool {
+        if self.read_only {
+            self.status_message = format!("Read-only mode: {} is disabled", action);
+        }
+        self.read_only
+    }
+
+    /// Mark or unmark the selected row for a bulk kill; a process header covers all its ports.
+    pub fn toggle_port_marked(&mut self) {
+        let keys: Vec<(i32, u16)> = match self.port_rows.get(self.port_selected) {
+            Some(PortRow::Socket(i)) => {
+                let port = self.filtered_port(*i);
+                vec![(port.pid, port.port)]
+            }
+            Some(PortRow::Process { sockets, .. }) => sockets
+                .iter()
+                .map(|&i| (self.filtered_port(i).pid, self.filtered_port(i).port))
+                .collect(),
+            None => return,
+        };
+
+        if keys.iter().all(|key| self.selected_ports.contains(key)) {
+            for key in &keys {
+                self.selected_ports.remove(key);
+            }
+        } else {
+            self.selected_ports.extend(keys);
+        }
+        self.status_message = format!("{} ports marked (K kills them all)", self.selected_ports.len());
+    }
+
+    pub fn port_marked(&self, port: &PortInfo) -> bool {
+        self.selected_ports.contains(&(port.pid, port.port))
+    }
+
+    /// Ask to kill the marked processes, or the selected one if nothing is marked.
+    ///
+    /// Sends SIGTERM first unless `graceful` is false.
+    pub fn request_kill(&mut self, graceful: bool) -> Result<()> {
+        self.request_signal(graceful, None)
+    }
+
+    /// Open the signal picker for the marked or selected processes, starting on SIGTERM.
+    pub fn open_signal_picker(&mut self) {
+        if self.blocked_by_read_only("signalling processes") {
+            return;
+        }
+        if self.selected_port().is_some() || !self.selected_ports.is_empty() {
+            self.signal_picker = Some(0);
+        }
+    }
+
+    /// Move the picker's highlight, wrapping around the ends.
+    pub fn move_signal_picker(&mut self, forward: bool) {
+        let count = crate::port::PICKER_SIGNALS.len();
+        if let Some(selected) = &mut self.signal_picker {
+            *selected = if forward {
+                (*selected + 1) % count
+            } else {
+                (*selected + count - 1) % count
+            };
+        }
+    }
+
+    /// Close the picker and ask to send the highlighted signal.
+    pub fn pick_signal(&mut self) -> Result<()> {
+        match self.signal_picker.take() {
+            Some(selected) => {
+                self.request_signal(false, Some(crate::port::PICKER_SIGNALS[selected]))
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Ask to kill the marked or selected processes, or send them `signal` if one was picked.
+    fn request_signal(&mut self, graceful: bool, signal: Option<&'static str>) -> Result<()> {
+        if self.blocked_by_read_only("killing processes") {
+            return Ok(());
+        }
+        let verb = match signal {
+            Some(signal) => format!("Send SIG{} to", signal),
+            None if graceful => String::from("Kill"),
+            None => String::from("Force kill (SIGKILL)"),
+        };
+
+        // Marks belong to the Ports tab
+        if !self.selected_ports.is_empty() && self.current_tab == AppTab::Ports {
+            // One target per process, however many of its ports are marked
+            let mut targets: Vec<KillTarget> = Vec::new();
+            for port in &self.ports {
+                if self.port_marked(port) && !targets.iter().any(|t| t.pid == port.pid) {
+                    targets.push(KillTarget {
+                        pid: port.pid,
+                        process_name: port.process_name.clone(),
+                        port: (port.port != 0).then_some(port.port),
+                    });
+                }
+            }
+
+            let mut message = format!("{} {} processes?", verb, targets.len());
+            for target in &targets {
+                message.push_str(&format!("\n{} (PID {})", target.process_name, target.pid));
+                if let Some(port) = target.port {
+                    message.push_str(&format!(" on port {}", port));
+                }
+            }
+            self.confirm_kill(targets, message, graceful, signal);
+            return Ok(());
+        }
+
+        if let Some(port) = self.selected_port() {
+            let mut message = format!(
+                "{} process '{}' (PID {}) on port {}",
+                verb,
+                port.process_name,
+                port.pid,
+                port.port
+            );
+            if !port.foreign_address.is_empty() {
+                message.push_str(&format!(" (-> {})", port.foreign_address));
+            }
+            message.push('?');
+
+            let target = KillTarget {
+                pid: port.pid,
+                process_name: port.process_name.clone(),
+                port: (port.port != 0).then_some(port.port),
+            };
+            self.confirm_kill(vec![target], message, graceful, signal);
+        }
+        Ok(())
+    }
+
+    /// Prompt for a PID to kill that isn't necessarily in the current view.
+    pub fn start_kill_pid(&mut self) {
+        if self.blocked_by_read_only("killing processes") {
+            return;
+        }
+
+        self.input_mode = InputMode::KillPid;
+        self.input_prompt = String::from("Kill PID:");
+        self.input_buffer.clear();
+        self.show_input = true;
+    }
+
+    fn submit_kill_pid(&mut self, input: &str) {
+        let Some(pid) = input.parse::<i32>().ok().filter(|pid| *pid > 0) else {
+            self.status_message = format!("'{}' is not a valid PID", input);
+            return;
+        };
+        if !crate::port::process_alive(pid) {
+            self.status_message = format!("No process with PID {}", pid);
+            return;
+        }
+
+        self.cancel_input();
+        let process_name =
+            crate::port::process_name(pid).unwrap_or_else(|| String::from("unknown"));
+        let message = format!("Kill process '{}' (PID {})?", process_name, pid);
+        let target = KillTarget {
+            pid,
+            process_name,
+            port: None,
+        };
+        self.confirm_kill(vec![target], message, true, None);
+    }
+
+    /// Open the kill confirmation, requiring typed input for protected processes or large batches.
+    fn confirm_kill(
+        &mut self,
+        targets: Vec<KillTarget>,
+        mut message: String,
+        graceful: bool,
+        signal: Option<&'static str>,
+    ) {
+        // Show the full command line so same-named processes can be told apart
+        if let [target] = targets.as_slice()
+            && let Some(command) = crate::port::process_command_line(target.pid)
+        {
+            message.push('\n');
+            message.push_str(&command);
+        }
+
+        // A big batch must not hide a protected process, so check every target
+        let protected: Vec<(&KillTarget, Protection)> = targets
+            .iter()
+            .filter_map(|t| self.protection(&t.process_name, t.pid).map(|p| (t, p)))
+            .collect();
+        let batch = targets.len() > self.batch_confirm_threshold;
+        match (protected.as_slice(), batch) {
+            ([], false) => {}
+            ([], true) => {
+                // Scale the friction with the blast radius
+                self.confirm_expected = Some(targets.len().to_string());
+                self.confirm_reason = format!("Killing {} processes", targets.len());
+            }
+            ([(_, protection)], false) => {
+                self.confirm_expected = Some(protection.answer.clone());
+                self.confirm_reason = protection.reason.clone();
+            }
+            _ => {
+                // One answer can't name several processes, so ask for `yes` and list them
+                let names: Vec<String> = protected
+                    .iter()
+                    .map(|(t, _)| format!("{} (PID {})", t.process_name, t.pid))
+                    .collect();
+                self.confirm_expected = Some(String::from(PROTECTED_CONFIRMATION));
+                self.confirm_reason = format!(
+                    "Killing {} processes, including protected {}",
+                    targets.len(),
+                    names.join(", ")
+                );
+            }
+        }
+        self.confirm_message = message;
+        self.open_confirm(PendingAction::KillProcesses {
+            targets,
+            graceful,
+            signal,
+        });
+    }
+
+    /// Show the confirmation dialog for `action`, focused on No since every action is
+    /// destructive.
+    fn open_confirm(&mut self, action: PendingAction) {
+        self.confirm_focus_yes = false;
+        self.pending_action = Some(action);
+        self.show_confirm = true;
+    }
+
+    /// Move focus between the Yes and No buttons.
+    pub fn toggle_confirm_focus(&mut self) {
+        self.confirm_focus_yes = !self.confirm_focus_yes;
+    }
+
+    /// Press whichever button has focus.
+    pub fn activate_confirm_focus(&mut self) -> Result<()> {
+        if self.confirm_focus_yes {
+            self.confirm_action()
+        } else {
+            self.cancel_confirm();
+            Ok(())
+        }
+    }
+
+    /// What must be typed before acting on this process, and why, if anything must.
+    ///
+    /// Core system processes take `yes`; names from `protected_processes` take the name.
+    fn protection(&self, process_name: &str, pid: i32) -> Option<Protection> {
+        // lsof cuts names to 9 characters, so `WindowServer` arrives as `WindowSer`
+        let full_name = crate::port::process_name(pid);
+        let names: Vec<&str> = std::iter::once(process_name).chain(full_name.as_deref()).collect();
+
+        if self.protect_system_processes
+            && let Some(reason) =
+                names.iter().find_map(|name| crate::port::is_protected_process(name, pid))
+        {
+            return Some(Protection {
+                reason: format!("Protected process ({})", reason),
+                answer: String::from(PROTECTED_CONFIRMATION),
+            });
+        }
+        self.protected_processes
+            .iter()
+            .find(|protected| names.contains(&protected.as_str()))
+            .map(|protected| Protection {
+                reason: String::from("Protected process"),
+                answer: protected.clone(),
+            })
+    }
+
+    /// Dismiss the confirmation dialog without acting.
+    pub fn cancel_confirm(&mut self) {
+        self.show_confirm = false;
+        self.confirm_message.clear();
+        self.pending_action = None;
+        self.confirm_expected = None;
+        self.confirm_input.clear();
+    }
+
+    pub fn request_restart(&mut self) -> Result<()> {
+        if self.blocked_by_read_only("restarting processes") {
+            return Ok(());
+        }
+
+        let Some(port) = self.selected_port().cloned() else {
+            return Ok(());
+        };
+
+        let Some(argv) = crate::port::process_argv(port.pid) else {
+            self.status_message = format!(
+                "Cannot restart '{}': its command line is unavailable",
+                port.process_name
+            );
+            return Ok(());
+        };
+        let cwd = crate::port::process_cwd(port.pid);
+
+        let mut message = format!(
+            "Restart process '{}' (PID {}) on port {}?\n{}",
+            port.process_name,
+            port.pid,
+            port.port,
+            argv.join(" ")
+        );
+        match &cwd {
+            Some(cwd) => message.push_str(&format!("\nin {}", cwd.display())),
+            None => message.push_str("\nin anchor's working directory (original unknown)"),
+        }
+
+        let protection = self.protection(&port.process_name, port.pid);
+        self.confirm_expected = protection.as_ref().map(|p| p.answer.clone());
+        self.confirm_reason = protection.map(|p| p.reason).unwrap_or_default();
+        self.confirm_message = message;
+        self.open_confirm(PendingAction::RestartProcess {
+            pid: port.pid,
+            port: port.port,
+            argv,
+            cwd,
+        });
+        Ok(())
+    }
+
+    /// Signal every target on a worker thread, escalating survivors of SIGTERM to SIGKILL
+    /// after the grace period, or send just the picked `signal`.
+    fn kill_processes(
+        &mut self,
+        targets: Vec<KillTarget>,
+        graceful: bool,
+        signal: Option<&'static str>,
+    ) {
+        if self.pending_kill.is_some() {
+            self.status_message = String::from("Still killing; try again when it's done");
+            return;
+        }
+        let pids: Vec<i32> = targets.iter().map(|target| target.pid).collect();
+        let grace = self.kill_grace;
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            let _ = tx.send(match signal {
+                Some(signal) => crate::port::signal_pids(&pids, signal),
+                None => crate::port::kill_pids(&pids, graceful, grace),
+            });
+        });
+        if signal.is_none() && graceful {
+            self.status_message = match targets.as_slice() {
+                [target] => {
+                    format!("Sent SIGTERM to process {}; waiting for it to exit", target.pid)
+                }
+                _ => format!(
+                    "Sent SIGTERM to {} processes; waiting for them to exit",
+                    targets.len()
+                ),
+            };
+        }
+        self.pending_kill = Some(PendingKill {
+            targets,
+            graceful,
+            signal,
+            outcomes: rx,
+        });
+    }
+
+    /// Record the outcome of finished kills, say how they went and rescan.
+    pub fn collect_kill(&mut self) {
+        let Some(pending) = &self.pending_kill else {
+            return;
+        };
+        let result = match pending.outcomes.try_recv() {
+            Ok(result) => result,
+            Err(mpsc::TryRecvError::Empty) => return,
+            Err(mpsc::TryRecvError::Disconnected) => Err(anyhow::anyhow!("kill worker stopped")),
+        };
+        let Some(PendingKill {
+            targets,
+            graceful,
+            signal,
+            ..
+        }) = self.pending_kill.take()
+        else {
+            return;
+        };
+        let outcomes = match result {
+            Ok(outcomes) => outcomes,
+            Err(e) => {
+                self.status_message = format!("Failed to kill: {:#}", e);
+                return;
+            }
+        };
+        let at = Instant::now();
+        let records: Vec<KillRecord> = targets
+            .into_iter()
+            .zip(outcomes)
+            .map(|(target, outcome)| KillRecord {
+                pid: target.pid,
+                process_name: target.process_name,
+                port: target.port,
+                signal: outcome.signal,
+                at,
+                error: outcome.error,
+            })
+            .collect();
+
+        let count = records.len();
+        let failures: Vec<&String> = records.iter().filter_map(|r| r.error.as_ref()).collect();
+        let killed = count - failures.len();
+        let escalated = records
+            .iter()
+            .filter(|r| graceful && r.error.is_none() && r.signal == "KILL")
+            .count();
+        self.status_message = if let Some(signal) = signal {
+            match (records.as_slice(), failures.first()) {
+                ([record], None) => format!("Sent SIG{} to process {}", signal, record.pid),
+                ([_], Some(e)) => format!("Failed to signal process: {}", e),
+                (_, None) => format!("Sent SIG{} to {} processes", signal, count),
+                (_, Some(e)) => {
+                    format!("Signalled {}/{} processes; first failure: {}", killed, count, e)
+                }
+            }
+        } else {
+            match (records.as_slice(), failures.first()) {
+                ([record], None) if graceful && record.signal == "TERM" => {
+                    format!("Process {} exited after SIGTERM", record.pid)
+                }
+                ([record], None) if graceful => format!(
+                    "Process {} ignored SIGTERM for {}s; killed with SIGKILL",
+                    record.pid,
+                    self.kill_grace.as_secs()
+                ),
+                ([record], None) => format!("Killed process {} with SIGKILL", record.pid),
+                ([_], Some(e)) => format!("Failed to kill process: {}", e),
+                (_, None) if graceful => format!(
+                    "Killed {} processes ({} by SIGTERM, {} by SIGKILL)",
+                    count,
+                    count - escalated,
+                    escalated
+                ),
+                (_, None) => format!("Killed {} processes with SIGKILL", count),
+                (_, Some(e)) => {
+                    format!("Killed {}/{} processes; first failure: {}", killed, count, e)
+                }
+            }
+        };
+        self.kill_log.extend(records);
+        self.reconcile_killed_tunnels();
+        if killed > 0 {
+            // A scan already in flight started before the kills and would still show them
+            self.pending_scan = None;
+            self.start_refresh(true);
+        }
+    }
+
+    /// Stop the process and launch the same command again on a worker thread.
+    fn restart_process(&mut self, pid: i32, port: u16, argv: Vec<String>, cwd: Option<PathBuf>) {
+        if let Some(pending) = &self.pending_restart {
+            self.status_message =
+                format!("Still restarting process {}; try again when it's done", pending.pid);
+            return;
+        }
+        let (tx, rx) = mpsc::channel();
+        let backend = self.port_backend;
+        std::thread::spawn(move || restart_worker(pid, port, backend, argv, cwd, tx));
+        self.pending_restart = Some(PendingRestart { pid, result: rx });
+        self.status_message = format!("Restarting process {}...", pid);
+    }
+
+    /// Report a finished restart and rescan so the relaunched process shows up.
+    pub fn collect_restart(&mut self) {
+        let Some(pending) = &self.pending_restart else {
+            return;
+        };
+        let message = match pending.result.try_recv() {
+            Ok(message) => message,
+            Err(mpsc::TryRecvError::Empty) => return,
+            Err(mpsc::TryRecvError::Disconnected) => String::from("Restart worker stopped"),
+        };
+        self.pending_restart = None;
+        self.status_message = message;
+        self.reconcile_killed_tunnels();
+        // A scan already in flight started before the relaunch and wouldn't show it
+        self.pending_scan = None;
+        self.start_refresh(true);
+    }
+
+    pub fn confirm_action(&mut self) -> Result<()> {
+        // Protected kills and big batches need an exact answer; a mismatch keeps the dialog open
+        if let Some(expected) = &self.confirm_expected {
+            if self.confirm_input != *expected {
+                self.status_message = format!("Type '{}' exactly to confirm", expected);
+                return Ok(());
+            }
+            self.confirm_expected = None;
+            self.confirm_input.clear();
+        }
+
+        self.show_confirm = false;
+
+        if self.blocked_by_read_only("this action") {
+            self.pending_action = None;
+            return Ok(());
+        }
+
+        if let Some(action) = self.pending_action.take() {
+            match action {
+                PendingAction::KillProcesses {
+                    targets,
+                    graceful,
+                    signal,
+                } => {
+                    self.selected_ports.clear();
+                    self.kill_processes(targets, graceful, signal);
+                }
+                PendingAction::RestartProcess {
+                    pid,
+                    port,
+                    argv,
+                    cwd,
+                } => {
+                    self.restart_process(pid, port, argv, cwd);
+                }
+                PendingAction::DeleteTunnel(name) => {
+                    self.tunnel_manager.remove(&name);
+                    self.tunnel_manager.save()?;
+                    self.apply_tunnel_filter();
+                    self.status_message = format!("Deleted tunnel '{}'", name);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Mark tunnels whose ssh was among the processes just killed as down, saying so.
+    fn reconcile_killed_tunnels(&mut self) {
+        let dropped = self.tunnel_manager.refresh_statuses();
+        if dropped.is_empty() {
+            return;
+        }
+        let names: Vec<String> = dropped.iter().map(|name| format!("'{}'", name)).collect();
+        self.status_message
+            .push_str(&format!("; tunnel {} disconnected", names.join(", ")));
+        if self.tunnel_sort == TunnelSort::Status {
+            self.resort_tunnels();
+        }
+    }
+
+    /// Open the add-tunnel form, or the step-by-step prompts if the terminal is too narrow.
+    pub fn start_add_tunnel(&mut self, width: u16) {
+        if width >= FORM_MIN_WIDTH {
+            self.tunnel_form = Some(TunnelForm::default());
+        } else {
+            self.start_add_tunnel_wizard();
+        }
+    }
+
+    fn start_add_tunnel_wizard(&mut self) {
+        self.new_tunnel = Some(TunnelConfig::default());
+        self.input_mode = InputMode::TunnelName;
+        self.input_prompt = String::from("Tunnel name:");
+        self.input_buffer.clear();
+        self.show_input = true;
+    }
+
+    /// Open the selected tunnel in the form (or prompts, if narrow) with its current values.
+    pub fn start_edit_tunnel(&mut self, width: u16) {
+        let Some(tunnel) = self.selected_tunnel().cloned() else {
+            return;
+        };
+
+        self.editing_tunnel = Some(tunnel.name.clone());
+        if width >= FORM_MIN_WIDTH {
+            self.tunnel_form = Some(TunnelForm::from_tunnel(&tunnel, 0));
+        } else {
+            self.input_mode = InputMode::TunnelName;
+            self.input_prompt = String::from("Tunnel name:");
+            self.set_input(&tunnel.name);
+            self.new_tunnel = Some(tunnel);
+            self.show_input = true;
+        }
+    }
+
+    /// Start adding a copy of the selected tunnel, prefilled and named `<name> copy`.
+    pub fn start_duplicate_tunnel(&mut self, width: u16) {
+        let Some(tunnel) = self.selected_tunnel() else {
+            return;
+        };
+        let source = tunnel.name.clone();
+        let mut name = format!("{} copy", source);
+        let mut n = 2;
+        while self.tunnel_manager.tunnels.iter().any(|t| t.name == name) {
+            name = format!("{} copy {}", source, n);
+            n += 1;
+        }
+        let copy = tunnel.duplicate(name);
+
+        self.duplicating_tunnel = Some(source);
+        if width >= FORM_MIN_WIDTH {
+            // Copies usually differ only in their ports
+            self.tunnel_form = Some(TunnelForm::from_tunnel(&copy, FORM_LOCAL_PORT));
+        } else {
+            self.input_mode = InputMode::TunnelName;
+            self.input_prompt = String::from("Tunnel name:");
+            self.set_input(&copy.name);
+            self.show_input = true;
+        }
+        // Holds the settings the form has no field for
+        self.new_tunnel = Some(copy);
+    }
+
+    /// Replace the input with `text`, cursor at the end.
+    fn set_input(&mut self, text: &str) {
+        self.input_buffer = text.to_string();
+        self.input_cursor = self.input_buffer.chars().count();
+        self.host_suggestion = None;
+    }
+
+    /// Recent hosts matching what's typed, while the SSH host prompt is open.
+    pub fn host_suggestions(&self) -> Vec<&str> {
+        if !self.show_input || self.input_mode != InputMode::TunnelHost {
+            return Vec::new();
+        }
+        let typed = self.input_buffer.trim().to_lowercase();
+        self.recent_hosts
+            .iter()
+            .filter(|host| host.to_lowercase().contains(&typed))
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// Highlight the next (or previous) suggested host; past either end goes back to the typed text.
+    pub fn move_host_suggestion(&mut self, forward: bool) {
+        let count = self.host_suggestions().len();
+        self.host_suggestion = match (self.host_suggestion, forward) {
+            (None, true) if count > 0 => Some(0),
+            (None, false) if count > 0 => Some(count - 1),
+            (Some(i), true) if i + 1 < count => Some(i + 1),
+            (Some(i), false) if i > 0 => Some(i - 1),
+            _ => None,
+        };
+    }
+
+    /// Move `host` to the front of the recent hosts and save them.
+    fn remember_host(&mut self, host: &str) {
+        self.recent_hosts.retain(|recent| recent != host);
+        self.recent_hosts.insert(0, host.to_string());
+        self.recent_hosts.truncate(RECENT_HOSTS_MAX);
+        self.save_setting("recent_hosts", serde_json::json!(self.recent_hosts));
+    }
+
+    /// The tunnel being edited, the copy being made, or defaults for a new one.
+    fn tunnel_being_edited(&self) -> TunnelConfig {
+        self.editing_tunnel
+            .as_ref()
+            .and_then(|name| self.tunnel_manager.tunnels.iter().find(|t| &t.name == name))
+            .or(self.new_tunnel.as_ref())
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Why `name` can't be used, if an edit or copy would overwrite a different tunnel.
+    fn name_conflict(&self, name: &str) -> Option<String> {
+        let taken = self.tunnel_manager.tunnels.iter().any(|t| t.name == name);
+        let conflict = match &self.editing_tunnel {
+            Some(original) => original != name && taken,
+            // Adding replaces a tunnel of the same name, which a copy must not do to its original
+            None => self.duplicating_tunnel.is_some() && taken,
+        };
+        conflict.then(|| format!("A tunnel named '{}' already exists", name))
+    }
+
+    /// Add the finished tunnel, or put it in place of the one being edited.
+    fn save_tunnel(&mut self, tunnel: TunnelConfig) -> Result<()> {
+        let host = tunnel.ssh_host.clone();
+        match self.editing_tunnel.take() {
+            Some(original) => {
+                // A running ssh keeps forwarding with the old settings until reconnected
+                self.status_message = if tunnel.is_connected() {
+                    format!("Updated tunnel '{}'; reconnect to apply", tunnel.name)
+                } else {
+                    format!("Updated tunnel '{}'", tunnel.name)
+                };
+                self.tunnel_manager.replace(&original, tunnel);
+            }
+            None => {
+                self.status_message = match self.duplicating_tunnel.take() {
+                    Some(source) => {
+                        format!("Added tunnel '{}', copied from '{}'", tunnel.name, source)
+                    }
+                    None => format!("Added tunnel '{}'", tunnel.name),
+                };
+                self.tunnel_manager.add(tunnel);
+            }
+        }
+        self.tunnel_manager.save()?;
+        self.apply_tunnel_filter();
+        self.remember_host(&host);
+        Ok(())
+    }
+
+    /// Cursor position clamped to the current buffer.
+    pub fn input_cursor_position(&self) -> usize {
+        self.input_cursor.min(self.input_buffer.chars().count())
+    }
+
+    /// Move the cursor, clamping to the ends of the buffer (`usize::MAX` for the end).
+    pub fn move_input_cursor(&mut self, position: usize) {
+        self.input_cursor = position.min(self.input_buffer.chars().count());
+    }
+
+    /// Byte offset of the character at `position`, for editing the buffer in place.
+    fn input_byte_index(&self, position: usize) -> usize {
+        self.input_buffer
+            .char_indices()
+            .nth(position)
+            .map_or(self.input_buffer.len(), |(i, _)| i)
+    }
+
+    pub fn input_insert(&mut self, c: char) {
+        let cursor = self.input_cursor_position();
+        let index = self.input_byte_index(cursor);
+        self.input_buffer.insert(index, c);
+        self.input_cursor = cursor + 1;
+        self.host_suggestion = None;
+    }
+
+    /// Delete the character before the cursor.
+    pub fn input_backspace(&mut self) {
+        let cursor = self.input_cursor_position();
+        if cursor > 0 {
+            let index = self.input_byte_index(cursor - 1);
+            self.input_buffer.remove(index);
+            self.input_cursor = cursor - 1;
+            self.host_suggestion = None;
+        }
+    }
+
+    /// Delete the character under the cursor.
+    pub fn input_delete(&mut self) {
+        let cursor = self.input_cursor_position();
+        if cursor < self.input_buffer.chars().count() {
+            let index = self.input_byte_index(cursor);
+            self.input_buffer.remove(index);
+            self.host_suggestion = None;
+        }
+    }
+
+    pub fn submit_input(&mut self) -> Result<()> {
+        if let Some(host) = self
+            .host_suggestion
+            .and_then(|i| self.host_suggestions().get(i).map(|host| host.to_string()))
+        {
+            self.set_input(&host);
+        }
+        let input = self.input_buffer.trim().to_string();
+
+        // Kind defaults to local, and the last two tunnel prompts are optional
+        let optional = matches!(
+            self.input_mode,
+            InputMode::TunnelKind
+                | InputMode::TunnelBindAddress
+                | InputMode::TunnelIdentityFile
+                | InputMode::TunnelSshPort
+                | InputMode::TunnelJumpHost
+        );
+        if input.is_empty() && !optional {
+            self.status_message = String::from("Input cannot be empty");
+            return Ok(());
+        }
+
+        if self.input_mode == InputMode::ProfileName {
+            self.switch_profile(&input);
+            return Ok(());
+        }
+
+        if self.input_mode == InputMode::KillPid {
+            self.submit_kill_pid(&input);
+            return Ok(());
+        }
+
+        if self.input_mode == InputMode::ExportFormat {
+            self.submit_export_ports(&input);
+            return Ok(());
+        }
+
+        let Some(tunnel) = self.new_tunnel.as_mut() else {
+            return Ok(());
+        };
+        // Each step starts from the tunnel's current value, which is empty when adding
+        match self.input_mode {
+            InputMode::TunnelName => {
+                if let Some(conflict) = self.name_conflict(&input) {
+                    self.status_message = conflict;
+                    return Ok(());
+                }
+                let Some(tunnel) = self.new_tunnel.as_mut() else {
+                    return Ok(());
+                };
+                tunnel.name = input;
+                let host = tunnel.ssh_host.clone();
+                self.input_mode = InputMode::TunnelHost;
+                self.input_prompt = String::from("SSH host (user@host):");
+                self.set_input(&host);
+            }
+            InputMode::TunnelHost => {
+                if let Err(e) = crate::tunnel::check_ssh_host(&input) {
+                    self.status_message = format!("Invalid SSH host: {}", e);
+                    return Ok(());
+                }
+                tunnel.ssh_host = input;
+                let kind = tunnel.kind.label();
+                self.input_mode = InputMode::TunnelKind;
+                self.input_prompt = String::from("Kind (local, remote or dynamic):");
+                self.set_input(kind);
+            }
+            InputMode::TunnelKind => {
+                match ForwardKind::parse(&input) {
+                    Ok(kind) => tunnel.kind = kind,
+                    Err(e) => {
+                        self.status_message = format!("Invalid kind: {}", e);
+                        return Ok(());
+                    }
+                }
+                let port = match tunnel.local_port {
+                    0 => String::new(),
+                    port => port.to_string(),
+                };
+                self.input_mode = InputMode::TunnelLocalPort;
+                self.input_prompt = String::from("Local port (Ctrl+F: next free):");
+                self.set_input(&port);
+            }
+            InputMode::TunnelLocalPort => {
+                if let Ok(port) = input.parse::<u16>() {
+                    tunnel.local_port = port;
+                    if tunnel.kind == ForwardKind::Remote {
+                        // The bind address of a remote forward is part of its target
+                        tunnel.bind_address = None;
+                        let target = tunnel.remote_target.clone();
+                        self.input_mode = InputMode::TunnelRemotePort;
+                        self.input_prompt =
+                            String::from("Port on the ssh host (port or bind:port):");
+                        self.set_input(&target);
+                        return Ok(());
+                    }
+                    let bind = tunnel.bind_address.clone().unwrap_or_default();
+                    self.input_mode = InputMode::TunnelBindAddress;
+                    self.input_prompt =
+                        String::from("Bind address (optional, e.g. 0.0.0.0; Enter for loopback):");
+                    self.set_input(&bind);
+                } else {
+                    self.status_message = String::from("Invalid port number");
+                }
+            }
+            InputMode::TunnelBindAddress => {
+                let bind = input.trim_matches(['[', ']']).to_string();
+                if !bind.is_empty()
+                    && let Err(e) = crate::tunnel::check_bind_address(&bind)
+                {
+                    self.status_message = format!("Invalid bind address: {}", e);
+                    return Ok(());
+                }
+                tunnel.bind_address = Some(bind).filter(|bind| !bind.is_empty());
+                if tunnel.kind == ForwardKind::Dynamic {
+                    // A SOCKS proxy has no remote target
+                    let identity_file = tunnel.identity_file.clone().unwrap_or_default();
+                    self.prompt_identity_file(&identity_file);
+                    return Ok(());
+                }
+                let target = tunnel.remote_target.clone();
+                self.input_mode = InputMode::TunnelRemotePort;
+                self.input_prompt = String::from("Remote target (host:port or [IPv6]:port):");
+                self.set_input(&target);
+            }
+            InputMode::TunnelRemotePort => {
+                if let Err(e) = crate::tunnel::check_remote_target(tunnel.kind, &input) {
+                    self.status_message = format!("Invalid remote target: {}", e);
+                    return Ok(());
+                }
+                tunnel.remote_target = input;
+                let identity_file = tunnel.identity_file.clone().unwrap_or_default();
+                self.prompt_identity_file(&identity_file);
+            }
+            InputMode::TunnelIdentityFile => {
+                tunnel.identity_file = Some(input).filter(|path| !path.is_empty());
+                let port = tunnel.ssh_port.map(|port| port.to_string()).unwrap_or_default();
+                self.input_mode = InputMode::TunnelSshPort;
+                self.input_prompt = String::from("SSH port (optional, Enter for default):");
+                self.set_input(&port);
+            }
+            InputMode::TunnelSshPort => match parse_optional_port(&input) {
+                Ok(port) => {
+                    tunnel.ssh_port = port;
+                    let jump_host = tunnel.jump_host.clone().unwrap_or_default();
+                    self.input_mode = InputMode::TunnelJumpHost;
+                    self.input_prompt =
+                        String::from("Jump host (optional, [user@]host[:port]; Enter for none):");
+                    self.set_input(&jump_host);
+                }
+                Err(_) => self.status_message = String::from("Invalid SSH port"),
+            },
+            InputMode::TunnelJumpHost => {
+                if !input.is_empty()
+                    && let Err(e) = crate::tunnel::check_jump_host(&input)
+                {
+                    self.status_message = format!("Invalid jump host: {}", e);
+                    return Ok(());
+                }
+                tunnel.jump_host = Some(input).filter(|jump| !jump.is_empty());
+                return self.finish_tunnel_wizard();
+            }
+            InputMode::ProfileName
+            | InputMode::KillPid
+            | InputMode::ExportFormat
+            | InputMode::None => {}
+        }
+
+        Ok(())
+    }
+
+    fn prompt_identity_file(&mut self, current: &str) {
+        self.input_mode = InputMode::TunnelIdentityFile;
+        self.input_prompt = String::from("Identity file (optional, Enter to skip):");
+        self.set_input(current);
+    }
+
+    /// Save the tunnel built up by the prompts and close them.
+    fn finish_tunnel_wizard(&mut self) -> Result<()> {
+        // Keep the prompts open on a bad tunnel, e.g. one from tunnels.toml being edited
+        if let Some(Err(problem)) = self.new_tunnel.as_ref().map(TunnelConfig::validate) {
+            self.status_message = problem;
+            return Ok(());
+        }
+        if let Some(tunnel) = self.new_tunnel.take() {
+            self.save_tunnel(tunnel)?;
+        }
+        self.show_input = false;
+        self.input_mode = InputMode::None;
+        Ok(())
+    }
+
+    /// Validate and save the form, focusing the first field that needs fixing.
+    pub fn submit_tunnel_form(&mut self) -> Result<()> {
+        let base = self.tunnel_being_edited();
+        let conflict = self
+            .tunnel_form
+            .as_ref()
+            .and_then(|form| self.name_conflict(form.values[0].trim()));
+        let Some(form) = &mut self.tunnel_form else {
+            return Ok(());
+        };
+        let values: Vec<String> = form.values.iter().map(|v| v.trim().to_string()).collect();
+
+        let kind = match ForwardKind::parse(&values[FORM_KIND]) {
+            Ok(kind) => kind,
+            Err(e) => {
+                form.focus = FORM_KIND;
+                self.status_message = format!("Invalid kind: {}", e);
+                return Ok(());
+            }
+        };
+        // Fields with a placeholder may be empty, as may a SOCKS proxy's remote target
+        let empty = (0..values.len()).find(|&i| {
+            values[i].is_empty()
+                && TUNNEL_FORM_PLACEHOLDERS[i].is_empty()
+                && !(i == FORM_REMOTE_TARGET && kind == ForwardKind::Dynamic)
+        });
+        if let Some(empty) = empty {
+            form.focus = empty;
+            self.status_message = format!("{} cannot be empty", TUNNEL_FORM_FIELDS[empty]);
+            return Ok(());
+        }
+        if let Some(conflict) = conflict {
+            form.focus = 0;
+            self.status_message = conflict;
+            return Ok(());
+        }
+        let Ok(local_port) = values[FORM_LOCAL_PORT].parse::<u16>() else {
+            form.focus = FORM_LOCAL_PORT;
+            self.status_message = String::from("Invalid port number");
+            return Ok(());
+        };
+
+        if let Err(e) = crate::tunnel::check_ssh_host(&values[FORM_SSH_HOST]) {
+            form.focus = FORM_SSH_HOST;
+            self.status_message = format!("Invalid SSH host: {}", e);
+            return Ok(());
+        }
+        if let Err(e) = crate::tunnel::check_remote_target(kind, &values[FORM_REMOTE_TARGET]) {
+            form.focus = FORM_REMOTE_TARGET;
+            self.status_message = format!("Invalid remote target: {}", e);
+            return Ok(());
+        }
+        let bind_address = values[FORM_BIND_ADDRESS].trim_matches(['[', ']']).to_string();
+        if !bind_address.is_empty() {
+            if kind == ForwardKind::Remote {
+                form.focus = FORM_BIND_ADDRESS;
+                self.status_message =
+                    String::from("Remote forwards take their bind address in the remote target");
+                return Ok(());
+            }
+            if let Err(e) = crate::tunnel::check_bind_address(&bind_address) {
+                form.focus = FORM_BIND_ADDRESS;
+                self.status_message = format!("Invalid bind address: {}", e);
+                return Ok(());
+            }
+        }
+        let Ok(ssh_port) = parse_optional_port(&values[FORM_SSH_PORT]) else {
+            form.focus = FORM_SSH_PORT;
+            self.status_message = String::from("Invalid SSH port");
+            return Ok(());
+        };
+        let jump_host = &values[FORM_JUMP_HOST];
+        if !jump_host.is_empty()
+            && let Err(e) = crate::tunnel::check_jump_host(jump_host)
+        {
+            form.focus = FORM_JUMP_HOST;
+            self.status_message = format!("Invalid jump host: {}", e);
+            return Ok(());
+        }
+
+        let tunnel = TunnelConfig {
+            name: values[0].clone(),
+            ssh_host: values[FORM_SSH_HOST].clone(),
+            local_port,
+            remote_target: values[FORM_REMOTE_TARGET].clone(),
+            kind,
+            bind_address: Some(bind_address).filter(|bind| !bind.is_empty()),
+            identity_file: Some(values[FORM_IDENTITY_FILE].clone()).filter(|path| !path.is_empty()),
+            ssh_port,
+            jump_host: Some(jump_host.clone()).filter(|jump| !jump.is_empty()),
+            ..base
+        };
+        self.tunnel_form = None;
+        self.new_tunnel = None;
+        self.save_tunnel(tunnel)
+    }
+
+    pub fn cancel_tunnel_form(&mut self) {
+        self.tunnel_form = None;
+        self.new_tunnel = None;
+        self.editing_tunnel = None;
+        self.duplicating_tunnel = None;
+    }
+
+    /// Fill the local port field with the next free port, continuing past the current value.
+    pub fn suggest_local_port(&mut self) {
+        if let Some(form) = &self.tunnel_form {
+            if form.focus != FORM_LOCAL_PORT {
+                return;
+            }
+            let current = form.values[FORM_LOCAL_PORT].clone();
+            if let Some(port) = self.next_free_local_port(&current)
+                && let Some(form) = &mut self.tunnel_form
+            {
+                form.values[FORM_LOCAL_PORT] = port.to_string();
+            }
+            return;
+        }
+
+        if self.input_mode != InputMode::TunnelLocalPort {
+            return;
+        }
+        if let Some(port) = self.next_free_local_port(&self.input_buffer.clone()) {
+            self.input_buffer = port.to_string();
+            self.input_cursor = self.input_buffer.chars().count();
+        }
+    }
+
+    fn next_free_local_port(&mut self, current: &str) -> Option<u16> {
+        let start = match current.trim().parse::<u16>() {
+            Ok(current) => current.saturating_add(1),
+            Err(_) => 8000,
+        };
+        let reserved: Vec<u16> = self
+            .tunnel_manager
+            .tunnels
+            .iter()
+            .map(|t| t.local_port)
+            .collect();
+
+        let port = crate::port::next_free_port(start, &reserved);
+        self.status_message = match port {
+            Some(port) => format!("Suggested free port {} (Enter to accept, or edit)", port),
+            None => format!("No free port found from {}", start),
+        };
+        port
+    }
+
+    pub fn start_switch_profile(&mut self) {
+        let profiles = TunnelManager::list_profiles();
+        self.input_mode = InputMode::ProfileName;
+        self.input_prompt = if profiles.is_empty() {
+            String::from("Switch to profile:")
+        } else {
+            format!("Switch to profile ({}):", profiles.join(", "))
+        };
+        self.input_buffer.clear();
+        self.show_input = true;
+    }
+
+    /// Point out tunnels whose key file has gone missing, before connect fails obscurely.
+    pub fn warn_missing_identity_files(&mut self) {
+        let missing = self
+            .tunnel_manager
+            .tunnels
+            .iter()
+            .filter(|t| t.identity_file_missing())
+            .count();
+
+        if missing > 0 {
+            self.status_message = format!(
+                "⚠ {} tunnel{} missing key files (fix identity_file in the tunnel config)",
+                missing,
+                if missing == 1 { " has" } else { "s have" }
+            );
+        }
+    }
+
+    /// Replace the tunnel set with the given profile's; a new name starts an empty profile.
+    fn switch_profile(&mut self, profile: &str) {
+        match TunnelManager::load(profile) {
+            Ok(manager) => {
+                self.tunnel_manager = manager;
+                self.profile = profile.to_string();
+                // Results from the old profile's auto-start would land on the wrong tunnels
+                self.auto_start = None;
+                self.reconnects = Reconnects::new();
+                self.tunnel_jobs = TunnelJobs::new();
+                self.tunnel_selected = 0;
+                self.apply_tunnel_filter();
+                self.status_message = format!(
+                    "Switched to profile '{}' ({} tunnels)",
+                    profile,
+                    self.tunnel_manager.tunnels.len()
+                );
+                self.show_input = false;
+                self.input_mode = InputMode::None;
+                self.input_buffer.clear();
+                self.warn_missing_identity_files();
+            }
+            Err(e) => {
+                self.status_message = format!("Failed to load profile: {}", e);
+            }
+        }
+    }
+
+    pub fn cancel_input(&mut self) {
+        self.show_input = false;
+        self.input_mode = InputMode::None;
+        self.new_tunnel = None;
+        self.editing_tunnel = None;
+        self.duplicating_tunnel = None;
+        self.input_buffer.clear();
+        self.host_suggestion = None;
+    }
+
+    pub fn connect_tunnel(&mut self) -> Result<()> {
+        if self.blocked_by_read_only("connecting tunnels") {
+            return Ok(());
+        }
+
+        if let Some(index) = self.selected_tunnel_index() {
+            let tunnel = &self.tunnel_manager.tunnels[index];
+            if tunnel.is_connected() {
+                self.status_message = format!("Tunnel '{}' is already connected", tunnel.name);
+                return Ok(());
+            }
+            if self.reconnects.in_flight.contains(&tunnel.name) {
+                self.status_message = format!("Tunnel '{}' is already reconnecting", tunnel.name);
+                return Ok(());
+            }
+            if self.tunnel_jobs.in_flight.contains_key(&tunnel.name) {
+                self.status_message =
+                    format!("Tunnel '{}' is busy; try again shortly", tunnel.name);
+                return Ok(());
+            }
+
+            // ssh can take up to connect_timeout_secs, so wait for it on a worker
+            let mut attempt = tunnel.clone();
+            self.status_message = format!("Connecting tunnel '{}'...", tunnel.name);
+            self.tunnel_jobs
+                .in_flight
+                .insert(tunnel.name.clone(), TunnelJob::Connect);
+            let sender = self.tunnel_jobs.sender.clone();
+            let ssh_binary = self.ssh_binary.clone();
+            std::thread::spawn(move || {
+                let error = attempt.connect(&ssh_binary).err().map(|e| e.to_string());
+                let _ = sender.send((TunnelJob::Connect, attempt, error));
+            });
+        }
+        Ok(())
+    }
+
+    /// What the worker for the tunnel named `name` is doing, if one is running.
+    pub fn tunnel_job(&self, name: &str) -> Option<TunnelJob> {
+        self.tunnel_jobs.in_flight.get(name).copied()
+    }
+
+    /// Apply the results of finished connects, disconnects and tests started by hand.
+    fn collect_tunnel_jobs(&mut self) {
+        let mut finished = false;
+        while let Ok((job, worked, error)) = self.tunnel_jobs.results.try_recv() {
+            self.tunnel_jobs.in_flight.remove(&worked.name);
+            finished = true;
+            let Some(tunnel) = self
+                .tunnel_manager
+                .tunnels
+                .iter_mut()
+                .find(|t| t.name == worked.name)
+            else {
+                // Deleted while connecting, so don't leave its ssh behind
+                if job == TunnelJob::Connect && error.is_none() {
+                    disconnect_in_background(worked);
+                }
+                continue;
+            };
+
+            match job {
+                TunnelJob::Connect => {
+                    tunnel.process = worked.process;
+                    tunnel.ssh_exited = worked.ssh_exited;
+                    tunnel.connected_at = worked.connected_at;
+                    tunnel.late_bind_deadline = worked.late_bind_deadline;
+                    tunnel.reconnect_paused = worked.reconnect_paused;
+                    match error {
+                        None => {
+                            tunnel.finish_reconnect(None);
+                            self.status_message = format!("Connected tunnel '{}'", tunnel.name);
+                        }
+                        Some(e) => {
+                            self.status_message = format!("Failed to connect: {}", e);
+                            self.notifier.send(
+                                &tunnel.name,
+                                &format!("Tunnel '{}' failed to connect", tunnel.name),
+                                &e,
+                            );
+                        }
+                    }
+                }
+                TunnelJob::Disconnect => {
+                    tunnel.process = worked.process;
+                    tunnel.connected_at = worked.connected_at;
+                    self.status_message = match error {
+                        None => format!("Disconnected tunnel '{}'", tunnel.name),
+                        Some(e) => format!("Failed to disconnect: {}", e),
+                    };
+                }
+                TunnelJob::Probe => {
+                    self.status_message = match &error {
+                        None => match tunnel.kind {
+                            ForwardKind::Dynamic => {
+                                format!("SOCKS proxy '{}' answers", tunnel.name)
+                            }
+                            _ => format!(
+                                "Tunnel '{}' answers on port {}",
+                                tunnel.name, tunnel.local_port
+                            ),
+                        },
+                        Some(e) => format!("Tunnel '{}' failed its test: {}", tunnel.name, e),
+                    };
+                    tunnel.probe_failure = error;
+                }
+            }
+        }
+        if finished && self.tunnel_sort == TunnelSort::Status {
+            self.resort_tunnels();
+        }
+    }
+
+    /// Connect every `auto_start` tunnel in the background; the summary arrives on a later tick.
+    pub fn auto_start_tunnels(&mut self) {
+        let tunnels: Vec<TunnelConfig> = self
+            .tunnel_manager
+            .tunnels
+            .iter()
+            .filter(|t| t.auto_start)
+            .cloned()
+            .collect();
+        if tunnels.is_empty() || self.blocked_by_read_only("auto-starting tunnels") {
+            return;
+        }
+
+        let (tx, rx) = mpsc::channel();
+        for mut tunnel in tunnels.iter().cloned() {
+            let tx = tx.clone();
+            let ssh_binary = self.ssh_binary.clone();
+            std::thread::spawn(move || {
+                let error = if tunnel.is_connected() {
+                    None
+                } else {
+                    tunnel.connect(&ssh_binary).err().map(|e| e.to_string())
+                };
+                let _ = tx.send((tunnel, error));
+            });
+        }
+
+        self.auto_start = Some(AutoStart {
+            results: rx,
+            pending: tunnels.len(),
+            started: 0,
+            failed: Vec::new(),
+        });
+        self.status_message = format!("Auto-starting {} tunnels...", tunnels.len());
+    }
+
+    /// Apply finished auto-start connections and report once all of them are done.
+    fn collect_auto_start(&mut self) {
+        let Some(auto_start) = &mut self.auto_start else {
+            return;
+        };
+
+        while let Ok((connected, error)) = auto_start.results.try_recv() {
+            auto_start.pending -= 1;
+            if let Some(tunnel) = self
+                .tunnel_manager
+                .tunnels
+                .iter_mut()
+                .find(|t| t.name == connected.name)
+            {
+                tunnel.process = connected.process;
+                tunnel.ssh_exited = connected.ssh_exited;
+                tunnel.connected_at = connected.connected_at;
+                tunnel.late_bind_deadline = connected.late_bind_deadline;
+            }
+            match error {
+                None => auto_start.started += 1,
+                Some(e) => auto_start.failed.push(format!("{} ({})", connected.name, e)),
+            }
+        }
+
+        if auto_start.pending > 0 {
+            return;
+        }
+        self.status_message = if auto_start.failed.is_empty() {
+            format!("Auto-started {} tunnels", auto_start.started)
+        } else {
+            format!(
+                "Auto-started {}/{} tunnels; failed: {}",
+                auto_start.started,
+                auto_start.started + auto_start.failed.len(),
+                auto_start.failed.join(", ")
+            )
+        };
+        self.auto_start = None;
+    }
+
+    /// Mark tunnels that finished connecting after their connect attempt timed out,
+    /// pick up auto-start and connect results, and keep the host resolution checks current.
+    pub fn reconcile_tunnels(&mut self) {
+        self.host_resolver.tick(&self.tunnel_manager.tunnels);
+        self.collect_auto_start();
+        self.collect_tunnel_jobs();
+
+        for tunnel in &mut self.tunnel_manager.tunnels {
+            if tunnel.reconcile_late_bind() {
+                self.status_message =
+                    format!("Tunnel '{}' came up after the timeout; now connected", tunnel.name);
+            }
+        }
+
+        self.auto_reconnect_tunnels();
+        self.watch_tunnels();
+        self.reload_tunnel_log();
+        self.collect_tunnel_test();
+    }
+
+    /// Apply finished reconnects, then start one for each dropped auto-reconnect tunnel
+    /// whose backoff has passed.
+    fn auto_reconnect_tunnels(&mut self) {
+        while let Ok((reconnected, error)) = self.reconnects.results.try_recv() {
+            self.reconnects.in_flight.remove(&reconnected.name);
+            let Some(tunnel) = self
+                .tunnel_manager
+                .tunnels
+                .iter_mut()
+                .find(|t| t.name == reconnected.name)
+            else {
+                continue;
+            };
+            if tunnel.reconnect_paused {
+                // Disconnected by hand while the attempt ran, so don't leave its ssh behind
+                if error.is_none() {
+                    disconnect_in_background(reconnected);
+                }
+                continue;
+            }
+
+            tunnel.process = reconnected.process;
+            tunnel.ssh_exited = reconnected.ssh_exited;
+            tunnel.connected_at = reconnected.connected_at;
+            tunnel.late_bind_deadline = reconnected.late_bind_deadline;
+            if error.is_none() {
+                self.status_message = format!(
+                    "Tunnel '{}' reconnected (attempt {})",
+                    tunnel.name, tunnel.reconnect_attempts
+                );
+            }
+            tunnel.finish_reconnect(error);
+        }
+
+        if self.read_only {
+            return;
+        }
+        for tunnel in &mut self.tunnel_manager.tunnels {
+            if tunnel.check_dropped() {
+                self.status_message = format!("Tunnel '{}' dropped; reconnecting", tunnel.name);
+            }
+            if !tunnel.reconnect_due()
+                || self.reconnects.in_flight.contains(&tunnel.name)
+                || self.tunnel_jobs.in_flight.contains_key(&tunnel.name)
+            {
+                continue;
+            }
+
+            tunnel.reconnect_attempts += 1;
+            self.reconnects.in_flight.insert(tunnel.name.clone());
+            let mut attempt = tunnel.clone();
+            let sender = self.reconnects.sender.clone();
+            let ssh_binary = self.ssh_binary.clone();
+            std::thread::spawn(move || {
+                let error = attempt.connect(&ssh_binary).err().map(|e| e.to_string());
+                let _ = sender.send((attempt, error));
+            });
+        }
+    }
+
+    /// Notify when a tunnel comes up or drops, checking every few seconds rather than every tick.
+    fn watch_tunnels(&mut self) {
+        if !self.notifier.enabled()
+            || self
+                .last_tunnel_watch
+                .is_some_and(|last| last.elapsed() < TUNNEL_WATCH_INTERVAL)
+        {
+            return;
+        }
+        self.last_tunnel_watch = Some(Instant::now());
+
+        for tunnel in &self.tunnel_manager.tunnels {
+            let up = tunnel.is_connected();
+            // The first sighting only records state; startup isn't a transition
+            match self.tunnel_up.insert(tunnel.name.clone(), up) {
+                Some(false) if up => self.notifier.send(
+                    &tunnel.name,
+                    &format!("Tunnel '{}' connected", tunnel.name),
+                    &tunnel.forward_display(),
+                ),
+                Some(true) if !up => self.notifier.send(
+                    &tunnel.name,
+                    &format!("Tunnel '{}' disconnected", tunnel.name),
+                    &tunnel.forward_display(),
+                ),
+                _ => {}
+            }
+        }
+    }
+
+    /// Probe the selected tunnel's forward on a worker and record the result in its health.
+    pub fn test_tunnel(&mut self) {
+        let Some(index) = self.selected_tunnel_index() else {
+            return;
+        };
+        let tunnel = &self.tunnel_manager.tunnels[index];
+        if !tunnel.is_connected() {
+            self.status_message = format!("Tunnel '{}' is not connected", tunnel.name);
+            return;
+        }
+        if self.tunnel_jobs.in_flight.contains_key(&tunnel.name) {
+            self.status_message =
+                format!("Tunnel '{}' is busy; try again shortly", tunnel.name);
+            return;
+        }
+
+        // A SOCKS handshake can wait out its whole timeout on a stuck proxy
+        let probing = tunnel.clone();
+        self.status_message = format!("Testing tunnel '{}'...", tunnel.name);
+        self.tunnel_jobs
+            .in_flight
+            .insert(tunnel.name.clone(), TunnelJob::Probe);
+        let sender = self.tunnel_jobs.sender.clone();
+        std::thread::spawn(move || {
+            let error = probing.probe().err().map(|e| format!("{:#}", e));
+            let _ = sender.send((TunnelJob::Probe, probing, error));
+        });
+    }
+
+    pub fn disconnect_tunnel(&mut self) -> Result<()> {
+        if self.blocked_by_read_only("disconnecting tunnels") {
+            return Ok(());
+        }
+
+        if let Some(index) = self.selected_tunnel_index() {
+            let tunnel = &mut self.tunnel_manager.tunnels[index];
+            if self.tunnel_jobs.in_flight.contains_key(&tunnel.name) {
+                self.status_message =
+                    format!("Tunnel '{}' is busy; try again shortly", tunnel.name);
+                return Ok(());
+            }
+            if !tunnel.is_connected() {
+                self.status_message = if tunnel.reconnecting() {
+                    // Nothing is running yet; a reconnect in flight is dropped when it lands
+                    tunnel.stop_reconnecting();
+                    format!("Stopped reconnecting tunnel '{}'", tunnel.name)
+                } else {
+                    format!("Tunnel '{}' is not connected", tunnel.name)
+                };
+                return Ok(());
+            }
+
+            // ssh gets a grace period after each signal, so wait for it on a worker
+            tunnel.stop_reconnecting();
+            let mut stopping = tunnel.clone();
+            self.status_message = format!("Disconnecting tunnel '{}'...", tunnel.name);
+            self.tunnel_jobs
+                .in_flight
+                .insert(tunnel.name.clone(), TunnelJob::Disconnect);
+            let sender = self.tunnel_jobs.sender.clone();
+            std::thread::spawn(move || {
+                let error = stopping.disconnect().err().map(|e| e.to_string());
+                let _ = sender.send((TunnelJob::Disconnect, stopping, error));
+            });
+        }
+        Ok(())
+    }
+
+    /// Write the tunnel set as a shell script that runs without anchor.
+    pub fn export_tunnel_script(&mut self) {
+        let path = format!("anchor-tunnels-{}.sh", self.profile);
+        let script = crate::tunnel::export_script(&self.tunnel_manager.tunnels);
+        self.status_message = match std::fs::write(&path, script) {
+            Ok(()) => format!(
+                "Exported {} tunnels to {}",
+                self.tunnel_manager.tunnels.len(),
+                path
+            ),
+            Err(e) => format!("Failed to export tunnels: {}", e),
+        };
+    }
+
+    /// Ask whether to export the ports in view as JSON or CSV.
+    pub fn start_export_ports(&mut self) {
+        self.input_mode = InputMode::ExportFormat;
+        self.input_prompt = format!(
+            "Export {} ports as (json or csv):",
+            self.filtered_ports.len()
+        );
+        self.set_input("json");
+        self.show_input = true;
+    }
+
+    /// Write the filtered port list to a new file in the current directory.
+    fn submit_export_ports(&mut self, input: &str) {
+        let format = match crate::port::ExportFormat::parse(input) {
+            Ok(format) => format,
+            Err(e) => {
+                self.status_message = format!("Invalid format: {}", e);
+                return;
+            }
+        };
+
+        self.cancel_input();
+        let path = crate::port::default_export_name(format);
+        let ports: Vec<PortInfo> = self.shown_ports().cloned().collect();
+        let mut content = Vec::new();
+        let written = format
+            .write(&ports, self.scanned_at, &mut content)
+            .and_then(|()| Ok(std::fs::write(&path, content)?));
+        self.status_message = match written {
+            Ok(()) => format!("Exported {} ports to {}", self.filtered_ports.len(), path),
+            Err(e) => format!("Failed to export ports: {}", e),
+        };
+    }
+
+    /// Add a tunnel for every `LocalForward` in `~/.ssh/config` not already saved by name.
+    pub fn import_ssh_config(&mut self) -> Result<()> {
+        let Some(path) = crate::tunnel::default_ssh_config_path() else {
+            self.status_message = String::from("No home directory to find ~/.ssh/config in");
+            return Ok(());
+        };
+
+        match self.tunnel_manager.import_ssh_config(&path) {
+            Ok(0) => {
+                self.status_message = format!("No new LocalForward tunnels in {}", path.display())
+            }
+            Ok(added) => {
+                self.tunnel_manager.save()?;
+                self.apply_tunnel_filter();
+                self.status_message = format!("Imported {} tunnels from {}", added, path.display());
+            }
+            Err(e) => self.status_message = format!("Import failed: {:#}", e),
         }
         Ok(())
     }
 
     pub fn request_delete_tunnel(&mut self) -> Result<()> {
-        if let Some(tunnel) = self.tunnel_manager.tunnels.get(self.tunnel_selected) {
-            self.confirm_message = format!("Delete tunnel '{}'?", tunnel.name);
-            self.pending_action = Some(PendingAction::DeleteTunnel(tunnel.name.clone()));
-            self.show_confirm = true;
+        if self.blocked_by_read_only("deleting tunnels") {
+            return Ok(());
+        }
+
+        if let Some(name) = self.selected_tunnel().map(|t| t.name.clone()) {
+            self.confirm_message = format!("Delete tunnel '{}'?", name);
+            self.open_confirm(PendingAction::DeleteTunnel(name));
         }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An app with no saved tunnels, built without reading the home directory or probing
+    /// for a socket backend.
+    fn test_app() -> App {
+        let settings = Settings {
+            port_backend: Some(PortBackend::Lsof),
+            ..Settings::default()
+        };
+        App::with_tunnels(&Options::default(), settings, TunnelManager::default())
+    }
+
+    fn socket(port: u16, pid: i32, name: &str, family: &str, address: &str) -> PortInfo {
+        PortInfo {
+            port,
+            pid,
+            process_name: name.to_string(),
+            protocol: String::from("TCP"),
+            family: family.to_string(),
+            state: String::from("LISTEN"),
+            local_address: address.to_string(),
+            ..PortInfo::default()
+        }
+    }
+
+    /// 5000 sockets across 1000 processes, half of the listeners dual-stacked.
+    fn synthetic_ports() -> Vec<PortInfo> {
+        (0..5000)
+            .map(|i| {
+                let v6 = i % 4 == 1;
+                let listen = i % 5 != 4;
+                PortInfo {
+                    // Twins share their port with the row before them
+                    port: 1024 + (i - usize::from(v6)) as u16,
+                    pid: 1000 + (i / 5) as i32,
+                    process_name: format!("worker-{}", i / 5),
+                    user: String::from("alice"),
+                    protocol: String::from("TCP"),
+                    family: String::from(if v6 { "IPv6" } else { "IPv4" }),
+                    state: String::from(if listen { "LISTEN" } else { "ESTABLISHED" }),
+                    local_address: String::from(if v6 { "::" } else { "0.0.0.0" }),
+                    ..PortInfo::default()
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn filter_picks_matching_ports_and_folds_twins() {
+        let mut app = test_app();
+        app.ports = vec![
+            socket(8080, 30, "java", "IPv4", "0.0.0.0"),
+            socket(80, 10, "nginx", "IPv4", "0.0.0.0"),
+            socket(80, 10, "nginx", "IPv6", "::"),
+            socket(3000, 20, "node", "IPv4", "127.0.0.1"),
+        ];
+        app.dual_stack_twins = crate::port::dual_stack_twins(&app.ports);
+
+        app.filter_text = String::from("80");
+        app.apply_filter();
+        let mut shown = app.filtered_ports.clone();
+        shown.sort_unstable();
+        assert_eq!(shown, vec![0, 1]);
+        let nginx = app.filtered_ports.iter().position(|&i| i == 1).expect("nginx shown");
+        assert_eq!(app.dual_stack_peer(nginx), Some("::"));
+
+        app.filter_text = String::from("node");
+        app.apply_filter();
+        assert_eq!(app.filtered_ports, vec![3]);
+
+        // Without merging, the IPv6 twin is a row of its own
+        app.merge_dual_stack = false;
+        app.filter_text = String::from("nginx");
+        app.apply_filter();
+        let mut shown = app.filtered_ports.clone();
+        shown.sort_unstable();
+        assert_eq!(shown, vec![1, 2]);
+        assert!((0..shown.len()).all(|row| app.dual_stack_peer(row).is_none()));
+    }
+
+    #[test]
+    #[ignore = "timing depends on the machine; run with --ignored on an idle one"]
+    fn filter_keystroke_over_5000_ports_fits_in_a_frame() {
+        let mut app = test_app();
+        app.ports = synthetic_ports();
+        app.dual_stack_twins = crate::port::dual_stack_twins(&app.ports);
+        app.apply_filter();
+        assert!(!app.dual_stack_merged.is_empty());
+
+        let started = Instant::now();
+        app.filter_text.push('7');
+        app.apply_filter();
+        let elapsed = started.elapsed();
+
+        assert!(!app.filtered_ports.is_empty());
+        assert!(app.shown_ports().all(|p| port_matches(p, "7")));
+        // One frame at 60Hz
+        assert!(elapsed < Duration::from_millis(16), "keystroke took {:?}", elapsed);
+    }
+}
@@ -0,0 +1,148 @@
+use clap::{CommandFactory, Parser, Subcommand};
+use std::path::PathBuf;
+
+/// Options that affect how the TUI behaves
+#[derive(Clone, Debug, clap::Args)]
+pub struct Options {
+    /// Disable kills and tunnel connect/disconnect/delete
+    #[arg(long, global = true)]
+    pub read_only: bool,
+    /// Scan with 'sudo -n' to see other users' sockets; run 'sudo -v' first, since anchor
+    /// never prompts for a password
+    #[arg(long, global = true)]
+    pub sudo: bool,
+    /// Use the tunnels of profile NAME
+    #[arg(
+        long,
+        global = true,
+        value_name = "NAME",
+        default_value = crate::tunnel::DEFAULT_PROFILE
+    )]
+    pub profile: String,
+    /// Don't capture the mouse, so terminal text selection works
+    #[arg(long, global = true)]
+    pub no_mouse: bool,
+    /// Background update interval (default 250); lower is livelier, higher uses less CPU.
+    /// Key presses are handled immediately
+    #[arg(long, global = true, value_name = "MS")]
+    pub tick_ms: Option<u64>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            read_only: false,
+            sudo: false,
+            profile: String::from(crate::tunnel::DEFAULT_PROFILE),
+            no_mouse: false,
+            tick_ms: None,
+        }
+    }
+}
+
+pub enum Command {
+    Tui,
+    /// Dump every current connection to a JSON file and exit
+    Snapshot(PathBuf),
+    /// Print the port table (or JSON) and exit
+    List { json: bool },
+    /// Kill whatever listens on a port and exit
+    Kill { port: u16, force: bool },
+    /// Print raw lsof output and the parsed rows, for parser bug reports (not in usage)
+    DumpLsof,
+    /// Print each tunnel's state and exit, failing if an expected tunnel is down
+    TunnelStatus { short: bool },
+    /// Write the tunnels as a shell script of ssh commands (stdout if no file)
+    ExportScript(Option<PathBuf>),
+}
+
+pub struct Cli {
+    pub options: Options,
+    pub command: Command,
+}
+
+/// Manage ports and SSH tunnels; runs the TUI unless given a command
+#[derive(Parser)]
+#[command(name = "anchor")]
+struct Args {
+    #[command(flatten)]
+    options: Options,
+    #[command(subcommand)]
+    command: Option<Subcommands>,
+    /// Print raw lsof output and the parsed rows
+    #[arg(long, hide = true, exclusive = true)]
+    dump_lsof: bool,
+}
+
+#[derive(Subcommand)]
+enum Subcommands {
+    /// Print every socket as a table
+    List {
+        /// Print a JSON array instead
+        #[arg(long)]
+        json: bool,
+    },
+    /// Kill the processes listening on PORT: SIGTERM, then SIGKILL after kill_grace_secs
+    Kill {
+        /// Port whose listeners to kill
+        port: u16,
+        /// Send SIGKILL straight away
+        #[arg(long)]
+        force: bool,
+    },
+    /// Write all current connections to FILE as JSON
+    Snapshot {
+        /// Defaults to anchor-snapshot-<time>.json
+        file: Option<PathBuf>,
+    },
+    /// Work with the saved tunnels without the TUI
+    Tunnels {
+        #[command(subcommand)]
+        command: TunnelsCommand,
+    },
+}
+
+#[derive(Subcommand)]
+enum TunnelsCommand {
+    /// Print tunnel states; exits 1 if an expected_up tunnel is down
+    Status {
+        /// One line like 'db:up web:down'
+        #[arg(long)]
+        short: bool,
+    },
+    /// Write the tunnels as a shell script of ssh commands
+    ExportScript {
+        /// Defaults to stdout
+        file: Option<PathBuf>,
+    },
+}
+
+/// Parse the command line, exiting with usage on `--help` or a bad argument.
+pub fn parse_args() -> Cli {
+    let args = Args::parse();
+    if args.dump_lsof && args.command.is_some() {
+        Args::command()
+            .error(
+                clap::error::ErrorKind::ArgumentConflict,
+                "--dump-lsof can't be combined with a command",
+            )
+            .exit();
+    }
+    let command = match args.command {
+        _ if args.dump_lsof => Command::DumpLsof,
+        None => Command::Tui,
+        Some(Subcommands::List { json }) => Command::List { json },
+        Some(Subcommands::Kill { port, force }) => Command::Kill { port, force },
+        Some(Subcommands::Snapshot { file }) => Command::Snapshot(
+            file.unwrap_or_else(|| PathBuf::from(crate::port::default_snapshot_name())),
+        ),
+        Some(Subcommands::Tunnels { command }) => match command {
+            TunnelsCommand::Status { short } => Command::TunnelStatus { short },
+            TunnelsCommand::ExportScript { file } => Command::ExportScript(file),
+        },
+    };
+    Cli {
+        options: args.options,
+        command,
+    }
+}
@@ -0,0 +1,21 @@
+//! System clipboard access for copying ports and ssh commands out of the TUI.
+
+use anyhow::{anyhow, Result};
+
+#[derive(Default)]
+pub struct Clipboard {
+    /// Kept open after the first copy: on X11 the contents vanish once the owner is dropped
+    inner: Option<arboard::Clipboard>,
+}
+
+impl Clipboard {
+    pub fn copy_to_clipboard(&mut self, text: &str) -> Result<()> {
+        if self.inner.is_none() {
+            self.inner = Some(arboard::Clipboard::new().map_err(|e| anyhow!("{}", e))?);
+        }
+        let clipboard = self.inner.as_mut().expect("clipboard opened above");
+        clipboard
+            .set_text(text.to_string())
+            .map_err(|e| anyhow!("{}", e))
+    }
+}
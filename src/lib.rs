@@ -0,0 +1,27 @@
+//! Core of anchor: port scanning and SSH tunnel management.
+//!
+//! The TUI binary is built on top of this crate; the same API can back other
+//! front ends such as a daemon or a web dashboard.
+//!
+//! ```no_run
+//! let ports = anchor::port::get_listening_ports(false)?;
+//! for port in ports.iter().filter(|p| p.state == "LISTEN") {
+//!     println!("{} {} ({})", port.port, port.process_name, port.pid);
+//! }
+//!
+//! let manager = anchor::tunnel::TunnelManager::load(anchor::tunnel::DEFAULT_PROFILE)?;
+//! for tunnel in &manager.tunnels {
+//!     println!("{}: {}", tunnel.name, tunnel.status_string());
+//! }
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+/// Socket scanning via `lsof` and process helpers
+pub mod port;
+/// User settings file
+pub mod settings;
+/// Saved SSH tunnels and their ssh processes
+pub mod tunnel;
+
+pub use port::{PortInfo, Scan, get_listening_ports, scan_ports};
+pub use tunnel::{TunnelConfig, TunnelManager};
@@ -1,44 +1,115 @@
 mod app;
-mod port;
-mod tunnel;
+mod cli;
+mod clipboard;
+mod notify;
+mod state;
+mod theme;
 mod ui;
 
+use anchor::{port, settings, tunnel};
+
 use anyhow::Result;
 use app::{App, AppTab};
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyModifiers},
+    cursor::Show,
+    event::{
+        self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyModifiers, MouseButton,
+        MouseEvent, MouseEventKind,
+    },
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use ratatui::{backend::CrosstermBackend, Terminal};
 use std::io;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 fn main() -> Result<()> {
-    // Setup terminal
-    enable_raw_mode()?;
-    let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
-    let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
+    let cli = cli::parse_args();
+
+    match &cli.command {
+        cli::Command::Snapshot(path) => {
+            let settings = settings::Settings::load();
+            let backend = settings.port_backend.unwrap_or_else(port::PortBackend::detect);
+            let ports = cli_scan(backend, cli.options.sudo)?;
+            port::write_snapshot(path, &ports)?;
+            println!("Wrote {} connections to {}", ports.len(), path.display());
+            return Ok(());
+        }
+        cli::Command::List { json } => {
+            let settings = settings::Settings::load();
+            let backend = settings.port_backend.unwrap_or_else(port::PortBackend::detect);
+            let ports = cli_scan(backend, cli.options.sudo)?;
+            if *json {
+                println!("{}", serde_json::to_string_pretty(&ports)?);
+            } else {
+                print_port_table(&ports);
+            }
+            return Ok(());
+        }
+        cli::Command::Kill { port, force } => {
+            let settings = settings::Settings::load();
+            if cli.options.read_only || settings.read_only {
+                anyhow::bail!("killing processes is disabled in read-only mode");
+            }
+            let all_killed = kill_port(*port, !*force, cli.options.sudo, &settings)?;
+            std::process::exit(if all_killed { 0 } else { 1 });
+        }
+        cli::Command::DumpLsof => {
+            dump_lsof()?;
+            return Ok(());
+        }
+        cli::Command::TunnelStatus { short } => {
+            let all_expected_up = print_tunnel_status(&cli.options.profile, *short)?;
+            std::process::exit(if all_expected_up { 0 } else { 1 });
+        }
+        cli::Command::ExportScript(path) => {
+            let manager = tunnel::TunnelManager::load(&cli.options.profile)?;
+            let script = tunnel::export_script(&manager.tunnels);
+            match path {
+                Some(path) => {
+                    std::fs::write(path, script)?;
+                    println!("Wrote {} tunnels to {}", manager.tunnels.len(), path.display());
+                }
+                None => print!("{}", script),
+            }
+            return Ok(());
+        }
+        cli::Command::Tui => {}
+    }
+
+    let settings = settings::Settings::load();
 
     // Create app state
-    let mut app = App::new()?;
+    let mut app = App::new(&cli.options, settings)?;
+    let mouse_capture = app.mouse_capture;
+
+    // Leave the terminal usable if anything panics while the TUI owns it
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let _ = suspend_terminal(mouse_capture);
+        default_hook(info);
+    }));
+
+    // Setup terminal
+    resume_terminal(mouse_capture)?;
+    let backend = CrosstermBackend::new(io::stdout());
+    let mut terminal = Terminal::new(backend)?;
 
     // Initial port scan
     app.refresh_ports()?;
+    app.restore_state(&state::UiState::load());
+    app.warn_missing_identity_files();
+    app.offer_privileged_scan();
+    app.auto_start_tunnels();
 
     // Main loop
     let res = run_app(&mut terminal, &mut app);
 
+    // Losing the saved position is not worth failing the exit over
+    let _ = app.ui_state().save();
+
     // Restore terminal
-    disable_raw_mode()?;
-    execute!(
-        terminal.backend_mut(),
-        LeaveAlternateScreen,
-        DisableMouseCapture
-    )?;
-    terminal.show_cursor()?;
+    suspend_terminal(mouse_capture)?;
 
     if let Err(err) = res {
         eprintln!("Error: {:?}", err);
@@ -47,116 +118,580 @@ fn main() -> Result<()> {
     Ok(())
 }
 
+/// Hand the terminal back to the shell: cooked mode, main screen, mouse and cursor released.
+fn suspend_terminal(mouse_capture: bool) -> io::Result<()> {
+    disable_raw_mode()?;
+    execute!(io::stdout(), LeaveAlternateScreen)?;
+    if mouse_capture {
+        execute!(io::stdout(), DisableMouseCapture)?;
+    }
+    execute!(io::stdout(), Show)
+}
+
+/// Take the terminal back for the TUI after `suspend_terminal`.
+fn resume_terminal(mouse_capture: bool) -> io::Result<()> {
+    enable_raw_mode()?;
+    execute!(io::stdout(), EnterAlternateScreen)?;
+    if mouse_capture {
+        execute!(io::stdout(), EnableMouseCapture)?;
+    }
+    Ok(())
+}
+
+/// Run an interactive shell with context about the selected row; returns when it exits.
+fn run_shell(handoff: &app::ShellHandoff) -> io::Result<std::process::ExitStatus> {
+    for line in &handoff.lines {
+        println!("{}", line);
+    }
+    println!("Exit the shell to return to anchor.");
+
+    let shell = std::env::var("SHELL").unwrap_or_else(|_| String::from("/bin/sh"));
+    std::process::Command::new(shell)
+        .envs(handoff.env.iter().map(|(key, value)| (key, value)))
+        .status()
+}
+
+/// Print each tunnel's state; returns whether every `expected_up` tunnel is up.
+fn print_tunnel_status(profile: &str, short: bool) -> Result<bool> {
+    let manager = tunnel::TunnelManager::load(profile)?;
+
+    let mut all_expected_up = true;
+    let mut entries = Vec::new();
+    for tunnel in &manager.tunnels {
+        let up = tunnel.is_connected();
+        if tunnel.expected_up && !up {
+            all_expected_up = false;
+        }
+
+        let state = if up { "up" } else { "down" };
+        if short {
+            entries.push(format!("{}:{}", tunnel.name, state));
+        } else {
+            println!("{:<20} {:<5} {}", tunnel.name, state, tunnel.forward_display());
+        }
+    }
+
+    if short {
+        println!("{}", entries.join(" "));
+    }
+    Ok(all_expected_up)
+}
+
+fn print_port_table(ports: &[port::PortInfo]) {
+    println!(
+        "{:<6} {:<8} {:<16} {:<10} {:<5} {:<12} ADDRESS",
+        "PORT", "PID", "PROCESS", "USER", "PROTO", "STATE"
+    );
+    for p in ports {
+        let address = if p.foreign_address.is_empty() {
+            p.local_address.clone()
+        } else {
+            format!("{} -> {}", p.local_address, p.foreign_address)
+        };
+        println!(
+            "{:<6} {:<8} {:<16} {:<10} {:<5} {:<12} {}",
+            p.port, p.pid, p.process_name, p.user, p.protocol, p.state, address
+        );
+    }
+}
+
+/// Scan for a one-shot command, warning on stderr when sudo wouldn't run it.
+fn cli_scan(backend: port::PortBackend, sudo: bool) -> Result<Vec<port::PortInfo>> {
+    let scan = port::scan_ports_with(backend, sudo)?;
+    if let Some(reason) = scan.sudo_refused {
+        eprintln!("warning: {}; scanned without sudo (run 'sudo -v' first)", reason);
+    }
+    Ok(scan.ports)
+}
+
+/// Kill every process listening on `port`, returning whether all of them are gone.
+fn kill_port(port: u16, graceful: bool, sudo: bool, settings: &settings::Settings) -> Result<bool> {
+    let backend = settings.port_backend.unwrap_or_else(port::PortBackend::detect);
+    let ports = cli_scan(backend, sudo)?;
+
+    // Clients connected to the port aren't what's occupying it
+    let mut owners: Vec<(i32, String)> = Vec::new();
+    for p in ports
+        .iter()
+        .filter(|p| p.port == port && p.state != "ESTABLISHED")
+    {
+        if !owners.iter().any(|(pid, _)| *pid == p.pid) {
+            owners.push((p.pid, p.process_name.clone()));
+        }
+    }
+    if owners.is_empty() {
+        eprintln!("Nothing is listening on port {}", port);
+        return Ok(false);
+    }
+
+    let pids: Vec<i32> = owners.iter().map(|(pid, _)| *pid).collect();
+    let grace = Duration::from_secs(settings.kill_grace_secs);
+    let outcomes = port::kill_pids(&pids, graceful, grace)?;
+
+    let mut all_killed = true;
+    for ((pid, name), outcome) in owners.iter().zip(outcomes) {
+        match outcome.error {
+            None => println!("Killed {} (PID {}) with SIG{}", name, pid, outcome.signal),
+            Some(e) => {
+                all_killed = false;
+                eprintln!("Failed to kill {} (PID {}): {}", name, pid, e);
+            }
+        }
+    }
+    Ok(all_killed)
+}
+
+/// Print exactly what the scan sees and how it was parsed.
+fn dump_lsof() -> Result<()> {
+    let run = port::run_lsof_scan(false)?;
+    let stdout = run.stdout;
+
+    println!("=== raw lsof output ===");
+    print!("{}", stdout);
+    println!();
+    if let Some(problem) = &run.problem {
+        println!("=== lsof reported problems (scan may be partial) ===");
+        println!("{}", problem);
+        println!();
+    }
+    println!("=== parsed ===");
+    for line in stdout.lines().skip(1) {
+        match port::parse_lsof_line(line) {
+            Some(info) => println!("{:?}", info),
+            None => println!("<skipped> {}", line),
+        }
+    }
+    Ok(())
+}
+
+/// Clicks select rows and tabs, the wheel moves the selection; ignored while a dialog is up.
+fn handle_mouse(app: &mut App, mouse: MouseEvent) -> Result<()> {
+    if app.dialog_open() {
+        return Ok(());
+    }
+    match mouse.kind {
+        MouseEventKind::Down(MouseButton::Left) => {
+            if let Some(tab) = ui::tab_at(app.tabs_area.get(), mouse.column, mouse.row) {
+                app.set_tab(tab);
+            } else if let Some(row) =
+                ui::table_row_at(app.table_area.get(), mouse.column, mouse.row)
+            {
+                app.click_row(row)?;
+            }
+        }
+        MouseEventKind::ScrollDown => app.select_next(),
+        MouseEventKind::ScrollUp => app.select_prev(),
+        _ => {}
+    }
+    Ok(())
+}
+
 fn run_app<B: ratatui::backend::Backend>(terminal: &mut Terminal<B>, app: &mut App) -> Result<()> {
+    let tick = Duration::from_millis(app.tick_ms);
+    let mut last_tick = Instant::now();
+
     loop {
+        // Background work runs on the tick; key presses redraw as soon as they arrive
+        if last_tick.elapsed() >= tick {
+            app.reconcile_tunnels();
+            app.auto_refresh_ports();
+            last_tick = Instant::now();
+        }
+        app.collect_scan();
+        app.collect_kill();
+        app.collect_restart();
         terminal.draw(|f| ui::draw(f, app))?;
 
-        // Poll for events with timeout for auto-refresh
-        if event::poll(Duration::from_millis(250))? {
-            if let Event::Key(key) = event::read()? {
-                // Global quit
-                if key.code == KeyCode::Char('q') && !app.show_input && !app.show_filter {
-                    return Ok(());
-                }
-                if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
-                    return Ok(());
-                }
-
-                // Handle input mode
-                if app.show_input {
-                    match key.code {
-                        KeyCode::Enter => app.submit_input()?,
-                        KeyCode::Esc => app.cancel_input(),
-                        KeyCode::Char(c) => app.input_buffer.push(c),
-                        KeyCode::Backspace => {
-                            app.input_buffer.pop();
-                        }
-                        _ => {}
-                    }
-                    continue;
-                }
-
-                // Handle filter mode
-                if app.show_filter {
-                    match key.code {
-                        KeyCode::Enter | KeyCode::Esc => {
-                            app.show_filter = false;
-                        }
-                        KeyCode::Char(c) => {
-                            app.filter_text.push(c);
-                            app.apply_filter();
-                        }
-                        KeyCode::Backspace => {
-                            app.filter_text.pop();
-                            app.apply_filter();
-                        }
-                        _ => {}
-                    }
-                    continue;
-                }
-
-                // Handle confirmation dialog
-                if app.show_confirm {
-                    match key.code {
-                        KeyCode::Char('y') | KeyCode::Char('Y') => {
-                            app.confirm_action()?;
-                        }
-                        KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
-                            app.show_confirm = false;
-                            app.confirm_message.clear();
-                        }
-                        _ => {}
-                    }
-                    continue;
-                }
-
-                // Normal mode key handling
+        if !event::poll(tick.saturating_sub(last_tick.elapsed()))? {
+            continue;
+        }
+        let event = event::read()?;
+        if let Event::Resize(..) = event {
+            // Repaint every cell at the new size rather than diffing against the old layout
+            terminal.clear()?;
+            continue;
+        }
+        if let Event::Mouse(mouse) = event {
+            handle_mouse(app, mouse)?;
+            continue;
+        }
+        if let Event::Key(key) = event {
+            // Help overlay swallows keys until closed
+            if app.show_help {
+                let size = terminal.size()?;
+                let max_scroll = ui::help_max_scroll(ratatui::layout::Rect::new(
+                    0,
+                    0,
+                    size.width,
+                    size.height,
+                ));
+                let page = (size.height / 2).max(1) as i32;
                 match key.code {
-                    // Tab navigation
-                    KeyCode::Tab => app.next_tab(),
-                    KeyCode::BackTab => app.prev_tab(),
-                    KeyCode::Char('1') => app.current_tab = AppTab::Ports,
-                    KeyCode::Char('2') => app.current_tab = AppTab::Tunnels,
-
-                    // List navigation
-                    KeyCode::Up | KeyCode::Char('k') => app.select_prev(),
-                    KeyCode::Down | KeyCode::Char('j') => app.select_next(),
-                    KeyCode::Home | KeyCode::Char('g') => app.select_first(),
-                    KeyCode::End | KeyCode::Char('G') => app.select_last(),
-
-                    // Actions
-                    KeyCode::Char('r') | KeyCode::F(5) => app.refresh_ports()?,
-                    KeyCode::Char('/') => {
-                        app.show_filter = true;
-                        app.filter_text.clear();
-                    }
-                    KeyCode::Char('K') => app.request_kill()?,
-                    KeyCode::Char('a') => {
-                        if matches!(app.current_tab, AppTab::Tunnels) {
-                            app.start_add_tunnel();
-                        }
-                    }
-                    KeyCode::Char('c') => {
-                        if matches!(app.current_tab, AppTab::Tunnels) {
-                            app.connect_tunnel()?;
-                        }
-                    }
-                    KeyCode::Char('d') => {
-                        if matches!(app.current_tab, AppTab::Tunnels) {
-                            app.disconnect_tunnel()?;
-                        }
-                    }
-                    KeyCode::Char('x') => {
-                        if matches!(app.current_tab, AppTab::Tunnels) {
-                            app.request_delete_tunnel()?;
-                        }
-                    }
-                    KeyCode::Esc => {
-                        app.filter_text.clear();
-                        app.apply_filter();
+                    KeyCode::Down | KeyCode::Char('j') => app.scroll_help(1, max_scroll),
+                    KeyCode::Up | KeyCode::Char('k') => app.scroll_help(-1, max_scroll),
+                    KeyCode::PageDown => app.scroll_help(page, max_scroll),
+                    KeyCode::PageUp => app.scroll_help(-page, max_scroll),
+                    KeyCode::Home | KeyCode::Char('g') => app.scroll_help(i32::MIN / 2, max_scroll),
+                    KeyCode::End | KeyCode::Char('G') => app.scroll_help(i32::MAX / 2, max_scroll),
+                    // Anything else closes it
+                    _ => app.toggle_help(),
+                }
+                continue;
+            }
+
+            // Kill log popup swallows keys until closed
+            if app.show_kill_log {
+                if matches!(
+                    key.code,
+                    KeyCode::Char('L') | KeyCode::Char('q') | KeyCode::Esc
+                ) {
+                    app.toggle_kill_log();
+                }
+                continue;
+            }
+
+            // Log viewer scrolls until closed
+            if app.log_view.is_some() {
+                let page = (terminal.size()?.height / 2).max(1) as i32;
+                match key.code {
+                    KeyCode::Up | KeyCode::Char('k') => app.scroll_tunnel_log(-1),
+                    KeyCode::Down | KeyCode::Char('j') => app.scroll_tunnel_log(1),
+                    KeyCode::PageUp => app.scroll_tunnel_log(-page),
+                    KeyCode::PageDown => app.scroll_tunnel_log(page),
+                    KeyCode::Home | KeyCode::Char('g') => app.scroll_tunnel_log(i32::MIN / 2),
+                    KeyCode::End | KeyCode::Char('G') => app.scroll_tunnel_log(i32::MAX / 2),
+                    KeyCode::Char('l') | KeyCode::Char('q') | KeyCode::Esc => app.log_view = None,
+                    _ => {}
+                }
+                continue;
+            }
+
+            // Test report stays up until dismissed; closing early abandons the test
+            if app.tunnel_test.is_some() {
+                if matches!(
+                    key.code,
+                    KeyCode::Char('T') | KeyCode::Char('q') | KeyCode::Esc | KeyCode::Enter
+                ) {
+                    app.tunnel_test = None;
+                }
+                continue;
+            }
+
+            // Command preview swallows keys until closed; y copies it
+            if app.command_preview.is_some() {
+                match key.code {
+                    KeyCode::Char('y') => app.copy_command_preview(),
+                    KeyCode::Char('C') | KeyCode::Char('q') | KeyCode::Esc | KeyCode::Enter => {
+                        app.command_preview = None;
+                    }
+                    _ => {}
+                }
+                continue;
+            }
+
+            // Signal picker swallows keys until a signal is chosen or it's closed
+            if app.signal_picker.is_some() {
+                match key.code {
+                    KeyCode::Down | KeyCode::Char('j') => app.move_signal_picker(true),
+                    KeyCode::Up | KeyCode::Char('k') => app.move_signal_picker(false),
+                    KeyCode::Enter => app.pick_signal()?,
+                    KeyCode::Esc | KeyCode::Char('q') => app.signal_picker = None,
+                    _ => {}
+                }
+                continue;
+            }
+
+            // Global quit
+            if key.code == KeyCode::Char('q')
+                && !app.show_input
+                && !app.show_filter
+                && !app.show_search
+                && app.tunnel_form.is_none()
+                && app.confirm_expected.is_none()
+            {
+                return Ok(());
+            }
+            if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+                return Ok(());
+            }
+
+            // Handle the add-tunnel form
+            if let Some(form) = &mut app.tunnel_form {
+                match key.code {
+                    KeyCode::Enter => app.submit_tunnel_form()?,
+                    KeyCode::Esc => app.cancel_tunnel_form(),
+                    KeyCode::Tab | KeyCode::Down => form.focus_next(),
+                    KeyCode::BackTab | KeyCode::Up => form.focus_prev(),
+                    KeyCode::Char('f') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        app.suggest_local_port()
+                    }
+                    KeyCode::Char(c) => form.push(c),
+                    KeyCode::Backspace => form.pop(),
+                    _ => {}
+                }
+                continue;
+            }
+
+            // Handle input mode
+            if app.show_input {
+                match key.code {
+                    KeyCode::Enter => app.submit_input()?,
+                    KeyCode::Esc => app.cancel_input(),
+                    KeyCode::Char('f') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        app.suggest_local_port()
+                    }
+                    KeyCode::Char(c) => app.input_insert(c),
+                    KeyCode::Backspace => app.input_backspace(),
+                    KeyCode::Delete => app.input_delete(),
+                    KeyCode::Left => {
+                        app.move_input_cursor(app.input_cursor_position().saturating_sub(1))
                     }
+                    KeyCode::Right => app.move_input_cursor(app.input_cursor_position() + 1),
+                    KeyCode::Home => app.move_input_cursor(0),
+                    KeyCode::End => app.move_input_cursor(usize::MAX),
+                    KeyCode::Down => app.move_host_suggestion(true),
+                    KeyCode::Up => app.move_host_suggestion(false),
+                    _ => {}
+                }
+                continue;
+            }
+
+            // Handle filter mode
+            if app.show_search {
+                match key.code {
+                    KeyCode::Enter => app.close_search(true),
+                    KeyCode::Esc => app.close_search(false),
+                    KeyCode::Char(c) => app.search_push(c),
+                    KeyCode::Backspace => app.search_pop(),
                     _ => {}
                 }
+                continue;
+            }
+
+            if app.show_filter {
+                match key.code {
+                    KeyCode::Enter | KeyCode::Esc => {
+                        app.show_filter = false;
+                    }
+                    KeyCode::Char(c) => app.filter_push(c),
+                    KeyCode::Backspace => app.filter_pop(),
+                    _ => {}
+                }
+                continue;
+            }
+
+            // Handle confirmation dialog
+            if app.show_confirm && app.confirm_expected.is_some() {
+                match key.code {
+                    KeyCode::Enter => app.confirm_action()?,
+                    KeyCode::Esc => app.cancel_confirm(),
+                    KeyCode::Char(c) => app.confirm_input.push(c),
+                    KeyCode::Backspace => {
+                        app.confirm_input.pop();
+                    }
+                    _ => {}
+                }
+                continue;
+            }
+            if app.show_confirm {
+                match key.code {
+                    KeyCode::Char('y') | KeyCode::Char('Y') => {
+                        app.confirm_action()?;
+                    }
+                    KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
+                        app.cancel_confirm();
+                    }
+                    KeyCode::Left
+                    | KeyCode::Right
+                    | KeyCode::Tab
+                    | KeyCode::BackTab
+                    | KeyCode::Char('h')
+                    | KeyCode::Char('l') => app.toggle_confirm_focus(),
+                    KeyCode::Enter => app.activate_confirm_focus()?,
+                    _ => {}
+                }
+                continue;
+            }
+
+            // Normal mode key handling
+            match key.code {
+                // Tab navigation
+                KeyCode::Tab => app.next_tab(),
+                KeyCode::BackTab => app.prev_tab(),
+                KeyCode::Char('1') => app.set_tab(AppTab::Ports),
+                KeyCode::Char('2') => app.set_tab(AppTab::Tunnels),
+                KeyCode::Char('3') => app.set_tab(AppTab::Connections),
+
+                // List navigation
+                KeyCode::Up | KeyCode::Char('k') => app.select_prev(),
+                KeyCode::Down | KeyCode::Char('j') => app.select_next(),
+                KeyCode::Home | KeyCode::Char('g') => app.select_first(),
+                KeyCode::End | KeyCode::Char('G') => app.select_last(),
+                KeyCode::PageDown => app.select_page(1),
+                KeyCode::PageUp => app.select_page(-1),
+
+                // Actions
+                KeyCode::Char('?') => app.toggle_help(),
+                KeyCode::Char('r') | KeyCode::F(5) => app.start_refresh(false),
+                KeyCode::Char('A') => app.toggle_auto_refresh(),
+                KeyCode::Char('T') => match app.current_tab {
+                    AppTab::Tunnels => app.start_tunnel_test(),
+                    AppTab::Ports | AppTab::Connections => app.cycle_theme(),
+                },
+                KeyCode::Char('/') => app.start_search(),
+                KeyCode::Char('f') => app.start_filter(),
+                KeyCode::Char('n') => app.search_next(true),
+                KeyCode::Char('N') => app.search_next(false),
+                KeyCode::Char(' ') => {
+                    if matches!(app.current_tab, AppTab::Ports) {
+                        app.toggle_port_marked();
+                    }
+                }
+                KeyCode::Char('K') => app.request_kill(true)?,
+                KeyCode::Char('X') => {
+                    if matches!(app.current_tab, AppTab::Ports | AppTab::Connections) {
+                        app.request_kill(false)?;
+                    }
+                }
+                KeyCode::Char('p') => {
+                    if matches!(app.current_tab, AppTab::Ports) {
+                        app.start_kill_pid();
+                    }
+                }
+                KeyCode::Char('U') if app.privileged => app.disable_privileged_scan()?,
+                KeyCode::Char('U') => {
+                    // sudo may need a password, so hand it the real terminal
+                    suspend_terminal(app.mouse_capture)?;
+                    let authorized = std::process::Command::new("sudo")
+                        .arg("-v")
+                        .status()
+                        .is_ok_and(|status| status.success());
+                    resume_terminal(app.mouse_capture)?;
+                    terminal.clear()?;
+
+                    app.enable_privileged_scan(authorized)?;
+                }
+                KeyCode::Char('R') => {
+                    if matches!(app.current_tab, AppTab::Ports) {
+                        app.request_restart()?;
+                    }
+                }
+                KeyCode::Char('!') => {
+                    if let Some(handoff) = app.shell_handoff() {
+                        suspend_terminal(app.mouse_capture)?;
+                        let result = run_shell(&handoff);
+                        resume_terminal(app.mouse_capture)?;
+                        terminal.clear()?;
+
+                        app.status_message = match result {
+                            Ok(_) => String::from("Back from shell"),
+                            Err(e) => format!("Failed to start shell: {}", e),
+                        };
+                    }
+                }
+                KeyCode::Char('L') => app.toggle_kill_log(),
+                KeyCode::Char('S') => app.snapshot()?,
+                KeyCode::Char('m') => {
+                    if matches!(app.current_tab, AppTab::Ports) {
+                        app.toggle_dual_stack_merge();
+                    }
+                }
+                KeyCode::Char('M') => {
+                    if matches!(app.current_tab, AppTab::Ports | AppTab::Connections) {
+                        app.toggle_mine_only();
+                    }
+                }
+                KeyCode::Char('v') => {
+                    if matches!(app.current_tab, AppTab::Ports) {
+                        app.toggle_group_by_process();
+                    }
+                }
+                KeyCode::Enter => {
+                    if matches!(app.current_tab, AppTab::Ports) {
+                        app.toggle_process_expanded();
+                    }
+                }
+                KeyCode::Char('t') => match app.current_tab {
+                    AppTab::Ports => app.cycle_proto_filter(),
+                    AppTab::Tunnels => app.test_tunnel(),
+                    AppTab::Connections => {}
+                },
+                KeyCode::Char('l') => match app.current_tab {
+                    AppTab::Ports => app.cycle_state_filter(),
+                    AppTab::Tunnels => app.show_tunnel_log(),
+                    AppTab::Connections => {}
+                },
+                KeyCode::Char('b') => {
+                    if matches!(app.current_tab, AppTab::Ports) {
+                        app.cycle_scope_filter();
+                    }
+                }
+                KeyCode::Char('s') => match app.current_tab {
+                    AppTab::Ports => app.cycle_port_sort(),
+                    AppTab::Tunnels => app.cycle_tunnel_sort(),
+                    AppTab::Connections => {}
+                },
+                KeyCode::Char('o') => {
+                    if matches!(app.current_tab, AppTab::Ports) {
+                        app.toggle_port_sort_direction();
+                    }
+                }
+                KeyCode::Char('w') => app.exposed_dismissed = true,
+                KeyCode::Char('a') => {
+                    if matches!(app.current_tab, AppTab::Tunnels) {
+                        app.start_add_tunnel(terminal.size()?.width);
+                    }
+                }
+                KeyCode::Char('e') => {
+                    if matches!(app.current_tab, AppTab::Tunnels) {
+                        app.start_edit_tunnel(terminal.size()?.width);
+                    }
+                }
+                KeyCode::Char('D') => {
+                    if matches!(app.current_tab, AppTab::Tunnels) {
+                        app.start_duplicate_tunnel(terminal.size()?.width);
+                    }
+                }
+                KeyCode::Char('c') => {
+                    if matches!(app.current_tab, AppTab::Tunnels) {
+                        app.connect_tunnel()?;
+                    }
+                }
+                KeyCode::Char('d') => {
+                    if matches!(app.current_tab, AppTab::Tunnels) {
+                        app.disconnect_tunnel()?;
+                    }
+                }
+                KeyCode::Char('C') => {
+                    if matches!(app.current_tab, AppTab::Tunnels) {
+                        app.show_command_preview();
+                    }
+                }
+                KeyCode::Char('P') => app.start_switch_profile(),
+                KeyCode::Char('E') => match app.current_tab {
+                    AppTab::Ports => app.start_export_ports(),
+                    AppTab::Tunnels => app.export_tunnel_script(),
+                    AppTab::Connections => {}
+                },
+                KeyCode::Char('I') => {
+                    if matches!(app.current_tab, AppTab::Tunnels) {
+                        app.import_ssh_config()?;
+                    }
+                }
+                KeyCode::Char('i') => match app.current_tab {
+                    AppTab::Ports => app.show_port_details = !app.show_port_details,
+                    AppTab::Tunnels => app.show_tunnel_details = !app.show_tunnel_details,
+                    AppTab::Connections => {}
+                },
+                KeyCode::Char('y') => app.copy_selection(),
+                KeyCode::Char('x') => match app.current_tab {
+                    AppTab::Ports | AppTab::Connections => app.open_signal_picker(),
+                    AppTab::Tunnels => app.request_delete_tunnel()?,
+                },
+                // Like :noh, the first Esc drops the search highlight
+                KeyCode::Esc if !app.search_text.is_empty() => app.search_text.clear(),
+                KeyCode::Esc => app.clear_filter(),
+                _ => {}
             }
         }
     }
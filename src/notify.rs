@@ -0,0 +1,51 @@
+//! Desktop notifications for events worth noticing from another window.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Minimum gap between notifications about the same subject, so flapping doesn't spam
+const THROTTLE: Duration = Duration::from_secs(30);
+
+pub struct Notifier {
+    enabled: bool,
+    last_sent: HashMap<String, Instant>,
+}
+
+impl Notifier {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            last_sent: HashMap::new(),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Show a notification unless disabled or `key` was notified about recently.
+    pub fn send(&mut self, key: &str, summary: &str, body: &str) {
+        if !self.enabled {
+            return;
+        }
+        let now = Instant::now();
+        if self
+            .last_sent
+            .get(key)
+            .is_some_and(|sent| now.duration_since(*sent) < THROTTLE)
+        {
+            return;
+        }
+        self.last_sent.insert(key.to_string(), now);
+
+        let (summary, body) = (summary.to_string(), body.to_string());
+        // Delivery can block on the session bus; without a notification daemon it just fails
+        std::thread::spawn(move || {
+            let _ = notify_rust::Notification::new()
+                .appname("anchor")
+                .summary(&summary)
+                .body(&body)
+                .show();
+        });
+    }
+}
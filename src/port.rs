@@ -1,84 +1,949 @@
+use crate::settings::ExposedService;
 use anyhow::Result;
-use std::process::Command;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-#[derive(Clone, Debug)]
+/// One socket from the scan
+#[derive(Clone, Debug, Default, Serialize)]
 pub struct PortInfo {
+    /// Local port (0 when lsof gave no parseable address)
     pub port: u16,
+    /// Owning process ID
     pub pid: i32,
+    /// Short command name from lsof
     pub process_name: String,
+    /// Owner as reported by lsof (login name, or UID when it has none)
+    pub user: String,
+    /// "TCP" or "UDP" ("???" when unknown)
     pub protocol: String,
+    /// Address family from lsof's TYPE column ("IPv4"/"IPv6"), empty if unknown
+    pub family: String,
+    /// "LISTEN", "ESTABLISHED" or "UNKNOWN"
     pub state: String,
+    /// Local bind address (`*` for all interfaces)
     pub local_address: String,
+    /// IP version of the local address, from the family when bound to `*`
+    pub ip_version: IpVersion,
+    /// Who can reach the local address: this machine only, every interface, or one network
+    pub scope: BindScope,
+    /// Remote end for established connections, empty otherwise
     pub foreign_address: String,
+    /// Set when the socket belongs to another network namespace (Linux containers)
+    pub container: Option<String>,
+    /// Connections waiting to be accepted, for LISTEN rows (Linux `ss` only)
+    pub accept_queue: Option<u32>,
+    /// Configured backlog limit, for LISTEN rows (Linux `ss` only)
+    pub backlog: Option<u32>,
+    /// Sockets held by this PID across the whole scan, before duplicates are dropped
+    pub socket_count: usize,
+    /// When the owning process started, in Unix seconds; `None` if it couldn't be read
+    pub start_time: Option<u64>,
+    /// Established connections on this port, for TCP LISTEN rows
+    pub connection_count: usize,
+    /// IPv6 address of the twin socket folded into this IPv4 row by the dual-stack merge
+    #[serde(skip)]
+    pub dual_stack_peer: Option<String>,
 }
 
-pub fn get_listening_ports() -> Result<Vec<PortInfo>> {
-    let mut ports = Vec::new();
+/// IP version of a socket's local address
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize)]
+pub enum IpVersion {
+    V4,
+    V6,
+    #[default]
+    Unknown,
+}
 
-    // Run lsof to get listening ports
-    // -iTCP -iUDP: Show TCP and UDP
-    // -sTCP:LISTEN,ESTABLISHED: Show listen and established states
-    // -P: Don't convert port numbers to names
-    // -n: Don't convert IP addresses to names
-    let output = Command::new("lsof")
-        .args(["-iTCP", "-iUDP", "-P", "-n"])
-        .output()?;
+impl IpVersion {
+    /// Version of `address`, falling back to the address `family` ("IPv4"/"IPv6") for `*`.
+    pub fn classify(address: &str, family: &str) -> Self {
+        match address.split('%').next().unwrap_or(address).parse::<std::net::IpAddr>() {
+            Ok(std::net::IpAddr::V4(_)) => IpVersion::V4,
+            Ok(std::net::IpAddr::V6(_)) => IpVersion::V6,
+            Err(_) => match family {
+                "IPv4" => IpVersion::V4,
+                "IPv6" => IpVersion::V6,
+                _ => IpVersion::Unknown,
+            },
+        }
+    }
 
-    if !output.status.success() {
-        // lsof might require sudo for some ports, but we'll work with what we get
-        return Ok(ports);
+    pub fn label(self) -> &'static str {
+        match self {
+            IpVersion::V4 => "v4",
+            IpVersion::V6 => "v6",
+            IpVersion::Unknown => "",
+        }
     }
+}
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
+/// Which interfaces a socket is bound to
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize)]
+pub enum BindScope {
+    /// 127.0.0.0/8 or ::1: reachable from this machine only
+    Loopback,
+    /// `*`, 0.0.0.0 or `::`: reachable from every network this machine is on
+    AllInterfaces,
+    /// One particular address, or one that couldn't be read
+    #[default]
+    Specific,
+}
+
+impl BindScope {
+    /// Scope of a local bind address as the backends report it.
+    pub fn classify(address: &str) -> Self {
+        let address = address.split('%').next().unwrap_or(address);
+        if address == "*" {
+            return BindScope::AllInterfaces;
+        }
+        match address.parse::<std::net::IpAddr>() {
+            Ok(ip) if ip.is_unspecified() => BindScope::AllInterfaces,
+            Ok(ip) if ip.is_loopback() => BindScope::Loopback,
+            Ok(std::net::IpAddr::V6(ip)) if ip.to_ipv4_mapped().is_some_and(|v4| v4.is_loopback()) => {
+                BindScope::Loopback
+            }
+            _ if address == "localhost" => BindScope::Loopback,
+            _ => BindScope::Specific,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            BindScope::Loopback => "loopback",
+            BindScope::AllInterfaces => "all interfaces",
+            BindScope::Specific => "specific address",
+        }
+    }
+}
+
+/// Arguments for the full socket scan
+/// -iTCP -iUDP: Show TCP and UDP
+/// -P: Don't convert port numbers to names
+/// -n: Don't convert IP addresses to names
+const LSOF_SCAN_ARGS: [&str; 4] = ["-iTCP", "-iUDP", "-P", "-n"];
 
-    for line in stdout.lines().skip(1) {
-        // Skip header line
-        if let Some(port_info) = parse_lsof_line(line) {
-            // Avoid duplicates
-            if !ports.iter().any(|p: &PortInfo| {
-                p.port == port_info.port
-                    && p.pid == port_info.pid
-                    && p.state == port_info.state
-            }) {
-                ports.push(port_info);
+/// Raw output of the scan's lsof command
+pub struct LsofRun {
+    pub stdout: String,
+    /// Why the listing may be incomplete (killed, or errors on stderr); `None` if it ran cleanly
+    pub problem: Option<String>,
+}
+
+/// Run the scan's lsof command, keeping whatever it printed even if it failed part way.
+///
+/// With `sudo` the scan runs through `sudo -n`, so it relies on cached credentials.
+pub fn run_lsof_scan(sudo: bool) -> Result<LsofRun> {
+    let output = if sudo {
+        Command::new("sudo")
+            .args(["-n", "lsof"])
+            .args(LSOF_SCAN_ARGS)
+            .output()?
+    } else {
+        Command::new("lsof").args(LSOF_SCAN_ARGS).output()?
+    };
+
+    // lsof exits 1 quietly when nothing matches, so only stderr or a signal means trouble
+    let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+    let problem = if output.status.code().is_none() {
+        Some(String::from("lsof was killed before finishing"))
+    } else if !stderr.is_empty() {
+        Some(stderr)
+    } else {
+        None
+    };
+
+    Ok(LsofRun {
+        stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+        problem,
+    })
+}
+
+/// Result of a scan, flagged when lsof may have missed sockets
+pub struct Scan {
+    pub ports: Vec<PortInfo>,
+    /// lsof's complaint when the listing may be partial
+    pub problem: Option<String>,
+    /// Why `sudo -n` refused a privileged scan, which then ran without sudo
+    pub sudo_refused: Option<String>,
+}
+
+/// Where socket listings come from, in order of preference
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PortBackend {
+    /// `lsof`, standard on macOS
+    Lsof,
+    /// `ss` from iproute2, for Linux boxes without lsof
+    Ss,
+    /// `/proc/net/{tcp,tcp6,udp,udp6}` read directly, when neither tool is installed
+    Proc,
+}
+
+impl PortBackend {
+    /// First backend that works on this machine, probed once per process.
+    pub fn detect() -> PortBackend {
+        static DETECTED: OnceLock<PortBackend> = OnceLock::new();
+        *DETECTED.get_or_init(|| {
+            if command_runs("lsof", &["-v"]) {
+                PortBackend::Lsof
+            } else if command_runs("ss", &["-V"]) {
+                PortBackend::Ss
+            } else if Path::new("/proc/net/tcp").exists() {
+                PortBackend::Proc
+            } else {
+                // Nothing works; lsof at least produces a clear error
+                PortBackend::Lsof
             }
+        })
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PortBackend::Lsof => "lsof",
+            PortBackend::Ss => "ss",
+            PortBackend::Proc => "/proc/net",
+        }
+    }
+}
+
+/// Whether `program` is installed and starts.
+fn command_runs(program: &str, args: &[&str]) -> bool {
+    Command::new(program)
+        .args(args)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok()
+}
+
+/// Scan all TCP and UDP sockets with the detected backend, deduplicated and sorted by port.
+///
+/// With `sudo` the scan runs through `sudo -n` to include root-owned sockets.
+pub fn scan_ports(sudo: bool) -> Result<Scan> {
+    scan_ports_with(PortBackend::detect(), sudo)
+}
+
+/// Scan all TCP and UDP sockets with a specific backend.
+///
+/// `sudo` applies to the command-line backends; `/proc/net` only resolves owners
+/// anchor is allowed to inspect.
+pub fn scan_ports_with(backend: PortBackend, sudo: bool) -> Result<Scan> {
+    let run = |sudo| match backend {
+        PortBackend::Ss => run_ss_scan(sudo),
+        _ => run_lsof_scan(sudo),
+    };
+    let mut sudo_refused = None;
+    let (mut ports, problem) = match backend {
+        PortBackend::Proc => (read_proc_net(), None),
+        PortBackend::Lsof | PortBackend::Ss => {
+            let listing = match run(sudo) {
+                Ok(listing) if sudo => match sudo_refusal(&listing) {
+                    Some(reason) => {
+                        sudo_refused = Some(reason);
+                        None
+                    }
+                    None => Some(listing),
+                },
+                Ok(listing) => Some(listing),
+                Err(e) if sudo => {
+                    sudo_refused = Some(format!("couldn't run sudo: {}", e));
+                    None
+                }
+                Err(e) => return Err(e),
+            };
+            // Some sockets beat none
+            let listing = match listing {
+                Some(listing) => listing,
+                None => run(false)?,
+            };
+            let ports = match backend {
+                PortBackend::Ss => parse_ss_output(&listing.stdout),
+                _ => parse_lsof_output(&listing.stdout),
+            };
+            (ports, listing.problem)
+        }
+    };
+
+    #[cfg(target_os = "linux")]
+    label_namespaced(&mut ports);
+    fill_start_times(&mut ports);
+
+    Ok(Scan {
+        ports,
+        problem,
+        sudo_refused,
+    })
+}
+
+/// sudo's own complaint when `sudo -n` refused to run the scan at all, e.g. for want of a
+/// cached password.
+fn sudo_refusal(run: &LsofRun) -> Option<String> {
+    let problem = run.problem.as_deref()?;
+    (run.stdout.trim().is_empty() && problem.starts_with("sudo:"))
+        .then(|| problem.lines().next().unwrap_or_default().to_string())
+}
+
+/// Scan all TCP and UDP sockets, ignoring whether the listing was complete.
+pub fn get_listening_ports(sudo: bool) -> Result<Vec<PortInfo>> {
+    Ok(scan_ports(sudo)?.ports)
+}
+
+/// Parse a full lsof listing into deduplicated rows sorted by port.
+pub fn parse_lsof_output(stdout: &str) -> Vec<PortInfo> {
+    // Skip header line
+    finish_rows(stdout.lines().skip(1).filter_map(parse_lsof_line))
+}
+
+/// Drop duplicate rows, sort by port and fill in per-process socket and connection counts.
+fn finish_rows(rows: impl Iterator<Item = PortInfo>) -> Vec<PortInfo> {
+    let mut ports = Vec::new();
+    let mut socket_counts: HashMap<i32, usize> = HashMap::new();
+
+    for port_info in rows {
+        *socket_counts.entry(port_info.pid).or_default() += 1;
+
+        // Avoid duplicates; the remote end tells connections on one port apart
+        if !ports.iter().any(|p: &PortInfo| {
+            p.port == port_info.port
+                && p.pid == port_info.pid
+                && p.state == port_info.state
+                && p.family == port_info.family
+                && p.foreign_address == port_info.foreign_address
+        }) {
+            ports.push(port_info);
         }
     }
 
     // Sort by port number
-    ports.sort_by(|a, b| a.port.cmp(&b.port));
+    ports.sort_by_key(|p| p.port);
+
+    // Counted after deduplication so a connection listed under several fds counts once
+    let connection_counts = count_connections(&ports);
+    for port in &mut ports {
+        port.socket_count = socket_counts.get(&port.pid).copied().unwrap_or(1);
+        if port.state == "LISTEN" && port.protocol == "TCP" {
+            port.connection_count = connection_counts.get(&port.port).copied().unwrap_or(0);
+        }
+    }
+
+    ports
+}
+
+/// Run `ss` over all TCP and UDP sockets with owning processes.
+fn run_ss_scan(sudo: bool) -> Result<LsofRun> {
+    // -t TCP, -u UDP, -a all states, -n numeric, -p owning processes
+    let args = ["-tuanp"];
+    let output = if sudo {
+        Command::new("sudo").args(["-n", "ss"]).args(args).output()?
+    } else {
+        Command::new("ss").args(args).output()?
+    };
+
+    let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+    Ok(LsofRun {
+        stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+        problem: (!output.status.success() || !stderr.is_empty()).then(|| {
+            if stderr.is_empty() {
+                format!("ss exited with {}", output.status)
+            } else {
+                stderr
+            }
+        }),
+    })
+}
+
+/// Parse `ss -tuanp` output; sockets without a visible owner are skipped, as lsof would.
+pub fn parse_ss_output(stdout: &str) -> Vec<PortInfo> {
+    let mut users = UserNames::default();
+    let mut rows = Vec::new();
+
+    for line in stdout.lines() {
+        // Netid State Recv-Q Send-Q Local:Port Peer:Port Process
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        if parts.len() < 6 || parts[0] == "Netid" {
+            continue;
+        }
+        let protocol = match parts[0] {
+            "tcp" => "TCP",
+            "udp" => "UDP",
+            _ => continue,
+        };
+        let Some((local, port)) = parse_address_port(parts[4]) else {
+            continue;
+        };
+        let foreign = if parts[5].ends_with(":*") {
+            String::new()
+        } else {
+            parts[5].to_string()
+        };
+        let state = socket_state(parts[1] == "LISTEN", &foreign);
+        let family = if parts[4].starts_with('[') { "IPv6" } else { "IPv4" };
+
+        // users:(("nginx",pid=10,fd=6),("nginx",pid=11,fd=6)) lists every owning process
+        let Some(process) = line.find("users:(").map(|i| &line[i..]) else {
+            continue;
+        };
+        for owner in process.split("((").flat_map(|s| s.split("),(")).skip(1) {
+            let Some((name, rest)) = owner.trim_start_matches('"').split_once("\",pid=") else {
+                continue;
+            };
+            let Some(pid) = rest.split(',').next().and_then(|pid| pid.parse::<i32>().ok()) else {
+                continue;
+            };
+            rows.push(PortInfo {
+                port,
+                pid,
+                process_name: name.to_string(),
+                user: users.owner_of(pid),
+                protocol: protocol.to_string(),
+                family: family.to_string(),
+                state: state.clone(),
+                ip_version: IpVersion::classify(&local, family),
+                scope: BindScope::classify(&local),
+                local_address: normalize_local_address(&local),
+                foreign_address: foreign.clone(),
+                socket_count: 1,
+                ..PortInfo::default()
+            });
+        }
+    }
+
+    finish_rows(rows.into_iter())
+}
+
+/// Read `/proc/net` socket tables, matching socket inodes to processes through `/proc/<pid>/fd`.
+fn read_proc_net() -> Vec<PortInfo> {
+    let owners = socket_owners();
+    let mut users = UserNames::default();
+    let mut names: HashMap<i32, String> = HashMap::new();
+    let mut rows = Vec::new();
 
-    Ok(ports)
+    for (table, protocol, family) in [
+        ("tcp", "TCP", "IPv4"),
+        ("tcp6", "TCP", "IPv6"),
+        ("udp", "UDP", "IPv4"),
+        ("udp6", "UDP", "IPv6"),
+    ] {
+        let Ok(content) = fs::read_to_string(format!("/proc/net/{}", table)) else {
+            continue;
+        };
+        for line in content.lines().skip(1) {
+            // sl local_address rem_address st tx:rx tr:when retrnsmt uid timeout inode
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            if parts.len() < 10 {
+                continue;
+            }
+            let Some(&pid) = parts[9].parse::<u64>().ok().and_then(|inode| owners.get(&inode))
+            else {
+                continue;
+            };
+            let (Some((local, port)), Some((remote, remote_port))) =
+                (parse_proc_address(parts[1]), parse_proc_address(parts[2]))
+            else {
+                continue;
+            };
+
+            let foreign = match (remote_port, remote) {
+                (0, _) => String::new(),
+                (port, std::net::IpAddr::V6(ip)) => format!("[{}]:{}", ip, port),
+                (port, ip) => format!("{}:{}", ip, port),
+            };
+            // 0A is TCP_LISTEN
+            let state = socket_state(protocol == "TCP" && parts[3] == "0A", &foreign);
+            let uid = parts[7].parse::<u32>().unwrap_or_default();
+
+            rows.push(PortInfo {
+                port,
+                pid,
+                process_name: names
+                    .entry(pid)
+                    .or_insert_with(|| {
+                        fs::read_to_string(format!("/proc/{}/comm", pid))
+                            .map(|comm| comm.trim().to_string())
+                            .unwrap_or_else(|_| String::from("?"))
+                    })
+                    .clone(),
+                user: users.name(uid),
+                protocol: protocol.to_string(),
+                family: family.to_string(),
+                state,
+                ip_version: IpVersion::classify(&local.to_string(), family),
+                scope: BindScope::classify(&local.to_string()),
+                local_address: normalize_local_address(&local.to_string()),
+                foreign_address: foreign,
+                socket_count: 1,
+                ..PortInfo::default()
+            });
+        }
+    }
+
+    finish_rows(rows.into_iter())
+}
+
+/// Map socket inodes to the PID holding them, for every process we may inspect.
+fn socket_owners() -> HashMap<u64, i32> {
+    let mut owners = HashMap::new();
+    let Ok(procs) = fs::read_dir("/proc") else {
+        return owners;
+    };
+    for entry in procs.flatten() {
+        let Some(pid) = entry.file_name().to_str().and_then(|name| name.parse::<i32>().ok())
+        else {
+            continue;
+        };
+        let Ok(fds) = fs::read_dir(entry.path().join("fd")) else {
+            continue;
+        };
+        for fd in fds.flatten() {
+            // Socket descriptors link to "socket:[<inode>]"
+            if let Ok(target) = fs::read_link(fd.path())
+                && let Some(inode) = target
+                    .to_str()
+                    .and_then(|t| t.strip_prefix("socket:["))
+                    .and_then(|t| t.strip_suffix(']'))
+                    .and_then(|t| t.parse::<u64>().ok())
+            {
+                owners.entry(inode).or_insert(pid);
+            }
+        }
+    }
+    owners
+}
+
+/// Decode a `/proc/net` address such as `0100007F:1F90` (hex IP in host byte order, hex port).
+fn parse_proc_address(field: &str) -> Option<(std::net::IpAddr, u16)> {
+    let (ip_hex, port_hex) = field.split_once(':')?;
+    let port = u16::from_str_radix(port_hex, 16).ok()?;
+
+    // The kernel prints each 32-bit word of the address as a native-endian number
+    let mut bytes = Vec::with_capacity(16);
+    for chunk in ip_hex.as_bytes().chunks(8) {
+        let word = u32::from_str_radix(std::str::from_utf8(chunk).ok()?, 16).ok()?;
+        bytes.extend(word.to_ne_bytes());
+    }
+    let ip = match bytes.len() {
+        4 => std::net::IpAddr::from(<[u8; 4]>::try_from(bytes).ok()?),
+        16 => std::net::IpAddr::from(<[u8; 16]>::try_from(bytes).ok()?),
+        _ => return None,
+    };
+    Some((ip, port))
+}
+
+/// State in lsof's terms: anything with a peer shows as established.
+fn socket_state(listening: bool, foreign: &str) -> String {
+    if !foreign.is_empty() {
+        String::from("ESTABLISHED")
+    } else if listening {
+        String::from("LISTEN")
+    } else {
+        String::from("UNKNOWN")
+    }
+}
+
+/// Show wildcard binds as `*` like lsof, and drop `%iface` scope suffixes.
+fn normalize_local_address(address: &str) -> String {
+    let address = address.split('%').next().unwrap_or(address);
+    match address {
+        "0.0.0.0" | "::" | "*" => String::from("*"),
+        _ => address.to_string(),
+    }
+}
+
+/// Login names by UID from /etc/passwd, and process owners from /proc, looked up lazily.
+#[derive(Default)]
+struct UserNames {
+    passwd: Option<HashMap<u32, String>>,
+    owners: HashMap<i32, String>,
+}
+
+impl UserNames {
+    /// Login name for `uid`, or the number itself when it has none (as lsof shows it).
+    fn name(&mut self, uid: u32) -> String {
+        let passwd = self.passwd.get_or_insert_with(|| {
+            fs::read_to_string("/etc/passwd")
+                .unwrap_or_default()
+                .lines()
+                .filter_map(|line| {
+                    let fields: Vec<&str> = line.split(':').collect();
+                    Some((fields.get(2)?.parse().ok()?, fields[0].to_string()))
+                })
+                .collect()
+        });
+        passwd.get(&uid).cloned().unwrap_or_else(|| uid.to_string())
+    }
+
+    /// Login name of the user running `pid`, empty if it can't be read.
+    fn owner_of(&mut self, pid: i32) -> String {
+        if let Some(owner) = self.owners.get(&pid) {
+            return owner.clone();
+        }
+        let uid = fs::read_to_string(format!("/proc/{}/status", pid))
+            .ok()
+            .and_then(|status| {
+                status
+                    .lines()
+                    .find_map(|line| line.strip_prefix("Uid:"))
+                    .and_then(|uids| uids.split_whitespace().next()?.parse::<u32>().ok())
+            });
+        let owner = uid.map(|uid| self.name(uid)).unwrap_or_default();
+        self.owners.insert(pid, owner.clone());
+        owner
+    }
 }
 
-fn parse_lsof_line(line: &str) -> Option<PortInfo> {
+/// Fill in accept queue depth and backlog for TCP listeners from `ss`.
+///
+/// Only Linux reports these cheaply; elsewhere the rows are left untouched.
+pub fn fill_listen_queues(ports: &mut [PortInfo]) {
+    #[cfg(target_os = "linux")]
+    {
+        // -l listening, -t TCP, -n numeric, -H no header
+        // For listeners Recv-Q is the accept queue and Send-Q the backlog limit
+        let Ok(output) = Command::new("ss").args(["-ltnH"]).output() else {
+            return;
+        };
+
+        let mut queues: HashMap<u16, (u32, u32)> = HashMap::new();
+        for line in String::from_utf8_lossy(&output.stdout).lines() {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            if parts.len() < 4 {
+                continue;
+            }
+            let (Ok(recv_q), Ok(send_q)) = (parts[1].parse::<u32>(), parts[2].parse::<u32>())
+            else {
+                continue;
+            };
+            let Some((_, port)) = parse_address_port(parts[3]) else {
+                continue;
+            };
+
+            // Dual-stack listeners show up twice; report the fuller queue
+            let entry = queues.entry(port).or_insert((recv_q, send_q));
+            if recv_q > entry.0 {
+                *entry = (recv_q, send_q);
+            }
+        }
+
+        for port in ports
+            .iter_mut()
+            .filter(|p| p.state == "LISTEN" && p.protocol == "TCP")
+        {
+            if let Some((recv_q, send_q)) = queues.get(&port.port) {
+                port.accept_queue = Some(*recv_q);
+                port.backlog = Some(*send_q);
+            }
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    let _ = ports;
+}
+
+/// Ports claimed by more than one process, mapped to the indices of the claiming rows.
+///
+/// Established connections don't count, since they share the local port with their
+/// listener; TCP and UDP on the same number are separate claims.
+pub fn find_conflicts(ports: &[PortInfo]) -> HashMap<u16, Vec<usize>> {
+    let mut claims: HashMap<(u16, &str), Vec<usize>> = HashMap::new();
+    for (i, p) in ports.iter().enumerate() {
+        if p.port != 0 && p.state != "ESTABLISHED" {
+            claims.entry((p.port, &p.protocol)).or_default().push(i);
+        }
+    }
+
+    let mut conflicts: HashMap<u16, Vec<usize>> = HashMap::new();
+    for ((port, _), rows) in claims {
+        let first_pid = ports[rows[0]].pid;
+        if rows.iter().any(|&i| ports[i].pid != first_pid) {
+            conflicts.entry(port).or_default().extend(rows);
+        }
+    }
+    conflicts
+}
+
+/// Established TCP connections per local port, to credit to the listener on that port.
+///
+/// Outgoing connections land on ephemeral ports no listener holds, so they drop out.
+pub fn count_connections(ports: &[PortInfo]) -> HashMap<u16, usize> {
+    let mut counts: HashMap<u16, usize> = HashMap::new();
+    for p in ports {
+        if p.port != 0 && p.state == "ESTABLISHED" && p.protocol == "TCP" {
+            *counts.entry(p.port).or_default() += 1;
+        }
+    }
+    counts
+}
+
+/// Sockets of one process, as indices into the list they were grouped from
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProcessGroup {
+    pub pid: i32,
+    pub sockets: Vec<usize>,
+}
+
+/// Group sockets by PID, keeping processes in the order their first socket appears.
+pub fn group_by_process<'a>(ports: impl IntoIterator<Item = &'a PortInfo>) -> Vec<ProcessGroup> {
+    let mut groups: Vec<ProcessGroup> = Vec::new();
+    let mut group_of: HashMap<i32, usize> = HashMap::new();
+    for (i, port) in ports.into_iter().enumerate() {
+        let group = *group_of.entry(port.pid).or_insert_with(|| {
+            groups.push(ProcessGroup {
+                pid: port.pid,
+                sockets: Vec::new(),
+            });
+            groups.len() - 1
+        });
+        groups[group].sockets.push(i);
+    }
+    groups
+}
+
+/// IPv4 listeners mapped to the IPv6 listener of the same process, port and protocol, as
+/// indices into `ports`; these are the pairs the dual-stack merge folds into one row.
+pub fn dual_stack_twins(ports: &[PortInfo]) -> HashMap<usize, usize> {
+    let listening = |family: &str, p: &PortInfo| p.family == family && p.state == "LISTEN";
+    let v6: HashMap<(i32, u16, &str), usize> = ports
+        .iter()
+        .enumerate()
+        .filter(|(_, p)| listening("IPv6", p))
+        .map(|(i, p)| ((p.pid, p.port, p.protocol.as_str()), i))
+        .collect();
+    ports
+        .iter()
+        .enumerate()
+        .filter(|(_, p)| listening("IPv4", p))
+        .filter_map(|(i, p)| Some((i, *v6.get(&(p.pid, p.port, p.protocol.as_str()))?)))
+        .collect()
+}
+
+/// Fold IPv6 listeners into the matching IPv4 row of the same process and port.
+///
+/// The IPv4 row survives with the IPv6 address recorded in `dual_stack_peer`.
+pub fn merge_dual_stack(ports: Vec<PortInfo>) -> Vec<PortInfo> {
+    let twins = dual_stack_twins(&ports);
+    let folded: HashSet<usize> = twins.values().copied().collect();
+    let peers: HashMap<usize, String> = twins
+        .iter()
+        .map(|(&v4, &v6)| (v4, ports[v6].local_address.clone()))
+        .collect();
+
+    ports
+        .into_iter()
+        .enumerate()
+        .filter(|(i, _)| !folded.contains(i))
+        .map(|(i, mut port)| {
+            port.dual_stack_peer = peers.get(&i).cloned();
+            port
+        })
+        .collect()
+}
+
+/// Describe listeners bound to every interface that match a sensitive service pattern.
+pub fn exposed_services(ports: &[PortInfo], patterns: &[ExposedService]) -> Vec<String> {
+    let mut exposed = Vec::new();
+    for port in ports {
+        if port.state != "LISTEN" || port.scope != BindScope::AllInterfaces {
+            continue;
+        }
+
+        let process = port.process_name.to_lowercase();
+        let matched = patterns.iter().find(|pattern| {
+            pattern.port.is_none_or(|p| p == port.port)
+                && pattern
+                    .process
+                    .as_ref()
+                    .is_none_or(|name| process.contains(&name.to_lowercase()))
+        });
+
+        if let Some(pattern) = matched {
+            let entry = format!("{} ({}:{})", pattern.label, port.process_name, port.port);
+            if !exposed.contains(&entry) {
+                exposed.push(entry);
+            }
+        }
+    }
+    exposed
+}
+
+/// Point-in-time dump of every socket, independent of any filter
+#[derive(Serialize)]
+struct Snapshot<'a> {
+    /// Seconds since the Unix epoch
+    taken_at: u64,
+    ports: &'a [PortInfo],
+}
+
+/// Seconds since the Unix epoch, for stamping scans and file names
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Write all `ports` with a timestamp to `path` as JSON.
+pub fn write_snapshot(path: &Path, ports: &[PortInfo]) -> Result<()> {
+    let mut content = Vec::new();
+    export_json(ports, unix_now(), &mut content)?;
+    fs::write(path, content)?;
+    Ok(())
+}
+
+/// Default snapshot file name, unique per second.
+pub fn default_snapshot_name() -> String {
+    format!("anchor-snapshot-{}.json", unix_now())
+}
+
+/// File format for exporting the port list
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    Json,
+    Csv,
+}
+
+impl ExportFormat {
+    /// Accept `json` or `csv`, with or without a leading `--` or `.`.
+    pub fn parse(input: &str) -> Result<Self> {
+        let name = input.trim().trim_start_matches('-').trim_start_matches('.');
+        match name.to_ascii_lowercase().as_str() {
+            "json" => Ok(Self::Json),
+            "csv" => Ok(Self::Csv),
+            _ => anyhow::bail!("expected json or csv, got '{}'", input.trim()),
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::Csv => "csv",
+        }
+    }
+
+    /// Write `ports`, scanned at `scanned_at` (Unix seconds), in this format.
+    pub fn write(self, ports: &[PortInfo], scanned_at: u64, writer: impl Write) -> Result<()> {
+        match self {
+            Self::Json => export_json(ports, scanned_at, writer),
+            Self::Csv => export_csv(ports, scanned_at, writer),
+        }
+    }
+}
+
+/// Default export file name, unique per second.
+pub fn default_export_name(format: ExportFormat) -> String {
+    format!("anchor-ports-{}.{}", unix_now(), format.extension())
+}
+
+/// Write `ports` as a pretty-printed JSON object with the scan time in `taken_at`.
+pub fn export_json(ports: &[PortInfo], scanned_at: u64, mut writer: impl Write) -> Result<()> {
+    let snapshot = Snapshot {
+        taken_at: scanned_at,
+        ports,
+    };
+    serde_json::to_writer_pretty(&mut writer, &snapshot)?;
+    writeln!(writer)?;
+    Ok(())
+}
+
+/// Columns of the CSV export, in order
+const CSV_HEADER: [&str; 16] = [
+    "scanned_at",
+    "port",
+    "pid",
+    "process_name",
+    "user",
+    "protocol",
+    "family",
+    "state",
+    "local_address",
+    "foreign_address",
+    "container",
+    "accept_queue",
+    "backlog",
+    "socket_count",
+    "connection_count",
+    "start_time",
+];
+
+/// Write `ports` as CSV with a header row, repeating the scan time on every row.
+pub fn export_csv(ports: &[PortInfo], scanned_at: u64, mut writer: impl Write) -> Result<()> {
+    writeln!(writer, "{}", CSV_HEADER.join(","))?;
+    let optional = |value: Option<u32>| value.map(|v| v.to_string()).unwrap_or_default();
+    for p in ports {
+        let fields = [
+            scanned_at.to_string(),
+            p.port.to_string(),
+            p.pid.to_string(),
+            p.process_name.clone(),
+            p.user.clone(),
+            p.protocol.clone(),
+            p.family.clone(),
+            p.state.clone(),
+            p.local_address.clone(),
+            p.foreign_address.clone(),
+            p.container.clone().unwrap_or_default(),
+            optional(p.accept_queue),
+            optional(p.backlog),
+            p.socket_count.to_string(),
+            p.connection_count.to_string(),
+            p.start_time.map(|t| t.to_string()).unwrap_or_default(),
+        ];
+        let row: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+        writeln!(writer, "{}", row.join(","))?;
+    }
+    Ok(())
+}
+
+/// Quote a CSV field if it contains a separator, quote or line break.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Parse one row of `lsof -iTCP -iUDP -P -n` output, or `None` if it isn't a socket.
+pub fn parse_lsof_line(line: &str) -> Option<PortInfo> {
     let parts: Vec<&str> = line.split_whitespace().collect();
 
-    if parts.len() < 9 {
+    // COMMAND PID USER FD TYPE DEVICE SIZE/OFF NODE is the minimum for a socket row
+    if parts.len() < 8 {
         return None;
     }
 
     let process_name = parts[0].to_string();
     let pid: i32 = parts[1].parse().ok()?;
+    let user = parts[2].to_string();
 
-    // Find the NAME column (usually last or second to last)
-    // Format is typically: COMMAND PID USER FD TYPE DEVICE SIZE/OFF NODE NAME
-    let name_idx = parts.len() - 1;
-    let name = parts[name_idx];
+    // Format is typically: COMMAND PID USER FD TYPE DEVICE SIZE/OFF NODE NAME [(STATE)]
+    // NAME is followed by the TCP state, so it isn't always the last column
+    let name_idx = 8;
+    let name = parts.get(name_idx).copied().unwrap_or("");
 
     // Parse the address:port
     // Format can be: *:port, localhost:port, 127.0.0.1:port, [::]:port, etc.
-    let (local_address, port) = if name.contains("->") {
+    let parsed = if name.contains("->") {
         // Established connection: local->remote
         let conn_parts: Vec<&str> = name.split("->").collect();
-        let local = conn_parts.first()?;
-        parse_address_port(local)?
+        conn_parts.first().and_then(|local| parse_address_port(local))
     } else {
-        parse_address_port(name)?
+        parse_address_port(name)
+    };
+    let (local_address, port) = match parsed {
+        Some(parsed) => parsed,
+        // Still a socket, just without a usable address: keep it so the list isn't short
+        None if is_socket_row(&parts) => (String::from("?"), 0),
+        None => return None,
     };
 
     // Determine protocol from TYPE column
-    let protocol = if parts.len() > 4 {
+    let protocol = if parts.len() > 7 {
         if parts[4].contains("TCP") || parts[7].contains("TCP") {
             "TCP".to_string()
         } else if parts[4].contains("UDP") || parts[7].contains("UDP") {
@@ -90,6 +955,12 @@ fn parse_lsof_line(line: &str) -> Option<PortInfo> {
         "???".to_string()
     };
 
+    let family = if parts[4].starts_with("IPv") {
+        parts[4].to_string()
+    } else {
+        String::new()
+    };
+
     // Determine state
     let state = if name.contains("->") {
         "ESTABLISHED".to_string()
@@ -126,13 +997,78 @@ fn parse_lsof_line(line: &str) -> Option<PortInfo> {
         port,
         pid,
         process_name,
+        user,
         protocol,
+        ip_version: IpVersion::classify(&local_address, &family),
+        scope: BindScope::classify(&local_address),
+        family,
         state,
         local_address,
         foreign_address,
+        container: None,
+        accept_queue: None,
+        backlog: None,
+        socket_count: 1,
+        start_time: None,
+        connection_count: 0,
+        dual_stack_peer: None,
     })
 }
 
+/// Whether an lsof row describes an internet socket, judging by its TYPE and NODE columns.
+fn is_socket_row(parts: &[&str]) -> bool {
+    parts[4].starts_with("IPv")
+        || ["TCP", "UDP"].iter().any(|proto| parts[7].contains(proto))
+}
+
+/// Label rows whose process lives outside our network namespace, or forwards into one.
+///
+/// Namespaces we aren't allowed to inspect are left unlabeled.
+#[cfg(target_os = "linux")]
+fn label_namespaced(ports: &mut [PortInfo]) {
+    let Ok(own_namespace) = fs::read_link("/proc/self/ns/net") else {
+        return;
+    };
+
+    let mut labels: HashMap<i32, Option<String>> = HashMap::new();
+    for port in ports.iter_mut() {
+        port.container = labels
+            .entry(port.pid)
+            .or_insert_with(|| container_label(port.pid, &own_namespace))
+            .clone();
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn container_label(pid: i32, own_namespace: &std::path::Path) -> Option<String> {
+    // docker-proxy sits in the host namespace but is really a stand-in for a container
+    if let Some(command) = process_command_line(pid) {
+        let args: Vec<&str> = command.split_whitespace().collect();
+        if args
+            .first()
+            .is_some_and(|program| program.ends_with("docker-proxy"))
+        {
+            let value_of = |flag: &str| {
+                args.iter()
+                    .position(|arg| *arg == flag)
+                    .and_then(|i| args.get(i + 1))
+            };
+            let ip = value_of("-container-ip");
+            let port = value_of("-container-port");
+            if let (Some(ip), Some(port)) = (ip, port) {
+                return Some(format!("container {}:{}", ip, port));
+            }
+        }
+    }
+
+    let namespace = fs::read_link(format!("/proc/{}/ns/net", pid)).ok()?;
+    if namespace != own_namespace {
+        Some(String::from("namespaced/container"))
+    } else {
+        None
+    }
+}
+
 fn parse_address_port(addr: &str) -> Option<(String, u16)> {
     // Handle IPv6 format like [::1]:port or [::]:port
     if addr.starts_with('[') {
@@ -155,22 +1091,678 @@ fn parse_address_port(addr: &str) -> Option<(String, u16)> {
     Some((ip.to_string(), port))
 }
 
-pub fn check_port(port: u16) -> Result<Option<PortInfo>> {
+/// Sockets using `port`, read with `backend`.
+///
+/// lsof can be asked about the one port; the other backends list everything and are filtered.
+pub fn sockets_on_port(backend: PortBackend, port: u16) -> Result<Vec<PortInfo>> {
+    if backend != PortBackend::Lsof {
+        let mut ports = scan_ports_with(backend, false)?.ports;
+        ports.retain(|p| p.port == port);
+        return Ok(ports);
+    }
+
+    // lsof ORs multiple -i selectors, so each one must carry the port
     let output = Command::new("lsof")
-        .args(["-iTCP", "-iUDP", "-P", "-n", &format!("-i:{}", port)])
+        .args([
+            "-P",
+            "-n",
+            &format!("-iTCP:{}", port),
+            &format!("-iUDP:{}", port),
+        ])
         .output()?;
 
+    // lsof exits non-zero when any selector matches nothing (e.g. no UDP socket on
+    // this port), so the output is checked rather than the status
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Ok(stdout.lines().skip(1).filter_map(parse_lsof_line).collect())
+}
+
+/// Find the first port from `start` upwards that nothing is using, skipping `reserved`.
+///
+/// A port counts as free when anchor could bind it on loopback itself, which is where a
+/// local forward listens; binding is instant, unlike asking lsof about each candidate.
+/// Gives up after a bounded number of candidates.
+pub fn next_free_port(start: u16, reserved: &[u16]) -> Option<u16> {
+    (start..=u16::MAX)
+        .filter(|port| !reserved.contains(port))
+        .take(100)
+        .find(|port| std::net::TcpListener::bind(("127.0.0.1", *port)).is_ok())
+}
+
+/// Resolve the full command line of a process, or `None` if it has exited.
+pub fn process_command_line(pid: i32) -> Option<String> {
+    process_argv(pid).map(|args| args.join(" "))
+}
+
+/// Resolve the argument vector of a process, or `None` if it has exited.
+///
+/// Without /proc the arguments come from `ps`, which can't preserve embedded spaces.
+pub fn process_argv(pid: i32) -> Option<Vec<String>> {
+    // Prefer /proc where available (Linux): argv is NUL-separated
+    if let Ok(raw) = fs::read(format!("/proc/{}/cmdline", pid)) {
+        let args: Vec<String> = raw
+            .split(|b| *b == 0)
+            .filter(|arg| !arg.is_empty())
+            .map(|arg| String::from_utf8_lossy(arg).into_owned())
+            .collect();
+        if !args.is_empty() {
+            return Some(args);
+        }
+    }
+
+    // Fall back to ps (macOS)
+    let output = Command::new("ps")
+        .args(["-p", &pid.to_string(), "-o", "command="])
+        .output()
+        .ok()?;
+
     if !output.status.success() {
-        return Ok(None);
+        return None;
     }
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
+    let command = String::from_utf8_lossy(&output.stdout);
+    let args: Vec<String> = command.split_whitespace().map(String::from).collect();
+    if args.is_empty() {
+        None
+    } else {
+        Some(args)
+    }
+}
+
+/// Short command name of a process, or `None` if it has exited.
+pub fn process_name(pid: i32) -> Option<String> {
+    let output = Command::new("ps")
+        .args(["-p", &pid.to_string(), "-o", "comm="])
+        .output()
+        .ok()?;
+
+    let name = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    // macOS reports the full executable path
+    let name = name.rsplit('/').next().unwrap_or_default().to_string();
+    (output.status.success() && !name.is_empty()).then_some(name)
+}
+
+/// What can be learned about a process beyond lsof's short command name
+#[derive(Clone, Debug, Serialize)]
+pub struct ProcessDetails {
+    pub command_line: String,
+    /// `None` if we're not allowed to see it
+    pub cwd: Option<PathBuf>,
+    pub user: Option<String>,
+    /// Unix seconds
+    pub start_time: Option<u64>,
+}
+
+/// Full command line, working directory and owner of a process, or `None` if it has exited.
+pub fn process_details(pid: i32) -> Option<ProcessDetails> {
+    let command_line = process_command_line(pid)?;
+    Some(ProcessDetails {
+        command_line,
+        cwd: process_cwd(pid),
+        user: process_user(pid),
+        start_time: process_start_time(pid),
+    })
+}
+
+/// When a process started, in Unix seconds.
+pub fn process_start_time(pid: i32) -> Option<u64> {
+    if let Ok(stat) = fs::read_to_string(format!("/proc/{}/stat", pid)) {
+        return proc_start_time(&stat);
+    }
 
-    for line in stdout.lines().skip(1) {
-        if let Some(port_info) = parse_lsof_line(line) {
-            return Ok(Some(port_info));
+    // macOS: ps prints lstart as a local date, but elapsed time needs no date parsing
+    let output = Command::new("ps")
+        .args(["-p", &pid.to_string(), "-o", "etime="])
+        .output()
+        .ok()?;
+    let elapsed = parse_etime(String::from_utf8_lossy(&output.stdout).trim())?;
+    unix_now().checked_sub(elapsed)
+}
+
+/// Start times of every process in the scan, looked up once per PID.
+fn fill_start_times(ports: &mut [PortInfo]) {
+    // Without /proc one ps call beats one per process
+    let listed: HashMap<i32, u64> = if Path::new("/proc/self/stat").exists() {
+        HashMap::new()
+    } else {
+        all_start_times()
+    };
+
+    let mut starts: HashMap<i32, Option<u64>> = HashMap::new();
+    for port in ports.iter_mut() {
+        port.start_time = *starts.entry(port.pid).or_insert_with(|| {
+            if listed.is_empty() {
+                process_start_time(port.pid)
+            } else {
+                listed.get(&port.pid).copied()
+            }
+        });
+    }
+}
+
+/// Start times of all processes from a single `ps`, for systems without /proc.
+fn all_start_times() -> HashMap<i32, u64> {
+    let Ok(output) = Command::new("ps").args(["-A", "-o", "pid=,etime="]).output() else {
+        return HashMap::new();
+    };
+    let now = unix_now();
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let (pid, etime) = line.trim().split_once(char::is_whitespace)?;
+            let elapsed = parse_etime(etime.trim())?;
+            Some((pid.parse().ok()?, now.checked_sub(elapsed)?))
+        })
+        .collect()
+}
+
+/// Start time from a `/proc/<pid>/stat` line: ticks after boot, plus the boot time.
+fn proc_start_time(stat: &str) -> Option<u64> {
+    // The command name may hold spaces and parentheses, so count fields from its end
+    let fields: Vec<&str> = stat.rsplit_once(')')?.1.split_whitespace().collect();
+    // Field 22 of the whole line; the state after the name is field 3
+    let ticks: u64 = fields.get(19)?.parse().ok()?;
+    Some(boot_time()? + ticks / clock_ticks())
+}
+
+/// Unix seconds at which the system booted, from /proc/stat.
+fn boot_time() -> Option<u64> {
+    static BOOT_TIME: OnceLock<Option<u64>> = OnceLock::new();
+    *BOOT_TIME.get_or_init(|| {
+        fs::read_to_string("/proc/stat")
+            .ok()?
+            .lines()
+            .find_map(|line| line.strip_prefix("btime "))?
+            .trim()
+            .parse()
+            .ok()
+    })
+}
+
+/// Kernel clock ticks per second, which /proc times are counted in.
+fn clock_ticks() -> u64 {
+    static CLOCK_TICKS: OnceLock<u64> = OnceLock::new();
+    *CLOCK_TICKS.get_or_init(|| {
+        Command::new("getconf")
+            .arg("CLK_TCK")
+            .output()
+            .ok()
+            .and_then(|output| String::from_utf8_lossy(&output.stdout).trim().parse().ok())
+            .filter(|&ticks| ticks > 0)
+            // What every mainstream Linux kernel uses
+            .unwrap_or(100)
+    })
+}
+
+/// Seconds from ps's `[[dd-]hh:]mm:ss` elapsed time.
+fn parse_etime(etime: &str) -> Option<u64> {
+    let (days, clock) = match etime.split_once('-') {
+        Some((days, clock)) => (days.parse::<u64>().ok()?, clock),
+        None => (0, etime),
+    };
+    let mut secs = 0;
+    for part in clock.split(':') {
+        secs = secs * 60 + part.parse::<u64>().ok()?;
+    }
+    Some(days * 86400 + secs)
+}
+
+/// Login name of the user running a process.
+fn process_user(pid: i32) -> Option<String> {
+    let output = Command::new("ps")
+        .args(["-p", &pid.to_string(), "-o", "user="])
+        .output()
+        .ok()?;
+
+    let user = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !user.is_empty()).then_some(user)
+}
+
+/// Resolve the working directory of a process, if we're allowed to see it.
+pub fn process_cwd(pid: i32) -> Option<PathBuf> {
+    if let Ok(cwd) = fs::read_link(format!("/proc/{}/cwd", pid)) {
+        return Some(cwd);
+    }
+
+    // macOS: lsof reports the cwd descriptor's path in its `n` field
+    if PortBackend::detect() != PortBackend::Lsof {
+        return None;
+    }
+    let output = Command::new("lsof")
+        .args(["-a", "-p", &pid.to_string(), "-d", "cwd", "-Fn"])
+        .output()
+        .ok()?;
+
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(|line| line.strip_prefix('n'))
+        .map(PathBuf::from)
+}
+
+/// Login name anchor runs as, for matching against lsof's USER column.
+pub fn current_user() -> String {
+    Command::new("id")
+        .arg("-un")
+        .output()
+        .ok()
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .filter(|user| !user.is_empty())
+        .or_else(|| std::env::var("USER").ok())
+        .unwrap_or_default()
+}
+
+/// Whether anchor itself is running as root.
+pub fn running_as_root() -> bool {
+    Command::new("id")
+        .arg("-u")
+        .output()
+        .is_ok_and(|output| String::from_utf8_lossy(&output.stdout).trim() == "0")
+}
+
+/// Send `kill -<signal>` to `pid`, returning kill's error output if it refused.
+pub fn send_signal(pid: i32, signal: &str) -> Result<Option<String>> {
+    let output = Command::new("kill")
+        .arg(format!("-{}", signal))
+        .arg(pid.to_string())
+        .output()?;
+    Ok((!output.status.success())
+        .then(|| String::from_utf8_lossy(&output.stderr).trim().to_string()))
+}
+
+/// Signals offered by the signal picker, most used first
+pub const PICKER_SIGNALS: [&str; 7] = ["TERM", "KILL", "HUP", "INT", "QUIT", "USR1", "USR2"];
+
+/// A picker signal's number on this platform, for display.
+///
+/// `kill` is always given the name, since USR1 and USR2 are numbered differently on macOS.
+pub fn signal_number(name: &str) -> Option<i32> {
+    Some(match name {
+        "HUP" => 1,
+        "INT" => 2,
+        "QUIT" => 3,
+        "KILL" => 9,
+        "TERM" => 15,
+        "USR1" if cfg!(target_os = "macos") => 30,
+        "USR2" if cfg!(target_os = "macos") => 31,
+        "USR1" => 10,
+        "USR2" => 12,
+        _ => return None,
+    })
+}
+
+/// How a kill attempt ended for one process
+#[derive(Clone, Debug)]
+pub struct KillOutcome {
+    pub pid: i32,
+    /// The last signal sent: "TERM" if the process exited from it, otherwise "KILL",
+    /// or whichever signal [`signal_pids`] was given
+    pub signal: &'static str,
+    /// `None` on success, otherwise kill's error output
+    pub error: Option<String>,
+}
+
+/// Send `signal` once to each process, without waiting for any to exit.
+pub fn signal_pids(pids: &[i32], signal: &'static str) -> Result<Vec<KillOutcome>> {
+    pids.iter()
+        .map(|&pid| {
+            Ok(KillOutcome {
+                pid,
+                signal,
+                error: send_signal(pid, signal)?,
+            })
+        })
+        .collect()
+}
+
+/// Kill one process; see [`kill_pids`].
+pub fn kill_pid(pid: i32, graceful: bool, grace: Duration) -> Result<KillOutcome> {
+    let mut outcomes = kill_pids(&[pid], graceful, grace)?;
+    Ok(outcomes.remove(0))
+}
+
+/// Kill processes with SIGKILL, or when `graceful` with SIGTERM first and SIGKILL
+/// for any still running after `grace`.
+pub fn kill_pids(pids: &[i32], graceful: bool, grace: Duration) -> Result<Vec<KillOutcome>> {
+    let first_signal = if graceful { "TERM" } else { "KILL" };
+    let mut outcomes = Vec::new();
+    for &pid in pids {
+        outcomes.push(KillOutcome {
+            pid,
+            signal: first_signal,
+            error: send_signal(pid, first_signal)?,
+        });
+    }
+
+    if graceful {
+        // Give every process the same window to clean up, rather than one after another
+        let deadline = Instant::now() + grace;
+        let signalled = |o: &KillOutcome| o.error.is_none();
+        while outcomes
+            .iter()
+            .any(|o| signalled(o) && process_alive(o.pid))
+            && Instant::now() < deadline
+        {
+            std::thread::sleep(Duration::from_millis(100));
+        }
+
+        for outcome in outcomes.iter_mut().filter(|o| signalled(o)) {
+            if process_alive(outcome.pid) {
+                outcome.signal = "KILL";
+                outcome.error = send_signal(outcome.pid, "KILL")?;
+            }
+        }
+    }
+    Ok(outcomes)
+}
+
+/// Processes whose loss takes down the machine, remote logins or the desktop
+pub const SYSTEM_PROCESSES: [&str; 5] = ["sshd", "systemd", "launchd", "init", "WindowServer"];
+
+/// Why killing this process would do more harm than stopping a service, if it would.
+pub fn is_protected_process(name: &str, pid: i32) -> Option<&'static str> {
+    if pid == 1 {
+        Some("PID 1 runs the whole system")
+    } else if pid as u32 == std::process::id() {
+        Some("this is anchor itself")
+    } else if SYSTEM_PROCESSES.contains(&name) {
+        Some("a core system process")
+    } else {
+        None
+    }
+}
+
+/// Whether a process with this PID is still running.
+///
+/// Zombies count as gone: they have exited and only wait for their parent to reap them.
+pub fn process_alive(pid: i32) -> bool {
+    if Path::new("/proc/self").exists() {
+        return fs::read_to_string(format!("/proc/{}/stat", pid))
+            .ok()
+            .and_then(|stat| proc_state(&stat))
+            .is_some_and(|state| !matches!(state, 'Z' | 'X'));
+    }
+
+    // Unlike `kill -0`, ps also sees other users' processes
+    Command::new("ps")
+        .args(["-o", "stat=", "-p", &pid.to_string()])
+        .output()
+        .is_ok_and(|output| {
+            let state = String::from_utf8_lossy(&output.stdout);
+            output.status.success() && !state.trim().is_empty() && !state.trim().starts_with('Z')
+        })
+}
+
+/// Process state letter from a `/proc/<pid>/stat` line, e.g. `R`, `S` or `Z`.
+fn proc_state(stat: &str) -> Option<char> {
+    // The command name may hold spaces and parentheses, so look after its end
+    stat.rsplit_once(')')?.1.trim_start().chars().next()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_lsof_line_reads_sockets() {
+        let listen = parse_lsof_line(
+            "nginx      412   root    6u  IPv4  12345      0t0  TCP *:80 (LISTEN)",
+        )
+        .expect("listener");
+        assert_eq!((listen.port, listen.pid), (80, 412));
+        assert_eq!((listen.protocol.as_str(), listen.state.as_str()), ("TCP", "LISTEN"));
+        assert_eq!(listen.local_address, "*");
+        assert_eq!(listen.ip_version, IpVersion::V4);
+
+        let established = parse_lsof_line(
+            "ssh      8624  alice   3u  IPv6 179223      0t0  TCP \
+             [::1]:53574->[::1]:22 (ESTABLISHED)",
+        )
+        .expect("connection");
+        assert_eq!(established.port, 53574);
+        assert_eq!(established.state, "ESTABLISHED");
+        assert_eq!(established.foreign_address, "[::1]:22");
+
+        let udp = parse_lsof_line("avahi-dae  701 avahi  12u  IPv4  15872      0t0  UDP *:5353")
+            .expect("udp socket");
+        assert_eq!((udp.port, udp.protocol.as_str()), (5353, "UDP"));
+    }
+
+    #[test]
+    fn parse_lsof_line_keeps_sockets_without_a_name() {
+        for line in [
+            "nginx      412   root    6u  IPv4  12345      0t0  TCP",
+            "java      2211  alice   40u  IPv6 881234      0t0  TCP",
+            "dnsmasq    903 nobody    4u  IPv4  20113      0t0  UDP garbage",
+        ] {
+            let port = parse_lsof_line(line).expect("still a socket");
+            assert_eq!(port.port, 0, "{}", line);
+            assert_eq!(port.local_address, "?", "{}", line);
         }
     }
 
-    Ok(None)
+    #[test]
+    fn parse_lsof_line_skips_everything_else() {
+        for line in [
+            "",
+            "COMMAND    PID   USER   FD   TYPE DEVICE SIZE/OFF NODE NAME",
+            "lsof: WARNING: can't stat() fuse.gvfsd-fuse file system /run/user/1000/gvfs",
+            "nginx      412   root",
+            "systemd      1   root   20u  unix 0xffff      0t0  3012 type=STREAM",
+        ] {
+            assert!(parse_lsof_line(line).is_none(), "{:?}", line);
+        }
+    }
+
+    #[test]
+    fn proc_state_reads_past_odd_command_names() {
+        assert_eq!(proc_state("412 (nginx) S 1 412 412 0 -1"), Some('S'));
+        assert_eq!(proc_state("9001 (my (weird) app) Z 1 9001"), Some('Z'));
+        assert_eq!(proc_state("garbage"), None);
+    }
+
+    #[test]
+    fn zombies_are_not_alive() {
+        let mut child = Command::new("true").spawn().expect("spawn true");
+        let pid = child.id() as i32;
+        // Not reaped yet, so once `true` exits it lingers as a zombie
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while process_alive(pid) {
+            assert!(Instant::now() < deadline, "exited process {} still counted alive", pid);
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        child.wait().expect("reap true");
+        assert!(!process_alive(pid));
+    }
+
+    #[test]
+    fn merge_dual_stack_folds_ipv6_twins() {
+        let socket = |port: u16, pid: i32, family: &str, state: &str, address: &str| PortInfo {
+            port,
+            pid,
+            protocol: String::from("TCP"),
+            family: family.to_string(),
+            state: state.to_string(),
+            local_address: address.to_string(),
+            ..PortInfo::default()
+        };
+        let merged = merge_dual_stack(vec![
+            socket(80, 10, "IPv4", "LISTEN", "*"),
+            socket(80, 10, "IPv6", "LISTEN", "*"),
+            // Another process on the same port isn't a twin
+            socket(443, 10, "IPv4", "LISTEN", "0.0.0.0"),
+            socket(443, 11, "IPv6", "LISTEN", "::"),
+            socket(5432, 12, "IPv6", "LISTEN", "::1"),
+            socket(80, 10, "IPv6", "ESTABLISHED", "::1"),
+        ]);
+
+        let rows: Vec<(u16, &str, Option<&str>)> = merged
+            .iter()
+            .map(|p| (p.port, p.family.as_str(), p.dual_stack_peer.as_deref()))
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                (80, "IPv4", Some("*")),
+                (443, "IPv4", None),
+                (443, "IPv6", None),
+                (5432, "IPv6", None),
+                (80, "IPv6", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn finish_rows_keeps_each_connection() {
+        let socket = |state: &str, foreign: &str| PortInfo {
+            port: 5432,
+            pid: 40,
+            protocol: String::from("TCP"),
+            family: String::from("IPv4"),
+            state: state.to_string(),
+            foreign_address: foreign.to_string(),
+            ..PortInfo::default()
+        };
+        let rows = finish_rows(
+            [
+                socket("LISTEN", ""),
+                socket("ESTABLISHED", "10.0.0.7:51000"),
+                socket("ESTABLISHED", "10.0.0.8:51000"),
+                // The same connection again under another fd
+                socket("ESTABLISHED", "10.0.0.8:51000"),
+            ]
+            .into_iter(),
+        );
+
+        let foreign: Vec<&str> = rows.iter().map(|p| p.foreign_address.as_str()).collect();
+        assert_eq!(foreign, vec!["", "10.0.0.7:51000", "10.0.0.8:51000"]);
+        assert_eq!(rows[0].connection_count, 2);
+    }
+
+    #[test]
+    fn next_free_port_skips_bound_and_reserved_ports() {
+        let taken = std::net::TcpListener::bind(("127.0.0.1", 0)).expect("bind a port");
+        let port = taken.local_addr().expect("bound address").port();
+        let next = next_free_port(port, &[]);
+        assert_ne!(next, Some(port));
+        if let Some(next) = next {
+            assert_ne!(next_free_port(next, &[next]), Some(next));
+        }
+    }
+
+    /// A socket with just the fields the grouping and counting helpers look at.
+    fn row(port: u16, pid: i32, protocol: &str, state: &str) -> PortInfo {
+        PortInfo {
+            port,
+            pid,
+            protocol: protocol.to_string(),
+            state: state.to_string(),
+            ..PortInfo::default()
+        }
+    }
+
+    #[test]
+    fn group_by_process_keeps_first_seen_order() {
+        let ports = [
+            row(8080, 30, "TCP", "LISTEN"),
+            row(80, 10, "TCP", "LISTEN"),
+            row(8081, 30, "TCP", "LISTEN"),
+            row(443, 10, "TCP", "LISTEN"),
+            row(53, 20, "UDP", "UNKNOWN"),
+        ];
+        assert_eq!(
+            group_by_process(&ports),
+            vec![
+                ProcessGroup {
+                    pid: 30,
+                    sockets: vec![0, 2],
+                },
+                ProcessGroup {
+                    pid: 10,
+                    sockets: vec![1, 3],
+                },
+                ProcessGroup {
+                    pid: 20,
+                    sockets: vec![4],
+                },
+            ]
+        );
+        assert!(group_by_process(&[]).is_empty());
+    }
+
+    #[test]
+    fn count_connections_counts_established_tcp_only() {
+        let ports = [
+            row(5432, 40, "TCP", "LISTEN"),
+            row(5432, 40, "TCP", "ESTABLISHED"),
+            row(5432, 41, "TCP", "ESTABLISHED"),
+            row(5432, 40, "TCP", "CLOSE_WAIT"),
+            row(53, 20, "UDP", "ESTABLISHED"),
+            // Sockets lsof gave no port for
+            row(0, 50, "TCP", "ESTABLISHED"),
+        ];
+        let counts = count_connections(&ports);
+        assert_eq!(counts.get(&5432), Some(&2));
+        assert_eq!(counts.len(), 1);
+    }
+
+    #[test]
+    fn find_conflicts_needs_two_processes_on_one_protocol() {
+        let ports = [
+            // Two processes listening on 8080
+            row(8080, 10, "TCP", "LISTEN"),
+            row(8080, 11, "TCP", "LISTEN"),
+            // One process with two sockets on 9000 is no conflict
+            row(9000, 20, "TCP", "LISTEN"),
+            row(9000, 20, "TCP", "LISTEN"),
+            // TCP and UDP on 53 are separate claims
+            row(53, 30, "TCP", "LISTEN"),
+            row(53, 31, "UDP", "UNKNOWN"),
+            // A client connected to 3000 shares its listener's port
+            row(3000, 40, "TCP", "LISTEN"),
+            row(3000, 41, "TCP", "ESTABLISHED"),
+            // Sockets without a port claim nothing
+            row(0, 50, "TCP", "LISTEN"),
+            row(0, 51, "TCP", "LISTEN"),
+        ];
+        let conflicts = find_conflicts(&ports);
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts.get(&8080), Some(&vec![0, 1]));
+    }
+
+    #[test]
+    fn bind_scope_classify() {
+        let cases = [
+            ("0.0.0.0", BindScope::AllInterfaces),
+            ("*", BindScope::AllInterfaces),
+            ("::", BindScope::AllInterfaces),
+            ("127.0.0.1", BindScope::Loopback),
+            ("::1", BindScope::Loopback),
+            ("::ffff:127.0.0.1", BindScope::Loopback),
+            ("localhost", BindScope::Loopback),
+            ("192.168.1.20", BindScope::Specific),
+            ("2001:db8::10", BindScope::Specific),
+            ("fe80::1%eth0", BindScope::Specific),
+        ];
+        for (address, scope) in cases {
+            assert_eq!(BindScope::classify(address), scope, "{}", address);
+        }
+    }
+
+    #[test]
+    fn ip_version_classify() {
+        let cases = [
+            ("0.0.0.0", "IPv4", IpVersion::V4),
+            ("127.0.0.1", "IPv4", IpVersion::V4),
+            ("*", "IPv4", IpVersion::V4),
+            ("*", "IPv6", IpVersion::V6),
+            ("*", "", IpVersion::Unknown),
+            ("::", "IPv6", IpVersion::V6),
+            ("::1", "IPv6", IpVersion::V6),
+            ("2001:db8::10", "IPv6", IpVersion::V6),
+            ("fe80::1%eth0", "IPv6", IpVersion::V6),
+        ];
+        for (address, family, version) in cases {
+            assert_eq!(IpVersion::classify(address, family), version, "{} {}", address, family);
+        }
+    }
 }
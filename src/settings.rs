@@ -0,0 +1,291 @@
+use crate::port::{PortBackend, PortInfo};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+/// A listener that shouldn't be reachable from other machines
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ExposedService {
+    /// Shown in the warning banner
+    pub label: String,
+    /// Port to match; any port if unset
+    #[serde(default)]
+    pub port: Option<u16>,
+    /// Case-insensitive substring of the process name; any process if unset
+    #[serde(default)]
+    pub process: Option<String>,
+}
+
+impl ExposedService {
+    fn builtin(label: &str, port: u16) -> Self {
+        Self {
+            label: label.to_string(),
+            port: Some(port),
+            process: None,
+        }
+    }
+}
+
+/// How rows of the tunnels table are colored
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TunnelColorMode {
+    /// Only the status column is colored
+    #[default]
+    None,
+    /// A stable color per tunnel group
+    Group,
+    /// Green/yellow/red by tunnel health
+    Health,
+}
+
+/// Column the ports table is ordered by
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortKey {
+    #[default]
+    Port,
+    Pid,
+    Process,
+    Protocol,
+    /// How long the owning process has been running
+    Age,
+}
+
+impl SortKey {
+    pub fn next(self) -> Self {
+        match self {
+            SortKey::Port => SortKey::Pid,
+            SortKey::Pid => SortKey::Process,
+            SortKey::Process => SortKey::Protocol,
+            SortKey::Protocol => SortKey::Age,
+            SortKey::Age => SortKey::Port,
+        }
+    }
+
+    /// Header of the column this key sorts
+    pub fn column(self) -> &'static str {
+        match self {
+            SortKey::Port => "Port",
+            SortKey::Pid => "PID",
+            SortKey::Process => "Process",
+            SortKey::Protocol => "Protocol",
+            SortKey::Age => "Age",
+        }
+    }
+
+    /// Order two sockets by this column, ascending.
+    pub fn compare(self, a: &PortInfo, b: &PortInfo) -> std::cmp::Ordering {
+        match self {
+            SortKey::Port => a.port.cmp(&b.port),
+            SortKey::Pid => a.pid.cmp(&b.pid),
+            SortKey::Process => a.process_name.to_lowercase().cmp(&b.process_name.to_lowercase()),
+            SortKey::Protocol => a.protocol.cmp(&b.protocol),
+            // Youngest first; unknown start times go last
+            SortKey::Age => match (a.start_time, b.start_time) {
+                (Some(a), Some(b)) => b.cmp(&a),
+                (a, b) => a.is_none().cmp(&b.is_none()),
+            },
+        }
+    }
+}
+
+/// Display order of the tunnels table; the saved file keeps its own order
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TunnelSort {
+    #[default]
+    Saved,
+    /// Connected tunnels first
+    Status,
+    Name,
+}
+
+impl TunnelSort {
+    pub fn next(self) -> Self {
+        match self {
+            TunnelSort::Saved => TunnelSort::Status,
+            TunnelSort::Status => TunnelSort::Name,
+            TunnelSort::Name => TunnelSort::Saved,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            TunnelSort::Saved => "saved order",
+            TunnelSort::Status => "status",
+            TunnelSort::Name => "name",
+        }
+    }
+}
+
+/// Built-in color palette for the UI
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ThemeName {
+    #[default]
+    Dark,
+    /// For terminals with a light background
+    Light,
+    /// Bright colors, blue/orange instead of green/red
+    HighContrast,
+}
+
+impl ThemeName {
+    pub fn next(self) -> Self {
+        match self {
+            ThemeName::Dark => ThemeName::Light,
+            ThemeName::Light => ThemeName::HighContrast,
+            ThemeName::HighContrast => ThemeName::Dark,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ThemeName::Dark => "dark",
+            ThemeName::Light => "light",
+            ThemeName::HighContrast => "high-contrast",
+        }
+    }
+}
+
+/// User settings from `~/.config/anchor/settings.json`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Disable kills and tunnel connect/disconnect/delete
+    pub read_only: bool,
+    /// Services to warn about when listening on all interfaces
+    pub exposed_services: Vec<ExposedService>,
+    /// How rows of the tunnels table are colored
+    pub tunnel_colors: TunnelColorMode,
+    /// Use text markers instead of colors (also enabled by NO_COLOR)
+    pub monochrome: bool,
+    /// Color palette: "dark", "light" or "high-contrast"
+    pub theme: ThemeName,
+    /// Show accept queue / backlog for listeners (runs `ss` on every scan, Linux only)
+    pub socket_stats: bool,
+    /// Capture the mouse; turn off to keep the terminal's own text selection
+    pub mouse_capture: bool,
+    /// Show an IPv4 and IPv6 listener of the same process and port as one row
+    pub merge_dual_stack: bool,
+    /// Process names whose kill/restart must be confirmed by typing the name
+    pub protected_processes: Vec<String>,
+    /// Also require typing `yes` for PID 1, anchor itself and core system processes
+    pub protect_system_processes: bool,
+    /// Kills affecting more processes than this need the count typed to confirm
+    pub batch_confirm_threshold: usize,
+    /// Seconds a kill waits after SIGTERM before escalating to SIGKILL
+    pub kill_grace_secs: u64,
+    /// Show how many sockets each process holds in the ports table
+    pub socket_count_column: bool,
+    /// Show how many established connections each listener has in the ports table
+    pub connection_count_column: bool,
+    /// Show how long each socket's process has been running in the ports table
+    pub age_column: bool,
+    /// Keep one filter across the Ports and Tunnels tabs instead of one per tab
+    pub shared_filter: bool,
+    /// ssh client used to start tunnels
+    pub ssh_binary: String,
+    /// Milliseconds between background updates; input is handled as it arrives
+    pub tick_ms: u64,
+    /// Rescan ports on a timer instead of only on `r`
+    pub auto_refresh: bool,
+    /// Seconds between automatic rescans
+    pub refresh_interval_secs: u64,
+    /// Column the ports table is sorted by, and in which direction
+    pub port_sort: SortKey,
+    pub port_sort_ascending: bool,
+    /// Order of the tunnels table
+    pub tunnel_sort: TunnelSort,
+    /// Desktop notifications when tunnels change state or services become exposed
+    pub notifications: bool,
+    /// Where to read sockets from: "lsof", "ss" or "proc"; detected at startup if unset
+    pub port_backend: Option<PortBackend>,
+    /// SSH hosts of recently saved tunnels, most recent first, offered when adding a tunnel
+    pub recent_hosts: Vec<String>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            read_only: false,
+            exposed_services: vec![
+                ExposedService::builtin("MySQL", 3306),
+                ExposedService::builtin("PostgreSQL", 5432),
+                ExposedService::builtin("Redis", 6379),
+                ExposedService::builtin("MongoDB", 27017),
+                ExposedService::builtin("Elasticsearch", 9200),
+                ExposedService::builtin("Memcached", 11211),
+                ExposedService::builtin("CouchDB", 5984),
+            ],
+            tunnel_colors: TunnelColorMode::default(),
+            monochrome: false,
+            theme: ThemeName::default(),
+            socket_stats: false,
+            mouse_capture: true,
+            merge_dual_stack: true,
+            protected_processes: crate::port::SYSTEM_PROCESSES.map(String::from).to_vec(),
+            protect_system_processes: true,
+            batch_confirm_threshold: 5,
+            kill_grace_secs: 3,
+            socket_count_column: true,
+            connection_count_column: true,
+            age_column: true,
+            shared_filter: false,
+            ssh_binary: String::from("ssh"),
+            tick_ms: 250,
+            auto_refresh: false,
+            refresh_interval_secs: 5,
+            port_sort: SortKey::default(),
+            port_sort_ascending: true,
+            tunnel_sort: TunnelSort::default(),
+            notifications: false,
+            port_backend: None,
+            recent_hosts: Vec::new(),
+        }
+    }
+}
+
+impl Settings {
+    /// Read the settings file, falling back to defaults if it's missing or invalid.
+    pub fn load() -> Self {
+        // A missing or unreadable settings file should never keep the TUI from starting
+        Self::get_config_path()
+            .ok()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    /// Write `changes` into the settings file, leaving every other key as the user wrote it.
+    ///
+    /// A file that doesn't parse is left alone, so a typo never costs the rest of the settings.
+    pub fn save_changes(changes: &[(&str, serde_json::Value)]) -> Result<()> {
+        let path = Self::get_config_path()?;
+        let mut settings: serde_json::Map<String, serde_json::Value> =
+            match fs::read_to_string(&path) {
+                Ok(content) => serde_json::from_str(&content)
+                    .with_context(|| format!("{} is not valid JSON", path.display()))?,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => serde_json::Map::new(),
+                Err(e) => return Err(e.into()),
+            };
+        for (key, value) in changes {
+            settings.insert(key.to_string(), value.clone());
+        }
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, serde_json::to_string_pretty(&settings)?)?;
+        Ok(())
+    }
+
+    fn get_config_path() -> Result<PathBuf> {
+        let config_dir = dirs::config_dir()
+            .ok_or_else(|| anyhow::anyhow!("Could not find config directory"))?;
+
+        Ok(config_dir.join("anchor").join("settings.json"))
+    }
+}
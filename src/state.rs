@@ -0,0 +1,46 @@
+use crate::app::AppTab;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+/// Where the user left off, restored on the next launch
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UiState {
+    pub tab: AppTab,
+    /// Port and process name of the selected port row
+    pub selected_port: Option<(u16, String)>,
+    /// PID of the selected port row, preferred while the same process is still running
+    pub selected_pid: Option<i32>,
+    /// Name of the selected tunnel
+    pub selected_tunnel: Option<String>,
+}
+
+impl UiState {
+    pub fn load() -> Self {
+        // Stale or corrupt state just means starting fresh
+        Self::get_state_path()
+            .ok()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = Self::get_state_path()?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::write(&path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    fn get_state_path() -> Result<PathBuf> {
+        let config_dir = dirs::config_dir()
+            .ok_or_else(|| anyhow::anyhow!("Could not find config directory"))?;
+
+        Ok(config_dir.join("anchor").join("state.json"))
+    }
+}
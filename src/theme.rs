@@ -0,0 +1,120 @@
+//! Color palettes for the UI, picked by the `theme` setting or `T` at runtime.
+
+use crate::settings::ThemeName;
+use ratatui::style::Color;
+
+/// Every color the UI draws with, by what it marks rather than how it looks
+pub struct Theme {
+    /// Ordinary text in prompts, tabs and the status bar
+    pub text: Color,
+    /// Hints, placeholders and other secondary text
+    pub muted: Color,
+    /// Closed sockets, disconnected tunnels, unfocused labels
+    pub inactive: Color,
+    /// App title, dialog borders, help keys and focused fields
+    pub accent: Color,
+    /// Table headers, help sections and the selected tab
+    pub header: Color,
+    /// LISTEN sockets
+    pub listen: Color,
+    /// ESTABLISHED sockets and remote ends
+    pub established: Color,
+    /// Connected tunnels, marks, successful kills
+    pub good: Color,
+    /// Conflicts, busy sockets, exposed listeners, reconnecting tunnels
+    pub warning: Color,
+    /// Errors, root-owned processes, kills and other destructive actions
+    pub danger: Color,
+    /// Sockets that just changed, privileged scans
+    pub flash: Color,
+    /// Background of the selected row
+    pub selection_bg: Color,
+    /// Background of rows matching the search
+    pub search_bg: Color,
+    /// Tunnel group colors, picked by a hash of the group name
+    pub groups: [Color; 6],
+}
+
+pub const DARK: Theme = Theme {
+    text: Color::White,
+    muted: Color::DarkGray,
+    inactive: Color::Gray,
+    accent: Color::Cyan,
+    header: Color::Yellow,
+    listen: Color::Green,
+    established: Color::Cyan,
+    good: Color::Green,
+    warning: Color::Yellow,
+    danger: Color::Red,
+    flash: Color::Magenta,
+    selection_bg: Color::DarkGray,
+    search_bg: Color::Blue,
+    groups: [
+        Color::Cyan,
+        Color::Magenta,
+        Color::Blue,
+        Color::LightGreen,
+        Color::LightYellow,
+        Color::LightRed,
+    ],
+};
+
+/// Dark text and deeper colors; yellow and cyan wash out on white
+pub const LIGHT: Theme = Theme {
+    text: Color::Black,
+    muted: Color::DarkGray,
+    inactive: Color::DarkGray,
+    accent: Color::Blue,
+    header: Color::Blue,
+    listen: Color::Green,
+    established: Color::Blue,
+    good: Color::Green,
+    warning: Color::Indexed(130),
+    danger: Color::Red,
+    flash: Color::Magenta,
+    selection_bg: Color::Indexed(252),
+    search_bg: Color::Indexed(153),
+    groups: [
+        Color::Blue,
+        Color::Magenta,
+        Color::Indexed(30),
+        Color::Green,
+        Color::Indexed(130),
+        Color::Red,
+    ],
+};
+
+/// Bright colors on dark; good and bad are blue and orange so they differ without red/green
+pub const HIGH_CONTRAST: Theme = Theme {
+    text: Color::White,
+    muted: Color::Gray,
+    inactive: Color::White,
+    accent: Color::LightCyan,
+    header: Color::LightYellow,
+    listen: Color::LightBlue,
+    established: Color::LightCyan,
+    good: Color::LightBlue,
+    warning: Color::LightYellow,
+    danger: Color::Indexed(208),
+    flash: Color::LightMagenta,
+    selection_bg: Color::Blue,
+    search_bg: Color::Indexed(238),
+    groups: [
+        Color::LightCyan,
+        Color::LightMagenta,
+        Color::LightBlue,
+        Color::LightYellow,
+        Color::White,
+        Color::Indexed(208),
+    ],
+};
+
+impl Theme {
+    pub fn named(name: ThemeName) -> &'static Theme {
+        match name {
+            ThemeName::Dark => &DARK,
+            ThemeName::Light => &LIGHT,
+            ThemeName::HighContrast => &HIGH_CONTRAST,
+        }
+    }
+}
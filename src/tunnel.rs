@@ -1,18 +1,202 @@
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
 use std::process::{Child, Command, Stdio};
+use std::sync::{Arc, OnceLock};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::{Duration, Instant};
 
+/// Which way a tunnel forwards traffic
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ForwardKind {
+    /// `-L`: local port forwarded to `remote_target` as seen from the ssh host
+    #[default]
+    Local,
+    /// `-R`: `remote_target` (port or bind:port) on the ssh host forwarded to the local port
+    Remote,
+    /// `-D`: SOCKS proxy on the local port
+    Dynamic,
+}
+
+impl ForwardKind {
+    /// Name as written in tunnels.toml
+    pub fn label(self) -> &'static str {
+        match self {
+            ForwardKind::Local => "local",
+            ForwardKind::Remote => "remote",
+            ForwardKind::Dynamic => "dynamic",
+        }
+    }
+
+    /// Parse a kind typed by the user: its name, initial or ssh flag, e.g. `dynamic`, `d` or `-D`.
+    pub fn parse(input: &str) -> Result<Self> {
+        match input.trim().trim_start_matches('-').to_lowercase().as_str() {
+            "" | "l" | "local" => Ok(ForwardKind::Local),
+            "r" | "remote" => Ok(ForwardKind::Remote),
+            "d" | "dynamic" | "socks" => Ok(ForwardKind::Dynamic),
+            other => bail!("'{}' is not local, remote or dynamic", other),
+        }
+    }
+}
+
+/// Profile used when none is given
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// How long after a timed-out connect a late-binding forward is still adopted
+const LATE_BIND_GRACE: Duration = Duration::from_secs(15);
+
+/// How long a health probe waits for the forward to answer
+const PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// How long a readiness check waits for the local port to accept a connection
+const READY_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// How long each signal gets to free the forward before `disconnect` escalates or gives up
+const DISCONNECT_GRACE: Duration = Duration::from_secs(2);
+
+/// Wait before the first reconnect of a dropped tunnel, doubled after each failure
+const RECONNECT_BACKOFF: Duration = Duration::from_secs(2);
+
+/// Longest wait between reconnect attempts
+const RECONNECT_BACKOFF_MAX: Duration = Duration::from_secs(120);
+
+/// Size at which a tunnel's ssh log is set aside and a new one started
+const SSH_LOG_MAX: u64 = 512 * 1024;
+
+/// Longest a pre-flight test may take, so a dead host can't keep its popup spinning
+const TEST_TIMEOUT: Duration = Duration::from_secs(8);
+
+/// How long after login a test waits for ssh to report that the remote target refused
+const TEST_TARGET_SETTLE: Duration = Duration::from_millis(1500);
+
+fn default_connect_timeout() -> u64 {
+    10
+}
+
+/// Coarse liveness of a tunnel for display
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TunnelHealth {
+    /// ssh is running
+    Up,
+    /// ssh is running but the forward failed its last probe
+    Degraded,
+    /// ssh is gone
+    Down,
+}
+
+/// What a pre-flight test of a tunnel found, without bringing its forward up
+#[derive(Clone, Debug)]
+pub struct TestReport {
+    /// Whether ssh logged in to the host
+    pub auth_ok: bool,
+    /// Whether the host could open the remote target; `None` when it wasn't checked
+    pub target_reachable: Option<bool>,
+    /// Time from starting ssh until it was logged in
+    pub latency: Option<Duration>,
+    /// ssh's last complaint, when something failed
+    pub error: Option<String>,
+}
+
+/// A saved SSH port forward
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct TunnelConfig {
+    /// Unique name shown in the table
     pub name: String,
+    /// ssh destination, e.g. `user@host` or an ssh config alias
     pub ssh_host: String,
+    /// Port on this machine
     pub local_port: u16,
-    pub remote_target: String, // host:port format
+    /// Other end of the forward, as `host:port`
+    pub remote_target: String,
+
+    /// Which way traffic is forwarded
+    #[serde(default)]
+    pub kind: ForwardKind,
+
+    /// Address the local end of a `-L`/`-D` forward listens on, e.g. `0.0.0.0` to share it
+    /// with the LAN; loopback if unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bind_address: Option<String>,
+
+    /// Private key passed to ssh with `-i`; `~` expands to the home directory
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub identity_file: Option<String>,
+
+    /// Port the ssh server listens on, passed with `-p`; ssh's default (usually 22) if unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ssh_port: Option<u16>,
+
+    /// Bastion to reach `ssh_host` through, passed with `-J` as `[user@]host[:port]`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jump_host: Option<String>,
+
+    /// Optional project/group name used to color related tunnels alike
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+
+    /// `anchor tunnels status` exits non-zero when this tunnel is down
+    #[serde(default)]
+    pub expected_up: bool,
+
+    /// Connect whenever anchor starts, whatever state it was left in
+    #[serde(default)]
+    pub auto_start: bool,
+
+    /// Reconnect with backoff whenever ssh dies, until disconnected by hand
+    #[serde(default)]
+    pub auto_reconnect: bool,
+
+    /// For dynamic tunnels, a `host:port` the SOCKS probe asks the proxy to reach
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub socks_test_target: Option<String>,
+
+    /// Seconds ssh may spend connecting before both it and anchor give up; `connect`
+    /// blocks that long, so the TUI calls it from a worker thread
+    #[serde(default = "default_connect_timeout")]
+    pub connect_timeout_secs: u64,
+
+    /// PID of the ssh process, when known
+    #[serde(skip)]
+    pub process: Option<u32>,
+
+    /// Set once the ssh `connect` started has exited; `None` for an ssh found by its PID
+    #[serde(skip)]
+    pub ssh_exited: Option<Arc<AtomicBool>>,
+
+    /// After a timed-out connect, keep watching until then in case ssh binds late
+    #[serde(skip)]
+    pub late_bind_deadline: Option<Instant>,
+
+    /// When anchor brought the forward up; `None` for one found already running at startup
+    #[serde(skip)]
+    pub connected_at: Option<Instant>,
+
+    /// Why the last `probe` failed, cleared when one succeeds
+    #[serde(skip)]
+    pub probe_failure: Option<String>,
+
+    /// Why the last reconnect attempt failed, cleared once one succeeds
+    #[serde(skip)]
+    pub last_error: Option<String>,
+
+    /// Reconnect attempts started since the tunnel dropped
+    #[serde(skip)]
+    pub reconnect_attempts: u32,
 
+    /// When the next reconnect is due, while an auto-reconnect tunnel is down
     #[serde(skip)]
-    pub process: Option<u32>, // PID of the SSH process
+    pub next_reconnect: Option<Instant>,
+
+    /// Set by `disconnect` so auto-reconnect leaves the tunnel down until it is connected again
+    #[serde(skip)]
+    pub reconnect_paused: bool,
 }
 
 impl Default for TunnelConfig {
@@ -22,94 +206,688 @@ impl Default for TunnelConfig {
             ssh_host: String::new(),
             local_port: 0,
             remote_target: String::new(),
+            kind: ForwardKind::default(),
+            bind_address: None,
+            identity_file: None,
+            ssh_port: None,
+            jump_host: None,
+            group: None,
+            expected_up: false,
+            auto_start: false,
+            auto_reconnect: false,
+            socks_test_target: None,
+            connect_timeout_secs: default_connect_timeout(),
             process: None,
+            ssh_exited: None,
+            late_bind_deadline: None,
+            connected_at: None,
+            probe_failure: None,
+            last_error: None,
+            reconnect_attempts: 0,
+            next_reconnect: None,
+            reconnect_paused: false,
         }
     }
 }
 
 impl TunnelConfig {
-    pub fn connect(&mut self) -> Result<()> {
-        // Build SSH command for local port forwarding
-        // ssh -L local_port:remote_host:remote_port -N -f ssh_host
-        let forward_spec = format!("{}:{}", self.local_port, self.remote_target);
-
-        let child: Child = Command::new("ssh")
-            .args([
-                "-L",
-                &forward_spec,
-                "-N",          // No remote command
-                "-f",          // Go to background
-                "-o",
-                "ExitOnForwardFailure=yes",
-                "-o",
-                "ServerAliveInterval=60",
-                "-o",
-                "ServerAliveCountMax=3",
-                &self.ssh_host,
-            ])
+    /// Start ssh (`ssh_binary`) for this tunnel and wait until the forward is up.
+    pub fn connect(&mut self, ssh_binary: &str) -> Result<()> {
+        self.late_bind_deadline = None;
+        self.reconnect_paused = false;
+
+        // Readiness is judged by the port answering, so it must not answer already
+        if self.kind != ForwardKind::Remote && self.forward_answers() {
+            bail!("Local port {} is already in use", self.local_port);
+        }
+
+        // Without -f ssh stays our child, so its exit status is never lost. stderr goes to a
+        // file rather than a pipe so ssh can keep writing after anchor exits, and its own
+        // process group keeps terminal signals aimed at anchor away from it
+        let log_path = self.ssh_log_path()?;
+        let mut log = open_ssh_log(&log_path)?;
+        writeln!(
+            log,
+            "--- connecting at unix time {}: {}",
+            crate::port::unix_now(),
+            self.command_preview(ssh_binary)
+        )?;
+        // Only this attempt's output explains a failure
+        let attempt_start = log.metadata()?.len() as usize;
+        let spawned = Command::new(ssh_binary)
+            .args(self.connect_args())
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(log)
+            .process_group(0)
+            .spawn();
+        let mut child: Child = match spawned {
+            Ok(child) => child,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                anyhow::bail!(
+                    "'{}' not found on PATH; install OpenSSH or set ssh_binary",
+                    ssh_binary
+                )
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        // Poll until the forward is up, ssh gives up, or the timeout passes
+        let deadline = Instant::now() + Duration::from_secs(self.connect_timeout_secs.max(1));
+        loop {
+            if self.forward_ready(&mut child)? {
+                self.process = Some(child.id());
+                self.connected_at = Some(Instant::now());
+                self.finish_reconnect(None);
+                // Reap ssh when it eventually exits, so it never lingers as a zombie, and
+                // flag the exit so checking the tunnel needn't probe the PID
+                let exited = Arc::new(AtomicBool::new(false));
+                self.ssh_exited = Some(Arc::clone(&exited));
+                std::thread::spawn(move || {
+                    let _ = child.wait();
+                    exited.store(true, Ordering::Release);
+                });
+                return Ok(());
+            }
+
+            // A foreground ssh only exits before the forward is up when it has failed
+            if let Some(status) = child.try_wait()? {
+                let log = fs::read_to_string(&log_path).unwrap_or_default();
+                let stderr = log.get(attempt_start..).unwrap_or_default().trim();
+                return Err(if stderr.is_empty() {
+                    anyhow::anyhow!("SSH tunnel failed to establish: ssh exited with {}", status)
+                } else {
+                    anyhow::anyhow!("SSH tunnel failed to establish: {}", stderr)
+                });
+            }
+
+            if Instant::now() >= deadline {
+                let _ = child.kill();
+                let _ = child.wait();
+                // A ControlMaster connection may still bring the forward up
+                self.late_bind_deadline = Some(Instant::now() + LATE_BIND_GRACE);
+                return Err(anyhow::anyhow!(
+                    "Timed out after {}s connecting to {}",
+                    self.connect_timeout_secs,
+                    self.ssh_host
+                ));
+            }
+
+            std::thread::sleep(Duration::from_millis(250));
+        }
+    }
+
+    /// Whether the freshly spawned ssh has its forward up.
+    ///
+    /// A remote forward listens on the ssh host, but ExitOnForwardFailure makes ssh exit if it
+    /// can't be set up, so a running ssh is as close as we can check from here.
+    fn forward_ready(&self, child: &mut Child) -> Result<bool> {
+        Ok(match self.kind {
+            ForwardKind::Remote => child.try_wait()?.is_none(),
+            ForwardKind::Local | ForwardKind::Dynamic => self.forward_answers(),
+        })
+    }
+
+    /// Whether anything accepts connections on the local port.
+    fn forward_answers(&self) -> bool {
+        TcpStream::connect_timeout(&self.local_addr(), READY_TIMEOUT).is_ok()
+    }
+
+    /// Where to reach the local end: the bind address if it is a specific IP, else loopback.
+    fn local_addr(&self) -> std::net::SocketAddr {
+        let ip = self
+            .bind_address
+            .as_deref()
+            .and_then(|bind| bind.parse::<std::net::IpAddr>().ok())
+            .map(|ip| match ip {
+                // A wildcard bind also listens on loopback
+                std::net::IpAddr::V6(ip) if ip.is_unspecified() => {
+                    std::net::IpAddr::V6(std::net::Ipv6Addr::LOCALHOST)
+                }
+                ip if ip.is_unspecified() => std::net::IpAddr::V4(std::net::Ipv4Addr::LOCALHOST),
+                ip => ip,
+            })
+            .unwrap_or(std::net::IpAddr::V4(std::net::Ipv4Addr::LOCALHOST));
+        std::net::SocketAddr::new(ip, self.local_port)
+    }
+
+    /// The local listener as ssh writes it: `[bind_address:]port`, bracketing IPv6.
+    fn listen_spec(&self) -> String {
+        match &self.bind_address {
+            Some(bind) => format_endpoint(bind, self.local_port),
+            None => self.local_port.to_string(),
+        }
+    }
+
+    /// Where `connect` appends ssh's stderr, kept across connects and restarts of anchor.
+    pub fn ssh_log_path(&self) -> Result<PathBuf> {
+        Ok(ssh_log_dir()?.join(format!("{}.log", log_file_stem(&self.name))))
+    }
+
+    /// Arguments for a standalone ssh that backgrounds itself, with `~` left unexpanded.
+    pub fn build_ssh_args(&self) -> Vec<String> {
+        self.ssh_args(true)
+    }
+
+    /// ssh arguments; `background` adds `-f`, which `connect` avoids so it keeps the process.
+    fn ssh_args(&self, background: bool) -> Vec<String> {
+        // ssh -L local_port:remote_host:remote_port -N [-f] ssh_host
+        let mut args = self.login_args();
+        args.extend([
+            self.forward_flag().to_string(),
+            self.forward_spec(),
+            String::from("-N"), // No remote command
+        ]);
+        if background {
+            args.push(String::from("-f")); // Go to background
+        }
+        args.extend([
+            String::from("-o"),
+            String::from("ExitOnForwardFailure=yes"),
+            String::from("-o"),
+            String::from("ServerAliveInterval=60"),
+            String::from("-o"),
+            String::from("ServerAliveCountMax=3"),
+            String::from("-o"),
+            format!("ConnectTimeout={}", self.connect_timeout_secs),
+            self.ssh_host.clone(),
+        ]);
+        args
+    }
+
+    /// Key, port and jump host options: how to reach and log in to the host.
+    fn login_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if let Some(identity_file) = &self.identity_file {
+            args.extend([String::from("-i"), identity_file.clone()]);
+        }
+        if let Some(port) = self.ssh_port {
+            args.extend([String::from("-p"), port.to_string()]);
+        }
+        if let Some(jump_host) = &self.jump_host {
+            args.extend([String::from("-J"), jump_host.clone()]);
+        }
+        args
+    }
+
+    /// Log in without forwarding anything, and for local forwards have the host open the
+    /// remote target through ssh's stdio forwarding (`-W`).
+    ///
+    /// Runs ssh in batch mode, so a host wanting a password fails rather than prompting,
+    /// and gives up after `TEST_TIMEOUT`.
+    pub fn test(&self, ssh_binary: &str) -> Result<TestReport> {
+        let timeout = TEST_TIMEOUT.as_secs().min(self.connect_timeout_secs.max(1));
+        let mut args = self.login_args();
+        // -v is how ssh says when login has finished
+        args.extend([
+            String::from("-v"),
+            String::from("-o"),
+            String::from("BatchMode=yes"),
+            String::from("-o"),
+            format!("ConnectTimeout={}", timeout),
+        ]);
+        let check_target = self.kind == ForwardKind::Local;
+        if check_target {
+            let target = match parse_endpoint(&self.remote_target) {
+                Ok((host, port)) => format_endpoint(&host, port),
+                Err(_) => self.remote_target.clone(),
+            };
+            args.extend([String::from("-W"), target]);
+        }
+        args.push(self.ssh_host.clone());
+        if !check_target {
+            args.push(String::from("true"));
+        }
+
+        let started = Instant::now();
+        let spawned = Command::new(ssh_binary)
+            .args(args.iter().map(|arg| expand_tilde(arg)))
             .stdin(Stdio::null())
             .stdout(Stdio::null())
             .stderr(Stdio::piped())
-            .spawn()?;
+            .process_group(0)
+            .spawn();
+        let mut child = match spawned {
+            Ok(child) => child,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                bail!("'{}' not found on PATH; install OpenSSH or set ssh_binary", ssh_binary)
+            }
+            Err(e) => return Err(e.into()),
+        };
 
-        // Give SSH a moment to establish or fail
-        std::thread::sleep(std::time::Duration::from_millis(500));
+        // Read stderr on its own thread so the deadline holds while ssh is silent
+        let stderr = child.stderr.take().expect("stderr is piped");
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            for line in BufReader::new(stderr).lines().map_while(|line| line.ok()) {
+                if tx.send(line).is_err() {
+                    break;
+                }
+            }
+        });
 
-        // Check if the port is now in use (indicating success)
-        if self.is_connected() {
-            // Find the actual SSH process PID
-            if let Some(pid) = self.find_ssh_pid() {
-                self.process = Some(pid);
+        let deadline = started + TEST_TIMEOUT;
+        let mut report = TestReport {
+            auth_ok: false,
+            target_reachable: None,
+            latency: None,
+            error: None,
+        };
+        loop {
+            // Once logged in, a target that isn't refused soon is taken as reachable
+            let until = match report.latency {
+                Some(latency) => (started + latency + TEST_TARGET_SETTLE).min(deadline),
+                None => deadline,
+            };
+            let line = match rx.recv_timeout(until.saturating_duration_since(Instant::now())) {
+                Ok(line) => line,
+                Err(mpsc::RecvTimeoutError::Timeout) if report.auth_ok => {
+                    report.target_reachable = Some(true);
+                    break;
+                }
+                Err(mpsc::RecvTimeoutError::Timeout) => {
+                    report.error = Some(format!("no answer within {}s", TEST_TIMEOUT.as_secs()));
+                    break;
+                }
+                // ssh has exited
+                Err(mpsc::RecvTimeoutError::Disconnected) => break,
+            };
+
+            if line.contains("Authenticated to") || line.contains("Authentication succeeded") {
+                report.auth_ok = true;
+                report.latency = Some(started.elapsed());
+                if !check_target {
+                    break;
+                }
+            } else if line.contains("open failed") {
+                report.target_reachable = Some(false);
+                report.error = Some(line.trim_start_matches("channel 0: ").to_string());
+                break;
+            } else if !line.starts_with("debug") && !line.starts_with("OpenSSH_") {
+                report.error = Some(line);
             }
-            Ok(())
-        } else {
-            // Try to get error message
-            let output = child.wait_with_output()?;
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            Err(anyhow::anyhow!(
-                "SSH tunnel failed to establish: {}",
-                stderr.trim()
-            ))
+        }
+
+        let _ = child.kill();
+        let status = child.wait()?;
+        if report.auth_ok && check_target && report.target_reachable.is_none() {
+            // The target answered and closed before the settle time was up
+            report.target_reachable = Some(status.success());
+        }
+        if report.auth_ok && report.target_reachable != Some(false) {
+            report.error = None;
+        } else if report.error.is_none() {
+            report.error = Some(format!("ssh exited with {}", status));
+        }
+        Ok(report)
+    }
+
+    /// Exactly the arguments `connect` passes to ssh: foreground, with `~` expanded.
+    fn connect_args(&self) -> Vec<String> {
+        self.ssh_args(false)
+            .iter()
+            .map(|arg| expand_tilde(arg).to_string_lossy().into_owned())
+            .collect()
+    }
+
+    /// What `connect` will run, quoted for pasting into a shell to debug auth problems.
+    pub fn command_preview(&self, ssh_binary: &str) -> String {
+        std::iter::once(ssh_binary.to_string())
+            .chain(self.connect_args())
+            .map(|word| shell_quote(&word))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// The ssh invocation as a line that can be pasted into a POSIX shell.
+    pub fn shell_command(&self) -> String {
+        let mut words = vec![String::from("ssh")];
+        words.extend(self.build_ssh_args().iter().map(|arg| shell_quote(arg)));
+        words.join(" ")
+    }
+
+    /// Adopt a forward that came up after `connect` gave up; true once it has.
+    ///
+    /// Stops watching when the grace period runs out, without spawning anything.
+    pub fn reconcile_late_bind(&mut self) -> bool {
+        let Some(deadline) = self.late_bind_deadline else {
+            return false;
+        };
+
+        if let Some(pid) = self.find_ssh_pid() {
+            self.process = Some(pid);
+            self.ssh_exited = None;
+            self.connected_at = Some(Instant::now());
+            self.late_bind_deadline = None;
+            return true;
+        }
+        if Instant::now() >= deadline {
+            self.late_bind_deadline = None;
+        }
+        false
+    }
+
+    fn forward_flag(&self) -> &'static str {
+        match self.kind {
+            ForwardKind::Local => "-L",
+            ForwardKind::Remote => "-R",
+            ForwardKind::Dynamic => "-D",
+        }
+    }
+
+    fn forward_spec(&self) -> String {
+        match self.kind {
+            // Normalise the target so IPv6 literals always reach ssh bracketed
+            ForwardKind::Local => match parse_endpoint(&self.remote_target) {
+                Ok((host, port)) => {
+                    format!("{}:{}", self.listen_spec(), format_endpoint(&host, port))
+                }
+                Err(_) => format!("{}:{}", self.listen_spec(), self.remote_target),
+            },
+            ForwardKind::Remote => format!("{}:localhost:{}", self.remote_target, self.local_port),
+            ForwardKind::Dynamic => self.listen_spec(),
         }
     }
 
+    /// Direction-aware summary of the forward for the tunnels table.
+    pub fn forward_display(&self) -> String {
+        match self.kind {
+            ForwardKind::Local => format!("{} → {}", self.listen_display(), self.remote_target),
+            ForwardKind::Remote => format!("{} → :{}", self.remote_target, self.local_port),
+            ForwardKind::Dynamic => format!("{} [SOCKS]", self.listen_display()),
+        }
+    }
+
+    /// The local end for the details pane, `localhost:port` unless bound elsewhere.
+    fn local_listener(&self) -> String {
+        match &self.bind_address {
+            Some(_) => self.listen_spec(),
+            None => format!("localhost:{}", self.local_port),
+        }
+    }
+
+    /// Whether the local end listens on something other than loopback.
+    fn shared_with_network(&self) -> bool {
+        self.bind_address.as_deref().is_some_and(|bind| {
+            !bind.eq_ignore_ascii_case("localhost")
+                && !bind
+                    .parse::<std::net::IpAddr>()
+                    .is_ok_and(|ip| ip.is_loopback())
+        })
+    }
+
+    /// `:port` on loopback, or `bind:port` when listening elsewhere.
+    fn listen_display(&self) -> String {
+        match &self.bind_address {
+            Some(_) => self.listen_spec(),
+            None => format!(":{}", self.local_port),
+        }
+    }
+
+    /// Explain where the forward's endpoints live, since `remote_target` is resolved by the ssh host.
+    pub fn detail_lines(&self) -> Vec<String> {
+        let mut lines = match self.kind {
+            ForwardKind::Local => {
+                let mut lines = vec![format!(
+                    "{} on this machine → {} as resolved by {}",
+                    self.local_listener(),
+                    self.remote_target,
+                    self.ssh_host
+                )];
+
+                if self.shared_with_network() {
+                    lines.push(format!(
+                        "Note: bound to {}, so other machines can reach the forward too",
+                        self.bind_address.as_deref().unwrap_or_default()
+                    ));
+                }
+
+                let host = parse_endpoint(&self.remote_target)
+                    .map_or_else(|_| self.remote_target.clone(), |(host, _)| host);
+                let host = host.as_str();
+
+                if host.eq_ignore_ascii_case("localhost") {
+                    lines.push(format!(
+                        "Note: 'localhost' is {} itself, not this machine",
+                        self.ssh_host
+                    ));
+                } else if let Ok(ip) = host.parse::<std::net::IpAddr>() {
+                    lines.push(format!("{} is {}", ip, describe_ip(&ip)));
+                    if ip.is_loopback() {
+                        lines.push(format!(
+                            "Note: loopback here is {}, not this machine",
+                            self.ssh_host
+                        ));
+                    }
+                }
+                lines
+            }
+            ForwardKind::Remote => vec![format!(
+                "{} on {} → localhost:{} on this machine",
+                self.remote_target, self.ssh_host, self.local_port
+            )],
+            ForwardKind::Dynamic => vec![format!(
+                "SOCKS proxy on {}; destinations are resolved by {}",
+                self.local_listener(),
+                self.ssh_host
+            )],
+        };
+        if let Some(jump_host) = &self.jump_host {
+            lines.push(format!("ssh reaches {} through {}", self.ssh_host, jump_host));
+        }
+        lines
+    }
+
+    /// Stop the tunnel's ssh process, escalating to SIGKILL, and confirm the forward is freed.
     pub fn disconnect(&mut self) -> Result<()> {
-        if let Some(pid) = self.process {
-            Command::new("kill").arg(pid.to_string()).output()?;
+        // The tracked PID, or whoever holds the forward if we lost track of it
+        let remembered = self
+            .process
+            .filter(|pid| crate::port::process_alive(*pid as i32));
+        // Whatever happens below, the user wants this tunnel down
+        self.stop_reconnecting();
+        let Some(pid) = remembered.or_else(|| self.find_ssh_pid()) else {
             self.process = None;
-        } else if let Some(pid) = self.find_ssh_pid() {
-            Command::new("kill").arg(pid.to_string()).output()?;
+            self.connected_at = None;
+            return Ok(());
+        };
+
+        // Ask politely, then insist; only a freed forward counts as disconnected
+        for signal in ["TERM", "KILL"] {
+            let output = Command::new("kill")
+                .args([&format!("-{}", signal), &pid.to_string()])
+                .output()?;
+            if !output.status.success() && crate::port::process_alive(pid as i32) {
+                bail!(
+                    "kill -{} {} failed: {}",
+                    signal,
+                    pid,
+                    String::from_utf8_lossy(&output.stderr).trim()
+                );
+            }
+
+            let deadline = Instant::now() + DISCONNECT_GRACE;
+            while Instant::now() < deadline {
+                let freed = self.kind == ForwardKind::Remote || !self.forward_answers();
+                if !crate::port::process_alive(pid as i32) && freed {
+                    self.process = None;
+                    self.connected_at = None;
+                    return Ok(());
+                }
+                std::thread::sleep(Duration::from_millis(100));
+            }
         }
 
-        Ok(())
+        match self.find_ssh_pid() {
+            Some(other) if other != pid => bail!(
+                "PID {} is gone but ssh PID {} still holds the forward",
+                pid,
+                other
+            ),
+            _ => bail!("ssh PID {} still holds the forward after SIGKILL", pid),
+        }
     }
 
+    /// Whether the ssh process serving this tunnel's forward is still running.
+    ///
+    /// Cheap enough to call on every render: an ssh `connect` started is watched by the
+    /// thread reaping it, and only one adopted by `TunnelManager::load` or a late bind is
+    /// looked up by PID. `probe` checks that the forward actually answers.
     pub fn is_connected(&self) -> bool {
-        // Check if there's an SSH process listening on our local port
-        self.find_ssh_pid().is_some()
+        match (self.process, &self.ssh_exited) {
+            (None, _) => false,
+            (Some(_), Some(exited)) => !exited.load(Ordering::Acquire),
+            (Some(pid), None) => crate::port::process_alive(pid as i32),
+        }
     }
 
-    fn find_ssh_pid(&self) -> Option<u32> {
-        // Use lsof to find SSH process on our local port
-        let output = Command::new("lsof")
-            .args(["-iTCP", "-P", "-n", &format!("-i:{}", self.local_port)])
-            .output()
-            .ok()?;
-
-        let stdout = String::from_utf8_lossy(&output.stdout);
-
-        for line in stdout.lines().skip(1) {
-            let parts: Vec<&str> = line.split_whitespace().collect();
-            if parts.len() >= 2 && parts[0] == "ssh" {
-                if let Ok(pid) = parts[1].parse::<u32>() {
-                    return Some(pid);
+    /// Keep auto-reconnect from bringing this tunnel back until it's connected again.
+    pub fn stop_reconnecting(&mut self) {
+        self.reconnect_paused = true;
+        self.next_reconnect = None;
+        self.reconnect_attempts = 0;
+    }
+
+    /// Whether auto-reconnect is bringing this tunnel back after it dropped.
+    pub fn reconnecting(&self) -> bool {
+        self.next_reconnect.is_some()
+    }
+
+    /// Schedule a reconnect if this is an auto-reconnect tunnel whose ssh has died; true if it just dropped.
+    pub fn check_dropped(&mut self) -> bool {
+        if !self.auto_reconnect
+            || self.reconnect_paused
+            || self.reconnecting()
+            || self.late_bind_deadline.is_some()
+            || self.process.is_none()
+            || self.is_connected()
+        {
+            return false;
+        }
+        self.process = None;
+        self.connected_at = None;
+        self.reconnect_attempts = 0;
+        self.next_reconnect = Some(Instant::now() + RECONNECT_BACKOFF);
+        true
+    }
+
+    /// Whether the backoff has passed and the next reconnect attempt should start.
+    pub fn reconnect_due(&self) -> bool {
+        !self.reconnect_paused
+            && self.late_bind_deadline.is_none()
+            && self.next_reconnect.is_some_and(|at| Instant::now() >= at)
+    }
+
+    /// Record the outcome of a reconnect attempt, backing off further after a failure.
+    pub fn finish_reconnect(&mut self, error: Option<String>) {
+        match error {
+            None => {
+                self.last_error = None;
+                self.reconnect_attempts = 0;
+                self.next_reconnect = None;
+            }
+            Some(e) => {
+                self.last_error = Some(e);
+                if !self.reconnect_paused {
+                    let doublings = self.reconnect_attempts.min(16);
+                    let backoff = (RECONNECT_BACKOFF * 2u32.pow(doublings)).min(RECONNECT_BACKOFF_MAX);
+                    self.next_reconnect = Some(Instant::now() + backoff);
                 }
             }
         }
+    }
+
+    /// Find an ssh process serving this forward, for tunnels anchor didn't start itself.
+    fn find_ssh_pid(&self) -> Option<u32> {
+        // A remote forward listens on the ssh host, so look for the ssh process by its arguments
+        if self.kind == ForwardKind::Remote {
+            let pattern = format!("ssh.*-R {}", self.forward_spec());
+            let output = Command::new("pgrep").args(["-f", &pattern]).output().ok()?;
+            return String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .next()
+                .and_then(|line| line.trim().parse().ok());
+        }
+
+        // Otherwise ssh holds our local port; ask whichever socket backend this machine has
+        let backend = crate::port::PortBackend::detect();
+        crate::port::sockets_on_port(backend, self.local_port)
+            .ok()?
+            .into_iter()
+            .find(|socket| socket.process_name == "ssh" && socket.protocol == "TCP")
+            .map(|socket| socket.pid as u32)
+    }
+
+    /// Whether a configured identity file no longer exists on disk.
+    pub fn identity_file_missing(&self) -> bool {
+        self.identity_file
+            .as_ref()
+            .is_some_and(|path| !expand_tilde(path).exists())
+    }
 
-        None
+    /// Liveness for display: up, running but failing its last probe, or down.
+    pub fn health(&self) -> TunnelHealth {
+        if !self.is_connected() {
+            TunnelHealth::Down
+        } else if self.probe_failure.is_some() {
+            TunnelHealth::Degraded
+        } else {
+            TunnelHealth::Up
+        }
     }
 
+    /// Check that the forward answers: a TCP connect for `-L`, a SOCKS5 handshake for `-D`.
+    ///
+    /// Remote forwards listen on the ssh host, so there is nothing to probe from here.
+    pub fn probe(&self) -> Result<()> {
+        let addr = self.local_addr();
+        match self.kind {
+            ForwardKind::Local => {
+                TcpStream::connect_timeout(&addr, PROBE_TIMEOUT)
+                    .with_context(|| format!("nothing answers on {}", addr))?;
+                Ok(())
+            }
+            ForwardKind::Dynamic => probe_socks(addr, self.socks_test_target.as_deref()),
+            ForwardKind::Remote => bail!("remote forwards listen on {}; test them there", self.ssh_host),
+        }
+    }
+
+    /// Check the hand-typed fields ssh would otherwise reject with a cryptic error at connect time.
+    pub fn validate(&self) -> Result<(), String> {
+        check_ssh_host(&self.ssh_host).map_err(|e| format!("Invalid SSH host: {}", e))?;
+        if let Some(bind) = &self.bind_address {
+            check_bind_address(bind).map_err(|e| format!("Invalid bind address: {}", e))?;
+        }
+        if let Some(jump_host) = &self.jump_host {
+            check_jump_host(jump_host).map_err(|e| format!("Invalid jump host: {}", e))?;
+        }
+        check_remote_target(self.kind, &self.remote_target)
+            .map_err(|e| format!("Invalid remote target: {}", e))
+    }
+
+    /// A disconnected copy under another name, keeping every saved setting.
+    pub fn duplicate(&self, name: String) -> TunnelConfig {
+        TunnelConfig {
+            name,
+            ssh_host: self.ssh_host.clone(),
+            local_port: self.local_port,
+            remote_target: self.remote_target.clone(),
+            kind: self.kind,
+            bind_address: self.bind_address.clone(),
+            identity_file: self.identity_file.clone(),
+            ssh_port: self.ssh_port,
+            jump_host: self.jump_host.clone(),
+            group: self.group.clone(),
+            expected_up: self.expected_up,
+            auto_start: self.auto_start,
+            auto_reconnect: self.auto_reconnect,
+            socks_test_target: self.socks_test_target.clone(),
+            connect_timeout_secs: self.connect_timeout_secs,
+            ..TunnelConfig::default()
+        }
+    }
+
+    /// Connected/disconnected label with a status dot.
     pub fn status_string(&self) -> &'static str {
         if self.is_connected() {
             "● Connected"
@@ -119,8 +897,552 @@ impl TunnelConfig {
     }
 }
 
-#[derive(Serialize, Deserialize)]
+/// Quote `word` for a POSIX shell, leaving a leading `~/` outside the quotes so it still expands.
+fn shell_quote(word: &str) -> String {
+    if let Some(rest) = word.strip_prefix("~/") {
+        return format!("~/{}", shell_quote(rest));
+    }
+
+    let safe = !word.is_empty()
+        && word
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "@%+=:,./-_".contains(c));
+    if safe {
+        word.to_string()
+    } else {
+        format!("'{}'", word.replace('\'', "'\\''"))
+    }
+}
+
+/// Render every tunnel as a runnable shell script, for people without anchor.
+pub fn export_script(tunnels: &[TunnelConfig]) -> String {
+    let mut script = String::from("#!/bin/sh\n# SSH tunnels exported from anchor:\n");
+    for tunnel in tunnels {
+        script.push_str(&format!("#   {} ({})\n", tunnel.name, tunnel.forward_display()));
+    }
+    for tunnel in tunnels {
+        script.push_str(&format!("\n# {}\n{}\n", tunnel.name, tunnel.shell_command()));
+    }
+    script
+}
+
+/// SOCKS5 no-auth handshake against `addr`, then a CONNECT to `target` if one is given.
+fn probe_socks(addr: std::net::SocketAddr, target: Option<&str>) -> Result<()> {
+    let mut stream = TcpStream::connect_timeout(&addr, PROBE_TIMEOUT)
+        .with_context(|| format!("nothing answers on {}", addr))?;
+    stream.set_read_timeout(Some(PROBE_TIMEOUT))?;
+    stream.set_write_timeout(Some(PROBE_TIMEOUT))?;
+
+    // Version 5, one method offered: no authentication
+    stream.write_all(&[5, 1, 0])?;
+    let mut reply = [0u8; 2];
+    stream
+        .read_exact(&mut reply)
+        .context("SOCKS proxy closed the connection during the handshake")?;
+    if reply != [5, 0] {
+        bail!("not a SOCKS5 proxy accepting no-auth (replied {:?})", reply);
+    }
+
+    let Some(target) = target else {
+        return Ok(());
+    };
+    let (host, port) = parse_endpoint(target)?;
+    let mut request = vec![5, 1, 0];
+    match host.parse::<std::net::IpAddr>() {
+        Ok(std::net::IpAddr::V4(ip)) => {
+            request.push(1);
+            request.extend(ip.octets());
+        }
+        Ok(std::net::IpAddr::V6(ip)) => {
+            request.push(4);
+            request.extend(ip.octets());
+        }
+        Err(_) => {
+            let len = u8::try_from(host.len()).context("test target host name is too long")?;
+            request.extend([3, len]);
+            request.extend(host.as_bytes());
+        }
+    }
+    request.extend(port.to_be_bytes());
+    stream.write_all(&request)?;
+
+    let mut reply = [0u8; 4];
+    stream
+        .read_exact(&mut reply)
+        .with_context(|| format!("SOCKS proxy gave no answer for {}", target))?;
+    match reply[1] {
+        0 => Ok(()),
+        code => bail!("SOCKS proxy could not reach {}: {}", target, socks_error(code)),
+    }
+}
+
+/// Meaning of a SOCKS5 reply code (RFC 1928).
+fn socks_error(code: u8) -> &'static str {
+    match code {
+        1 => "general failure",
+        2 => "not allowed by ruleset",
+        3 => "network unreachable",
+        4 => "host unreachable",
+        5 => "connection refused",
+        6 => "TTL expired",
+        7 => "command not supported",
+        8 => "address type not supported",
+        _ => "unknown error",
+    }
+}
+
+/// Split a `host:port` target, accepting bracketed IPv6 literals like `[::1]:5432`.
+pub fn parse_endpoint(target: &str) -> Result<(String, u16)> {
+    let (host, port) = if let Some(rest) = target.strip_prefix('[') {
+        let Some((host, port)) = rest.split_once("]:") else {
+            bail!("'{}' should look like [IPv6]:port", target);
+        };
+        host.parse::<std::net::Ipv6Addr>()
+            .with_context(|| format!("'{}' is not an IPv6 address", host))?;
+        (host, port)
+    } else {
+        let Some((host, port)) = target.rsplit_once(':') else {
+            bail!("'{}' should look like host:port", target);
+        };
+        if host.contains(':') {
+            bail!("wrap IPv6 addresses in brackets, e.g. [{}]:{}", host, port);
+        }
+        (host, port)
+    };
+
+    if host.is_empty() {
+        bail!("'{}' is missing a host", target);
+    }
+    let port = port
+        .parse::<u16>()
+        .ok()
+        .filter(|port| *port != 0)
+        .with_context(|| format!("'{}' is not a valid port", port))?;
+    Ok((host.to_string(), port))
+}
+
+/// Check an ssh destination looks like `[user@]host`.
+///
+/// Catches the usual slips: a port tacked on (`host:22`, which belongs in `ssh_port`), stray
+/// spaces, and a leading `-` that ssh would read as an option.
+pub fn check_ssh_host(destination: &str) -> Result<()> {
+    if destination.is_empty() {
+        bail!("no host given");
+    }
+    if destination.starts_with('-') {
+        bail!("'{}' would be read as an ssh option", destination);
+    }
+    if destination.chars().any(char::is_whitespace) {
+        bail!("'{}' contains spaces", destination);
+    }
+
+    let host = match destination.rsplit_once('@') {
+        Some(("", _)) => bail!("'{}' is missing a user before '@'", destination),
+        Some((_, host)) => host,
+        None => destination,
+    };
+    if host.is_empty() {
+        bail!("'{}' is missing a host after '@'", destination);
+    }
+    if host.parse::<std::net::Ipv6Addr>().is_ok() {
+        return Ok(());
+    }
+    if let Some((_, port)) = host.rsplit_once(':') {
+        bail!("'{}' includes a port; put {} in the SSH port instead", destination, port);
+    }
+    if let Some(bad) = host
+        .chars()
+        .find(|c| !(c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_')))
+    {
+        bail!("'{}' contains '{}', which isn't valid in a host name", destination, bad);
+    }
+    Ok(())
+}
+
+/// Check a tunnel's `remote_target` suits its kind: `host:port` for `-L`, `port` or
+/// `bind:port` for `-R`, and anything for `-D`, which has no fixed target.
+pub fn check_remote_target(kind: ForwardKind, target: &str) -> Result<()> {
+    match kind {
+        ForwardKind::Local => parse_endpoint(target).map(|_| ()),
+        ForwardKind::Remote => {
+            let port = target.rsplit(':').next().unwrap_or_default();
+            if port.parse::<u16>().ok().filter(|port| *port != 0).is_none() {
+                bail!("'{}' should be a port or bind:port", target);
+            }
+            Ok(())
+        }
+        ForwardKind::Dynamic => Ok(()),
+    }
+}
+
+/// Check a `-J` value: one or more comma-separated `[user@]host[:port]` hops.
+pub fn check_jump_host(jump: &str) -> Result<()> {
+    if jump.is_empty() {
+        bail!("no host given");
+    }
+    for hop in jump.split(',') {
+        let host = match hop.rsplit_once('@') {
+            Some(("", _)) => bail!("'{}' is missing a user before '@'", hop),
+            Some((_, host)) => host,
+            None => hop,
+        };
+        // A port may follow the host, with IPv6 literals in brackets
+        let host = match parse_endpoint(host) {
+            Ok((host, _)) => host,
+            Err(_) if !host.contains(':') || host.parse::<std::net::Ipv6Addr>().is_ok() => {
+                host.to_string()
+            }
+            Err(_) => bail!("'{}' should be [user@]host[:port]", hop),
+        };
+        check_ssh_host(&host)?;
+    }
+    Ok(())
+}
+
+/// Check a local bind address: an IP, a host name, or `*` for every interface.
+pub fn check_bind_address(bind: &str) -> Result<()> {
+    if bind.is_empty() {
+        bail!("no address given");
+    }
+    if bind == "*" || bind.parse::<std::net::IpAddr>().is_ok() {
+        return Ok(());
+    }
+    if let Some(bad) = bind
+        .chars()
+        .find(|c| !(c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_')))
+    {
+        bail!("'{}' contains '{}'; use an IP address or host name", bind, bad);
+    }
+    if bind.starts_with('-') {
+        bail!("'{}' would be read as an ssh option", bind);
+    }
+    Ok(())
+}
+
+/// Join a host and port for ssh, bracketing IPv6 literals.
+fn format_endpoint(host: &str, port: u16) -> String {
+    if host.contains(':') {
+        format!("[{}]:{}", host, port)
+    } else {
+        format!("{}:{}", host, port)
+    }
+}
+
+/// Tunnel name as a file name: other bytes than letters, digits and `-` become `_xx` in hex,
+/// so no two names share a log.
+fn log_file_stem(name: &str) -> String {
+    let mut stem = String::with_capacity(name.len());
+    for byte in name.bytes() {
+        if byte.is_ascii_alphanumeric() || byte == b'-' {
+            stem.push(byte as char);
+        } else {
+            stem.push_str(&format!("_{:02x}", byte));
+        }
+    }
+    stem
+}
+
+/// Directory for ssh logs: the user's cache directory, or without one a directory only we
+/// can read, made fresh in the shared temp directory for this run.
+fn ssh_log_dir() -> Result<PathBuf> {
+    if let Some(cache) = dirs::cache_dir() {
+        return Ok(cache.join("anchor").join("logs"));
+    }
+
+    static PRIVATE: OnceLock<Option<PathBuf>> = OnceLock::new();
+    PRIVATE
+        .get_or_init(|| {
+            use std::os::unix::fs::DirBuilderExt;
+            // Creating it ourselves means nobody else can have planted it or a symlink there
+            let dir = std::env::temp_dir().join(format!(
+                "anchor-logs-{}-{}",
+                std::process::id(),
+                crate::port::unix_now()
+            ));
+            fs::DirBuilder::new().mode(0o700).create(&dir).ok()?;
+            Some(dir)
+        })
+        .clone()
+        .context("No cache directory, and no private log directory could be made")
+}
+
+/// Open a tunnel's ssh log for appending, moving it to `.log.1` once it passes `SSH_LOG_MAX`.
+fn open_ssh_log(path: &Path) -> Result<fs::File> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)
+            .with_context(|| format!("Could not create {}", dir.display()))?;
+    }
+    if fs::metadata(path).is_ok_and(|meta| meta.len() > SSH_LOG_MAX) {
+        fs::rename(path, path.with_extension("log.1"))?;
+    }
+    fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Could not open {}", path.display()))
+}
+
+/// The last `max` lines of a tunnel's ssh log; empty if it has never connected.
+pub fn read_ssh_log(path: &Path, max: usize) -> Vec<String> {
+    let log = fs::read_to_string(path).unwrap_or_default();
+    let lines: Vec<&str> = log.lines().collect();
+    lines[lines.len().saturating_sub(max)..]
+        .iter()
+        .map(|line| line.to_string())
+        .collect()
+}
+
+fn expand_tilde(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+fn describe_ip(ip: &std::net::IpAddr) -> &'static str {
+    use std::net::IpAddr;
+
+    if ip.is_loopback() {
+        return "a loopback address";
+    }
+    if ip.is_unspecified() {
+        return "the unspecified address";
+    }
+    let private = match ip {
+        IpAddr::V4(v4) => v4.is_private() || v4.is_link_local(),
+        // fc00::/7 unique local and fe80::/10 link-local
+        IpAddr::V6(v6) => {
+            (v6.segments()[0] & 0xfe00) == 0xfc00 || (v6.segments()[0] & 0xffc0) == 0xfe80
+        }
+    };
+    if private {
+        "a private address"
+    } else {
+        "a public address"
+    }
+}
+
+/// Hostname part of an ssh destination like `user@host` or `host`.
+pub fn ssh_hostname(ssh_host: &str) -> &str {
+    ssh_host.rsplit_once('@').map_or(ssh_host, |(_, host)| host)
+}
+
+/// Concrete (non-wildcard) `Host` aliases defined in `~/.ssh/config`.
+pub fn ssh_config_aliases() -> Vec<String> {
+    let Some(content) = dirs::home_dir()
+        .and_then(|home| fs::read_to_string(home.join(".ssh").join("config")).ok())
+    else {
+        return Vec::new();
+    };
+
+    content
+        .lines()
+        .filter_map(|line| {
+            let mut words = line.split_whitespace();
+            match words.next() {
+                Some(keyword) if keyword.eq_ignore_ascii_case("host") => Some(words),
+                _ => None,
+            }
+        })
+        .flatten()
+        .filter(|pattern| !pattern.contains(['*', '?', '!']))
+        .map(String::from)
+        .collect()
+}
+
+/// `~/.ssh/config`, if there is a home directory.
+pub fn default_ssh_config_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".ssh").join("config"))
+}
+
+/// One tunnel per `LocalForward` in each concrete `Host` block of an ssh config.
+///
+/// Wildcard and negated blocks, `Match` blocks and unix socket forwards are skipped. Tunnels
+/// connect to `[User@]HostName` rather than the alias, since ssh would otherwise also open
+/// every forward in the block alongside the tunnel's own.
+pub fn parse_ssh_config_forwards(content: &str) -> Vec<TunnelConfig> {
+    #[derive(Default)]
+    struct HostBlock {
+        alias: String,
+        host_name: Option<String>,
+        user: Option<String>,
+        port: Option<u16>,
+        identity_file: Option<String>,
+        forwards: Vec<(Option<String>, u16, String)>,
+    }
+
+    fn finish(block: HostBlock, tunnels: &mut Vec<TunnelConfig>) {
+        let host = block.host_name.unwrap_or_else(|| block.alias.clone());
+        let ssh_host = match &block.user {
+            Some(user) => format!("{}@{}", user, host),
+            None => host,
+        };
+        let several = block.forwards.len() > 1;
+        for (bind_address, local_port, remote_target) in block.forwards {
+            tunnels.push(TunnelConfig {
+                name: if several {
+                    format!("{}-{}", block.alias, local_port)
+                } else {
+                    block.alias.clone()
+                },
+                ssh_host: ssh_host.clone(),
+                local_port,
+                remote_target,
+                bind_address,
+                identity_file: block.identity_file.clone(),
+                ssh_port: block.port,
+                ..TunnelConfig::default()
+            });
+        }
+    }
+
+    let mut tunnels = Vec::new();
+    let mut block: Option<HostBlock> = None;
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        // Keywords are case-insensitive and separated from their value by spaces or '='
+        let (keyword, value) = line
+            .split_once(|c: char| c.is_whitespace() || c == '=')
+            .map(|(k, v)| {
+                let v = v.trim_start_matches(|c: char| c.is_whitespace() || c == '=');
+                (k.to_lowercase(), v.trim())
+            })
+            .unwrap_or((line.to_lowercase(), ""));
+
+        match keyword.as_str() {
+            "host" | "match" => {
+                if let Some(done) = block.take() {
+                    finish(done, &mut tunnels);
+                }
+                let patterns: Vec<&str> = value.split_whitespace().collect();
+                if keyword == "host" && !patterns.iter().any(|p| p.contains(['*', '?', '!'])) {
+                    block = patterns.first().map(|alias| HostBlock {
+                        alias: alias.to_string(),
+                        ..HostBlock::default()
+                    });
+                }
+            }
+            _ => {
+                let Some(block) = block.as_mut() else {
+                    continue;
+                };
+                // ssh uses the first value given for each option
+                match keyword.as_str() {
+                    "hostname" => {
+                        block.host_name.get_or_insert_with(|| value.to_string());
+                    }
+                    "user" => {
+                        block.user.get_or_insert_with(|| value.to_string());
+                    }
+                    "port" if block.port.is_none() => block.port = value.parse().ok(),
+                    "identityfile" => {
+                        block.identity_file.get_or_insert_with(|| value.to_string());
+                    }
+                    "localforward" => {
+                        let mut args = value.split_whitespace();
+                        // The listener is [bind_address:]port; the target host:hostport
+                        let (bind_address, local_port) = match args.next() {
+                            Some(listen) => match listen.rsplit_once(':') {
+                                Some((bind, port)) => (
+                                    Some(bind.trim_matches(['[', ']']).to_string()),
+                                    port.parse::<u16>().ok(),
+                                ),
+                                None => (None, listen.parse::<u16>().ok()),
+                            },
+                            None => (None, None),
+                        };
+                        let target = args.next().filter(|target| parse_endpoint(target).is_ok());
+                        if let (Some(local_port), Some(target)) = (local_port, target) {
+                            block.forwards.push((bind_address, local_port, target.to_string()));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+    if let Some(done) = block {
+        finish(done, &mut tunnels);
+    }
+    tunnels
+}
+
+/// How long a DNS answer for a tunnel host is trusted before checking again
+const RESOLVE_INTERVAL: Duration = Duration::from_secs(300);
+
+/// Background DNS checks for tunnel hosts, so dead entries can be flagged before connecting
+pub struct HostResolver {
+    resolved: HashMap<String, bool>,
+    last_checked: HashMap<String, Instant>,
+    aliases: Vec<String>,
+    tx: Sender<(String, bool)>,
+    rx: Receiver<(String, bool)>,
+}
+
+impl Default for HostResolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HostResolver {
+    /// Create a resolver that trusts `~/.ssh/config` aliases.
+    pub fn new() -> Self {
+        let (tx, rx) = mpsc::channel();
+        Self {
+            resolved: HashMap::new(),
+            last_checked: HashMap::new(),
+            aliases: ssh_config_aliases(),
+            tx,
+            rx,
+        }
+    }
+
+    /// Collect finished lookups and start new ones for hosts not checked recently.
+    pub fn tick(&mut self, tunnels: &[TunnelConfig]) {
+        while let Ok((host, ok)) = self.rx.try_recv() {
+            self.resolved.insert(host, ok);
+        }
+
+        for tunnel in tunnels {
+            let host = ssh_hostname(&tunnel.ssh_host);
+            // ssh config aliases resolve through HostName, which ssh handles itself
+            if host.is_empty() || self.aliases.iter().any(|alias| alias == host) {
+                continue;
+            }
+            if self
+                .last_checked
+                .get(host)
+                .is_some_and(|checked| checked.elapsed() < RESOLVE_INTERVAL)
+            {
+                continue;
+            }
+
+            self.last_checked.insert(host.to_string(), Instant::now());
+            let host = host.to_string();
+            let tx = self.tx.clone();
+            std::thread::spawn(move || {
+                let ok = (host.as_str(), 22)
+                    .to_socket_addrs()
+                    .is_ok_and(|mut addrs| addrs.next().is_some());
+                let _ = tx.send((host, ok));
+            });
+        }
+    }
+
+    /// Whether the tunnel's host failed its last DNS lookup.
+    pub fn is_unresolved(&self, tunnel: &TunnelConfig) -> bool {
+        self.resolved.get(ssh_hostname(&tunnel.ssh_host)) == Some(&false)
+    }
+}
+
+/// The saved tunnels of one profile; the default has none and no file behind it
+#[derive(Default, Serialize, Deserialize)]
 pub struct TunnelManager {
+    /// Tunnels in display order
     pub tunnels: Vec<TunnelConfig>,
 
     #[serde(skip)]
@@ -128,18 +1450,30 @@ pub struct TunnelManager {
 }
 
 impl TunnelManager {
-    pub fn load() -> Result<Self> {
-        let config_path = Self::get_config_path()?;
+    /// Load a profile's tunnels, noting which are already running.
+    ///
+    /// A profile still saved as JSON is converted to TOML on first load, keeping the old
+    /// file alongside as `tunnels.json.bak`.
+    pub fn load(profile: &str) -> Result<Self> {
+        let config_path = Self::get_config_path(profile)?;
 
-        if config_path.exists() {
+        let loaded = if config_path.exists() {
             let content = fs::read_to_string(&config_path)?;
-            let mut manager: TunnelManager = serde_json::from_str(&content)?;
+            let manager: TunnelManager = toml::from_str(&content)
+                .with_context(|| format!("could not parse {}", config_path.display()))?;
+            Some(manager)
+        } else {
+            Self::migrate_json(profile, &config_path)?
+        };
+
+        if let Some(mut manager) = loaded {
             manager.config_path = config_path;
 
             // Update connection status for each tunnel
             for tunnel in &mut manager.tunnels {
                 if let Some(pid) = tunnel.find_ssh_pid() {
                     tunnel.process = Some(pid);
+                    tunnel.ssh_exited = None;
                 }
             }
 
@@ -152,24 +1486,95 @@ impl TunnelManager {
         }
     }
 
+    /// Write the tunnels back to the profile's config file.
     pub fn save(&self) -> Result<()> {
         // Ensure parent directory exists
         if let Some(parent) = self.config_path.parent() {
             fs::create_dir_all(parent)?;
         }
 
-        let content = serde_json::to_string_pretty(&self)?;
+        let content = toml::to_string_pretty(&self)?;
         fs::write(&self.config_path, content)?;
 
         Ok(())
     }
 
+    /// Convert the profile's `tunnels.json` to TOML at `config_path`, if it has one.
+    fn migrate_json(profile: &str, config_path: &Path) -> Result<Option<Self>> {
+        let mut json_path = config_path.with_extension("json");
+        // Tunnels saved before profiles existed belong to the default profile
+        if !json_path.exists() && profile == DEFAULT_PROFILE {
+            json_path = Self::get_legacy_config_path()?;
+        }
+        if !json_path.exists() {
+            return Ok(None);
+        }
+
+        let content = fs::read_to_string(&json_path)?;
+        let mut manager: TunnelManager = serde_json::from_str(&content)
+            .with_context(|| format!("could not parse {}", json_path.display()))?;
+        manager.config_path = config_path.to_path_buf();
+        // Only retire the JSON once its replacement is safely written
+        manager.save()?;
+        fs::rename(&json_path, json_path.with_extension("json.bak"))?;
+        Ok(Some(manager))
+    }
+
+    /// Forget the ssh of tunnels whose process has died, however it died, so a reused PID
+    /// can't make them look connected. Returns the names of tunnels that went down.
+    ///
+    /// Auto-reconnect tunnels are scheduled to come back, as when they drop by themselves.
+    pub fn refresh_statuses(&mut self) -> Vec<String> {
+        let mut dropped = Vec::new();
+        for tunnel in &mut self.tunnels {
+            if tunnel.process.is_none() {
+                continue;
+            }
+            if tunnel.check_dropped() {
+                dropped.push(tunnel.name.clone());
+            } else if !tunnel.is_connected() {
+                tunnel.process = None;
+                tunnel.connected_at = None;
+                tunnel.probe_failure = None;
+                dropped.push(tunnel.name.clone());
+            }
+        }
+        dropped
+    }
+
+    /// Add a tunnel, replacing any existing one with the same name.
     pub fn add(&mut self, tunnel: TunnelConfig) {
         // Remove existing tunnel with same name
         self.tunnels.retain(|t| t.name != tunnel.name);
         self.tunnels.push(tunnel);
     }
 
+    /// Add the `LocalForward`s of an ssh config as tunnels, skipping names already taken.
+    ///
+    /// Returns how many tunnels were added; the caller saves.
+    pub fn import_ssh_config(&mut self, path: &Path) -> Result<usize> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("could not read {}", path.display()))?;
+
+        let mut added = 0;
+        for tunnel in parse_ssh_config_forwards(&content) {
+            if !self.tunnels.iter().any(|t| t.name == tunnel.name) {
+                self.tunnels.push(tunnel);
+                added += 1;
+            }
+        }
+        Ok(added)
+    }
+
+    /// Put `tunnel` where the one named `original` is, keeping its place in the saved order.
+    pub fn replace(&mut self, original: &str, tunnel: TunnelConfig) {
+        match self.tunnels.iter().position(|t| t.name == original) {
+            Some(index) => self.tunnels[index] = tunnel,
+            None => self.add(tunnel),
+        }
+    }
+
+    /// Disconnect and forget the tunnel with this name.
     pub fn remove(&mut self, name: &str) {
         // Disconnect first if connected
         if let Some(tunnel) = self.tunnels.iter_mut().find(|t| t.name == name) {
@@ -178,10 +1583,351 @@ impl TunnelManager {
         self.tunnels.retain(|t| t.name != name);
     }
 
-    fn get_config_path() -> Result<PathBuf> {
+    /// Names of the profiles that have a directory on disk, sorted.
+    pub fn list_profiles() -> Vec<String> {
+        let Ok(dir) = Self::get_profiles_dir() else {
+            return Vec::new();
+        };
+        let mut profiles: Vec<String> = fs::read_dir(dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter(|entry| entry.path().is_dir())
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .collect();
+        profiles.sort();
+        profiles
+    }
+
+    fn get_config_path(profile: &str) -> Result<PathBuf> {
+        if profile.is_empty() || profile.contains(['/', '\\']) || profile.starts_with('.') {
+            anyhow::bail!("Invalid profile name '{}'", profile);
+        }
+
+        Ok(Self::get_profiles_dir()?.join(profile).join("tunnels.toml"))
+    }
+
+    fn get_profiles_dir() -> Result<PathBuf> {
+        let config_dir = dirs::config_dir()
+            .ok_or_else(|| anyhow::anyhow!("Could not find config directory"))?;
+
+        Ok(config_dir.join("anchor").join("profiles"))
+    }
+
+    fn get_legacy_config_path() -> Result<PathBuf> {
         let config_dir = dirs::config_dir()
             .ok_or_else(|| anyhow::anyhow!("Could not find config directory"))?;
 
         Ok(config_dir.join("anchor").join("tunnels.json"))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// PID of a process that has already exited and been reaped
+    fn dead_pid() -> u32 {
+        let mut child = Command::new("true").spawn().expect("spawn true");
+        let pid = child.id();
+        child.wait().expect("reap true");
+        pid
+    }
+
+    #[test]
+    fn refresh_statuses_drops_tunnels_whose_ssh_died() {
+        let mut manager = TunnelManager {
+            tunnels: vec![TunnelConfig {
+                name: String::from("db"),
+                process: Some(dead_pid()),
+                ..TunnelConfig::default()
+            }],
+            config_path: PathBuf::new(),
+        };
+
+        assert_eq!(manager.refresh_statuses(), vec![String::from("db")]);
+        let tunnel = &manager.tunnels[0];
+        assert_eq!(tunnel.process, None);
+        assert!(!tunnel.is_connected());
+        assert_eq!(tunnel.status_string(), "○ Disconnected");
+        // Nothing left to drop the second time
+        assert!(manager.refresh_statuses().is_empty());
+    }
+
+    #[test]
+    fn is_connected_trusts_the_reaper_over_the_pid() {
+        let exited = Arc::new(AtomicBool::new(false));
+        let mut tunnel = TunnelConfig {
+            name: String::from("db"),
+            // Alive for the whole test, so only the flag can say the ssh is gone
+            process: Some(std::process::id()),
+            ssh_exited: Some(Arc::clone(&exited)),
+            ..TunnelConfig::default()
+        };
+        assert!(tunnel.is_connected());
+
+        exited.store(true, Ordering::Release);
+        assert!(!tunnel.is_connected());
+        assert_eq!(tunnel.status_string(), "○ Disconnected");
+
+        // Adopted at startup, with no reaper to ask
+        tunnel.ssh_exited = None;
+        assert!(tunnel.is_connected());
+        tunnel.process = Some(dead_pid());
+        assert!(!tunnel.is_connected());
+    }
+
+    #[test]
+    fn log_file_stems_never_collide() {
+        assert_eq!(log_file_stem("db-prod"), "db-prod");
+        assert_eq!(log_file_stem("a b"), "a_20b");
+        assert_eq!(log_file_stem("a_b"), "a_5fb");
+        assert_eq!(log_file_stem("../etc"), "_2e_2e_2fetc");
+        assert_ne!(log_file_stem("é"), log_file_stem("_c3_a9"));
+    }
+
+    #[test]
+    fn parse_endpoint_splits_host_and_port() {
+        let host_port = |host: &str, port| Some((host.to_string(), port));
+        let cases = [
+            ("[::1]:5432", host_port("::1", 5432)),
+            ("[fe80::1]:22", host_port("fe80::1", 22)),
+            ("host:80", host_port("host", 80)),
+            ("10.0.0.5:6379", host_port("10.0.0.5", 6379)),
+            ("[::1:5432", None),
+            ("[not-ipv6]:80", None),
+            ("::1:80", None),
+            ("host:65536", None),
+            ("host:0", None),
+            ("host", None),
+            (":80", None),
+        ];
+        for (target, expected) in cases {
+            assert_eq!(parse_endpoint(target).ok(), expected, "{}", target);
+        }
+    }
+
+    #[test]
+    fn forward_spec_brackets_ipv6_targets() {
+        let tunnel = TunnelConfig {
+            local_port: 5432,
+            remote_target: String::from("[::1]:5432"),
+            ..TunnelConfig::default()
+        };
+        assert_eq!(tunnel.forward_spec(), "5432:[::1]:5432");
+
+        let bound = TunnelConfig { bind_address: Some(String::from("::1")), ..tunnel };
+        assert_eq!(bound.forward_spec(), "[::1]:5432:[::1]:5432");
+    }
+
+    #[test]
+    fn check_ssh_host_accepts_user_at_host() {
+        for good in [
+            "bastion",
+            "deploy@bastion.example.com",
+            "me@10.0.0.5",
+            "ops@fe80::1",
+            "a_b-c",
+        ] {
+            assert!(check_ssh_host(good).is_ok(), "{} should be accepted", good);
+        }
+    }
+
+    #[test]
+    fn check_ssh_host_rejects_malformed_destinations() {
+        for bad in [
+            "",
+            "-oProxyCommand=sh",
+            "@bastion",
+            "deploy@",
+            "bastion:22",
+            "deploy@bastion:2222",
+            "two words",
+            "host/path",
+        ] {
+            assert!(check_ssh_host(bad).is_err(), "{:?} should be rejected", bad);
+        }
+    }
+
+    #[test]
+    fn check_remote_target_by_kind() {
+        let cases = [
+            (ForwardKind::Local, "db.internal:5432", true),
+            (ForwardKind::Local, "[::1]:5432", true),
+            (ForwardKind::Local, "db.internal", false),
+            (ForwardKind::Local, "db.internal:", false),
+            (ForwardKind::Local, "db.internal:0", false),
+            (ForwardKind::Local, "::1:5432", false),
+            (ForwardKind::Remote, "8080", true),
+            (ForwardKind::Remote, "0.0.0.0:8080", true),
+            (ForwardKind::Remote, "0", false),
+            (ForwardKind::Remote, "web:", false),
+            (ForwardKind::Dynamic, "", true),
+        ];
+        for (kind, target, ok) in cases {
+            assert_eq!(
+                check_remote_target(kind, target).is_ok(),
+                ok,
+                "{:?} {:?}",
+                kind,
+                target
+            );
+        }
+    }
+
+    #[test]
+    fn ssh_config_import_skips_wildcard_hosts() {
+        let tunnels = parse_ssh_config_forwards(
+            "Host *\n    LocalForward 9000 localhost:9000\n\
+             Host web-*\n    LocalForward 9001 localhost:9001\n\
+             Host !bastion db\n    LocalForward 9002 localhost:9002\n\
+             Match host db\n    LocalForward 9003 localhost:9003\n\
+             Host db\n    LocalForward 5432 localhost:5432\n",
+        );
+        let names: Vec<&str> = tunnels.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, vec!["db"]);
+    }
+
+    #[test]
+    fn ssh_config_import_names_each_of_several_forwards() {
+        let tunnels = parse_ssh_config_forwards(
+            "Host dev\n\
+             LocalForward 8080 localhost:80\n\
+             LocalForward 127.0.0.2:5432 db.internal:5432\n\
+             LocalForward [::1]:6379 [fd00::7]:6379\n\
+             LocalForward /tmp/socket localhost:22\n",
+        );
+        let forwards: Vec<(&str, Option<&str>, u16, &str)> = tunnels
+            .iter()
+            .map(|t| {
+                (t.name.as_str(), t.bind_address.as_deref(), t.local_port, t.remote_target.as_str())
+            })
+            .collect();
+        assert_eq!(
+            forwards,
+            vec![
+                ("dev-8080", None, 8080, "localhost:80"),
+                ("dev-5432", Some("127.0.0.2"), 5432, "db.internal:5432"),
+                ("dev-6379", Some("::1"), 6379, "[fd00::7]:6379"),
+            ]
+        );
+    }
+
+    #[test]
+    fn ssh_config_import_connects_to_hostname_user_and_port() {
+        let tunnels = parse_ssh_config_forwards(
+            "host db\n\
+             \tHostName=db1.example.com\n\
+             \tUser alice\n\
+             \tUser bob\n\
+             \tPort 2222\n\
+             \tIdentityFile ~/.ssh/db\n\
+             \tLocalForward 5432 localhost:5432\n\
+             Host cache\n\
+             \tLocalForward 6379 localhost:6379\n",
+        );
+        assert_eq!(tunnels.len(), 2);
+        // ssh keeps the first User, so anchor does too
+        assert_eq!(tunnels[0].ssh_host, "alice@db1.example.com");
+        assert_eq!(tunnels[0].ssh_port, Some(2222));
+        assert_eq!(tunnels[0].identity_file.as_deref(), Some("~/.ssh/db"));
+        // Without HostName or User the alias is the host
+        assert_eq!(tunnels[1].ssh_host, "cache");
+        assert_eq!(tunnels[1].ssh_port, None);
+    }
+
+    #[test]
+    fn validate_names_the_bad_field() {
+        let tunnel = TunnelConfig {
+            name: String::from("db"),
+            ssh_host: String::from("deploy@bastion"),
+            local_port: 5432,
+            remote_target: String::from("db.internal:5432"),
+            ..TunnelConfig::default()
+        };
+        assert_eq!(tunnel.validate(), Ok(()));
+
+        let bad_host = TunnelConfig { ssh_host: String::from("-bastion"), ..tunnel.clone() };
+        assert!(bad_host.validate().unwrap_err().starts_with("Invalid SSH host"));
+
+        let bad_target = TunnelConfig { remote_target: String::from("::1:5432"), ..tunnel.clone() };
+        assert!(bad_target.validate().unwrap_err().starts_with("Invalid remote target"));
+
+        let socks = TunnelConfig {
+            kind: ForwardKind::Dynamic,
+            remote_target: String::new(),
+            ..tunnel
+        };
+        assert_eq!(socks.validate(), Ok(()));
+    }
+
+    #[test]
+    fn every_saved_field_survives_toml() {
+        let tunnel = TunnelConfig {
+            name: String::from("socks"),
+            ssh_host: String::from("deploy@bastion"),
+            local_port: 1080,
+            remote_target: String::new(),
+            kind: ForwardKind::Dynamic,
+            bind_address: Some(String::from("::1")),
+            identity_file: Some(String::from("~/.ssh/id_ed25519")),
+            ssh_port: Some(2222),
+            jump_host: Some(String::from("ops@gateway:22")),
+            group: Some(String::from("prod")),
+            expected_up: true,
+            auto_start: true,
+            auto_reconnect: true,
+            socks_test_target: Some(String::from("example.com:443")),
+            connect_timeout_secs: 30,
+            ..TunnelConfig::default()
+        };
+        let manager = TunnelManager { tunnels: vec![tunnel.clone()], config_path: PathBuf::new() };
+
+        let content = toml::to_string_pretty(&manager).expect("serialize");
+        let loaded: TunnelManager = toml::from_str(&content).expect("parse");
+        let [back] = loaded.tunnels.as_slice() else {
+            panic!("expected one tunnel in {}", content);
+        };
+
+        assert_eq!(back.name, tunnel.name);
+        assert_eq!(back.ssh_host, tunnel.ssh_host);
+        assert_eq!(back.local_port, tunnel.local_port);
+        assert_eq!(back.remote_target, tunnel.remote_target);
+        assert_eq!(back.kind, tunnel.kind);
+        assert_eq!(back.bind_address, tunnel.bind_address);
+        assert_eq!(back.identity_file, tunnel.identity_file);
+        assert_eq!(back.ssh_port, tunnel.ssh_port);
+        assert_eq!(back.jump_host, tunnel.jump_host);
+        assert_eq!(back.group, tunnel.group);
+        assert_eq!(back.expected_up, tunnel.expected_up);
+        assert_eq!(back.auto_start, tunnel.auto_start);
+        assert_eq!(back.auto_reconnect, tunnel.auto_reconnect);
+        assert_eq!(back.socks_test_target, tunnel.socks_test_target);
+        assert_eq!(back.connect_timeout_secs, tunnel.connect_timeout_secs);
+    }
+
+    #[test]
+    fn migrate_json_replaces_the_json_with_toml() {
+        let dir = std::env::temp_dir().join(format!("anchor-migrate-{}", std::process::id()));
+        fs::create_dir_all(&dir).expect("create temp dir");
+        let json = r#"{"tunnels":[
+            {"name":"db","ssh_host":"bastion","local_port":5432,"remote_target":"db:5432"}
+        ]}"#;
+        fs::write(dir.join("tunnels.json"), json).expect("write json");
+
+        let config_path = dir.join("tunnels.toml");
+        let migrated = TunnelManager::migrate_json("migration-test", &config_path)
+            .expect("migrate")
+            .expect("a json file to migrate");
+
+        assert_eq!(migrated.tunnels[0].name, "db");
+        assert!(config_path.exists());
+        assert!(dir.join("tunnels.json.bak").exists());
+        assert!(!dir.join("tunnels.json").exists());
+        let reloaded: TunnelManager =
+            toml::from_str(&fs::read_to_string(&config_path).expect("read toml")).expect("parse");
+        assert_eq!(reloaded.tunnels[0].remote_target, "db:5432");
+
+        fs::remove_dir_all(&dir).expect("clean up temp dir");
+    }
+}
@@ -1,291 +1,1539 @@
-use crate::app::{App, AppTab};
+use crate::app::{
+    App, AppTab, LogView, PortRow, TunnelForm, TunnelJob, TunnelTest, TUNNEL_FORM_FIELDS,
+    TUNNEL_FORM_PLACEHOLDERS,
+};
+use crate::port::{signal_number, BindScope, PortBackend, PortInfo, PICKER_SIGNALS};
+use crate::settings::{TunnelColorMode, TunnelSort};
+use crate::theme::Theme;
+use crate::tunnel::{TunnelConfig, TunnelHealth};
 use ratatui::{
-    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    layout::{Alignment, Constraint, Direction, Layout, Margin, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, Cell, Clear, Paragraph, Row, Table, Tabs},
+    widgets::{
+        Block, Borders, Cell, Clear, Paragraph, Row, Scrollbar, ScrollbarOrientation,
+        ScrollbarState, Table, Tabs, Wrap,
+    },
     Frame,
 };
 
 pub fn draw(f: &mut Frame, app: &App) {
+    let theme = Theme::named(app.theme);
+    let show_banner = !app.exposed.is_empty() && !app.exposed_dismissed;
+
+    // On short terminals give up the help line, then the status bar, then the tabs,
+    // before the table itself
+    let height = f.area().height;
+    let help_height = if height >= 18 { 2 } else { 0 };
+    let status_height = if height >= 14 { 3 } else { 0 };
+    let tabs_height = if height >= 10 { 3 } else { 0 };
+
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
-            Constraint::Length(3), // Tabs
-            Constraint::Min(10),   // Main content
-            Constraint::Length(3), // Status bar
-            Constraint::Length(2), // Help
+            Constraint::Length(tabs_height),                      // Tabs
+            Constraint::Length(if show_banner { 1 } else { 0 }), // Exposed services
+            Constraint::Min(3),                                  // Main content
+            Constraint::Length(status_height),                   // Status bar
+            Constraint::Length(help_height),                     // Help
         ])
         .split(f.area());
 
-    draw_tabs(f, app, chunks[0]);
+    if tabs_height > 0 {
+        draw_tabs(f, app, chunks[0], theme);
+    }
+    app.tabs_area.set(chunks[0]);
+
+    if show_banner {
+        draw_exposed_banner(f, app, chunks[1], theme);
+    }
 
     match app.current_tab {
-        AppTab::Ports => draw_ports_tab(f, app, chunks[1]),
-        AppTab::Tunnels => draw_tunnels_tab(f, app, chunks[1]),
+        AppTab::Ports => draw_ports_tab(f, app, chunks[2], theme),
+        AppTab::Tunnels => draw_tunnels_tab(f, app, chunks[2], theme),
+        AppTab::Connections => draw_connections_tab(f, app, chunks[2], theme),
     }
 
-    draw_status_bar(f, app, chunks[2]);
-    draw_help(f, app, chunks[3]);
+    if status_height > 0 {
+        draw_status_bar(f, app, chunks[3], theme);
+    }
+    if help_height > 0 {
+        draw_help(f, app, chunks[4], theme);
+    }
 
     // Draw dialogs on top
     if app.show_filter {
-        draw_filter_dialog(f, app);
+        draw_filter_dialog(f, app, theme);
+    }
+
+    if app.show_search {
+        draw_search_dialog(f, app, theme);
     }
 
     if app.show_input {
-        draw_input_dialog(f, app);
+        draw_input_dialog(f, app, theme);
+    }
+
+    if let Some(form) = &app.tunnel_form {
+        draw_tunnel_form(f, form, app.editing_tunnel.is_some(), theme);
     }
 
     if app.show_confirm {
-        draw_confirm_dialog(f, app);
+        draw_confirm_dialog(f, app, theme);
+    }
+
+    if app.show_kill_log {
+        draw_kill_log(f, app, theme);
+    }
+
+    if let Some(view) = &app.log_view {
+        draw_log_view(f, view, theme);
     }
+
+    if let Some(command) = &app.command_preview {
+        draw_command_preview(f, command, theme);
+    }
+
+    if let Some(test) = &app.tunnel_test {
+        draw_tunnel_test(f, test, theme);
+    }
+
+    if let Some(selected) = app.signal_picker {
+        draw_signal_picker(f, selected, theme);
+    }
+
+    if app.show_help {
+        draw_help_overlay(f, app, theme);
+    }
+}
+
+/// Every kill attempted this session, newest first.
+fn draw_kill_log(f: &mut Frame, app: &App, theme: &Theme) {
+    let height = (app.kill_log.len().max(1) as u16 + 2).min(f.area().height);
+    let area = centered_rect(70, height, f.area());
+
+    let lines: Vec<Line> = if app.kill_log.is_empty() {
+        vec![Line::styled(
+            "No processes killed this session",
+            Style::default().fg(theme.muted),
+        )]
+    } else {
+        app.kill_log
+            .iter()
+            .rev()
+            .map(|record| {
+                let port = record.port.map_or(String::new(), |p| format!(" port {}", p));
+                let (outcome, color) = match &record.error {
+                    None if matches!(record.signal, "TERM" | "KILL") => {
+                        (String::from("killed"), theme.good)
+                    }
+                    None => (String::from("sent"), theme.good),
+                    Some(e) => (format!("failed: {}", e), theme.danger),
+                };
+                Line::from(vec![
+                    Span::styled(
+                        format!("{:>6} ago ", format_age(record.at.elapsed().as_secs())),
+                        Style::default().fg(theme.muted),
+                    ),
+                    Span::raw(format!(
+                        "SIG{} {} (PID {}){} ",
+                        record.signal, record.process_name, record.pid, port
+                    )),
+                    Span::styled(outcome, Style::default().fg(color)),
+                ])
+            })
+            .collect()
+    };
+
+    let log = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(" Recent kills (L to close) ")
+            .border_style(Style::default().fg(theme.danger)),
+    );
+
+    f.render_widget(Clear, area);
+    f.render_widget(log, area);
+}
+
+/// The end of a tunnel's ssh log, or an earlier stretch when scrolled back.
+fn draw_log_view(f: &mut Frame, view: &LogView, theme: &Theme) {
+    let height = f.area().height.saturating_sub(4).max(3);
+    let area = centered_rect(90, height, f.area());
+    let visible = height.saturating_sub(2) as usize;
+
+    let end = view.lines.len() - view.scroll_back.min(view.lines.len());
+    let lines: Vec<Line> = if view.lines.is_empty() {
+        vec![Line::styled(
+            format!("No ssh output yet in {}", view.path.display()),
+            Style::default().fg(theme.muted),
+        )]
+    } else {
+        view.lines[end.saturating_sub(visible)..end]
+            .iter()
+            .map(|line| {
+                // Connect attempts start with a marker line; set them apart
+                if line.starts_with("--- ") {
+                    Line::styled(line.clone(), Style::default().fg(theme.accent))
+                } else {
+                    Line::from(line.clone())
+                }
+            })
+            .collect()
+    };
+
+    let title = if view.scroll_back > 0 {
+        format!(" ssh log: {} (G to follow, Esc to close) ", view.tunnel)
+    } else {
+        format!(" ssh log: {} (↑/↓ to scroll, Esc to close) ", view.tunnel)
+    };
+    let log = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(title)
+            .border_style(Style::default().fg(theme.accent)),
+    );
+
+    f.render_widget(Clear, area);
+    f.render_widget(log, area);
+}
+
+/// The ssh command a connect would run, wrapped so long key paths stay visible.
+fn draw_command_preview(f: &mut Frame, command: &str, theme: &Theme) {
+    let text = vec![Line::styled(command.to_string(), Style::default().fg(theme.text))];
+    let inner_width = (f.area().width * 80 / 100).saturating_sub(2);
+    let area = centered_rect(80, wrapped_height(&text, inner_width) + 2, f.area());
+
+    let preview = Paragraph::new(text).wrap(Wrap { trim: false }).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(" ssh command (y to copy, Esc to close) ")
+            .border_style(Style::default().fg(theme.accent)),
+    );
+
+    f.render_widget(Clear, area);
+    f.render_widget(preview, area);
+}
+
+/// Progress, then the findings, of a pre-flight tunnel test.
+fn draw_tunnel_test(f: &mut Frame, test: &TunnelTest, theme: &Theme) {
+    let good = Style::default().fg(theme.good);
+    let bad = Style::default().fg(theme.danger);
+    let muted = Style::default().fg(theme.muted);
+    let mut lines = Vec::new();
+    match &test.result {
+        None => {
+            let frame = (test.started.elapsed().as_millis() / 100) as usize % SPINNER.len();
+            lines.push(Line::from(format!("{} Logging in…", SPINNER[frame])));
+        }
+        Some(Err(e)) => lines.push(Line::styled(format!("✗ {}", e), bad)),
+        Some(Ok(report)) => {
+            lines.push(match (report.auth_ok, report.latency) {
+                (true, Some(latency)) => Line::styled(
+                    format!("✓ SSH login ok in {:.2}s", latency.as_secs_f64()),
+                    good,
+                ),
+                (true, None) => Line::styled("✓ SSH login ok", good),
+                (false, _) => Line::styled("✗ SSH login failed", bad),
+            });
+            lines.push(match report.target_reachable {
+                Some(true) => Line::styled("✓ Remote target reachable", good),
+                Some(false) => Line::styled("✗ Remote target unreachable", bad),
+                None if report.auth_ok => {
+                    Line::styled("– Remote target not checked for this kind", muted)
+                }
+                None => Line::styled("– Remote target not checked", muted),
+            });
+            if let Some(error) = &report.error {
+                lines.push(Line::styled(error.clone(), Style::default().fg(theme.text)));
+            }
+        }
+    }
+
+    let inner_width = (f.area().width * 60 / 100).saturating_sub(2);
+    let area = centered_rect(60, wrapped_height(&lines, inner_width) + 2, f.area());
+    let popup = Paragraph::new(lines).wrap(Wrap { trim: false }).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(format!(" Testing '{}' (Esc to close) ", test.tunnel))
+            .border_style(Style::default().fg(theme.accent)),
+    );
+
+    f.render_widget(Clear, area);
+    f.render_widget(popup, area);
+}
+
+/// Signals to send the selected process, with their numbers on this platform.
+fn draw_signal_picker(f: &mut Frame, selected: usize, theme: &Theme) {
+    let lines: Vec<Line> = PICKER_SIGNALS
+        .iter()
+        .enumerate()
+        .map(|(i, signal)| {
+            let number = signal_number(signal).map_or(String::new(), |n| n.to_string());
+            let text = format!(" {:>2}  SIG{} ", number, signal);
+            if i == selected {
+                Line::styled(text, Style::default().add_modifier(Modifier::REVERSED))
+            } else {
+                Line::from(text)
+            }
+        })
+        .collect();
+
+    let area = centered_rect(30, lines.len() as u16 + 2, f.area());
+    let picker = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(" Send signal (Enter) ")
+            .border_style(Style::default().fg(theme.danger)),
+    );
+
+    f.render_widget(Clear, area);
+    f.render_widget(picker, area);
+}
+
+/// Compact age such as `42s`, `5m` or `3h`.
+fn format_age(secs: u64) -> String {
+    match secs {
+        0..60 => format!("{}s", secs),
+        60..3600 => format!("{}m", secs / 60),
+        _ => format!("{}h", secs / 3600),
+    }
+}
+
+/// How long ago a process started, or `?` when unknown or in the future (clock skew).
+fn process_age(start_time: Option<u64>) -> String {
+    match start_time.and_then(|start| crate::port::unix_now().checked_sub(start)) {
+        Some(secs) => format_duration(std::time::Duration::from_secs(secs)),
+        None => String::from("?"),
+    }
+}
+
+/// Duration in its two largest units, such as `42s`, `5m12s`, `1h23m` or `2d4h`.
+fn format_duration(duration: std::time::Duration) -> String {
+    let secs = duration.as_secs();
+    match secs {
+        0..60 => format!("{}s", secs),
+        60..3600 => format!("{}m{}s", secs / 60, secs % 60),
+        3600..86400 => format!("{}h{}m", secs / 3600, secs % 3600 / 60),
+        _ => format!("{}d{}h", secs / 86400, secs % 86400 / 3600),
+    }
+}
+
+/// Keybindings for the help overlay; an empty key starts a new section.
+const HELP_ENTRIES: &[(&str, &str)] = &[
+    ("", "Global"),
+    ("Tab / Shift+Tab", "Next / previous tab"),
+    ("1 / 2 / 3", "Jump to Ports / Tunnels / Connections"),
+    ("↑ k / ↓ j", "Move selection"),
+    ("g Home / G End", "First / last item"),
+    ("PgUp / PgDn", "Move a page up / down"),
+    ("Mouse", "Click a tab or row, wheel to move; double-click a port to kill it"),
+    ("P", "Switch tunnel profile"),
+    ("w", "Dismiss the exposed-services warning"),
+    ("T", "Cycle color theme: dark, light, high-contrast (not on Tunnels)"),
+    ("y", "Copy the selected port, or the tunnel's ssh command, to the clipboard"),
+    ("!", "Open a shell with the selection's PID or tunnel in its environment"),
+    ("/", "Search; highlights every match and jumps to the first"),
+    ("n / N", "Next / previous search match"),
+    ("?", "Toggle this help"),
+    ("q / Ctrl+C", "Quit"),
+    ("", "Ports"),
+    ("r / F5", "Refresh"),
+    ("A", "Toggle auto-refresh"),
+    ("f", "Filter by port, process or PID"),
+    ("Esc", "Clear the search highlight, then the filter"),
+    ("Space", "Mark / unmark row for a bulk kill"),
+    ("K", "Kill marked processes, or the selected one (SIGTERM, then SIGKILL)"),
+    ("X", "Force kill marked processes, or the selected one (SIGKILL)"),
+    ("x", "Pick a signal to send (HUP, INT, USR1...)"),
+    ("p", "Kill a process by PID"),
+    ("R", "Restart selected process"),
+    ("m", "Toggle dual-stack merge"),
+    ("M", "Show only my processes"),
+    ("t / l", "Cycle protocol (TCP/UDP/all) / state (LISTEN/ESTABLISHED/all) filter"),
+    ("b", "Cycle bind filter: all interfaces, loopback, specific address, all"),
+    ("v", "Group sockets by process"),
+    ("Enter", "Expand / collapse process (grouped view)"),
+    ("i", "Toggle command line / directory pane"),
+    ("s / o", "Sort by port / PID / process / protocol / age; reverse order"),
+    ("U", "Toggle scanning with sudo"),
+    ("S", "Save a snapshot of all connections"),
+    ("E", "Export the ports in view as JSON or CSV"),
+    ("L", "Show processes killed this session"),
+    ("", "Tunnels"),
+    ("a", "Add tunnel (Ctrl+F suggests a free local port)"),
+    ("e", "Edit selected tunnel"),
+    ("D", "Duplicate selected tunnel into a prefilled add form"),
+    ("c / d", "Connect / disconnect"),
+    ("t", "Test that the forward answers (SOCKS handshake for -D)"),
+    ("T", "Check ssh login and the remote target without connecting"),
+    ("x", "Delete"),
+    ("f", "Filter by name, host, local port or remote target"),
+    ("i", "Toggle details"),
+    ("C", "Show the exact ssh command connecting runs (y copies it)"),
+    ("l", "Show the tunnel's ssh log (↑/↓ PgUp/PgDn scroll, G follows)"),
+    ("s", "Sort by saved order / status / name"),
+    ("E", "Export tunnels as a shell script"),
+    ("I", "Import LocalForwards from ~/.ssh/config"),
+    ("", "Connections"),
+    ("x", "Pick a signal to send the connection's process"),
+    ("K / X", "Kill the connection's process (graceful / SIGKILL)"),
+    ("f / M", "Filter, only my processes (shared with Ports)"),
+    ("", "Dialogs"),
+    ("y / n", "Confirm / cancel"),
+    ("← → Tab", "Move between Yes and No (No has focus for kills and deletes)"),
+    ("Enter / Esc", "Submit / cancel a prompt or typed confirmation"),
+    ("← → Home End", "Move the cursor in a prompt"),
+    ("Backspace / Delete", "Delete before / under the cursor"),
+    ("Tab / Shift+Tab", "Next / previous field of the add-tunnel form"),
+    ("Ctrl+F", "Suggest a free local port"),
+    ("↑ / ↓", "Pick a recent host at the SSH host prompt"),
+];
+
+fn help_lines(theme: &Theme) -> Vec<Line<'static>> {
+    let mut lines = Vec::new();
+    for (key, description) in HELP_ENTRIES {
+        if key.is_empty() {
+            if !lines.is_empty() {
+                lines.push(Line::from(""));
+            }
+            lines.push(Line::styled(
+                *description,
+                Style::default().fg(theme.header).add_modifier(Modifier::BOLD),
+            ));
+        } else {
+            lines.push(Line::from(vec![
+                Span::styled(format!("  {:<16} ", key), Style::default().fg(theme.accent)),
+                Span::raw(*description),
+            ]));
+        }
+    }
+    lines
+}
+
+/// Popup area of the help overlay and its content height once wrapped.
+fn help_layout(area: Rect) -> (Rect, u16) {
+    let inner_width = (area.width * 70 / 100).saturating_sub(2);
+    // Colors don't change the wrapped height, so any theme will do
+    let content_height = wrapped_height(&help_lines(&crate::theme::DARK), inner_width);
+    let height = (content_height + 2).min(area.height.saturating_sub(2));
+    (centered_rect(70, height, area), content_height)
 }
 
-fn draw_tabs(f: &mut Frame, app: &App, area: Rect) {
-    let titles = vec!["[1] Ports", "[2] SSH Tunnels"];
+/// How far the help overlay can scroll in a terminal of the given size.
+pub fn help_max_scroll(area: Rect) -> u16 {
+    let (popup, content_height) = help_layout(area);
+    content_height.saturating_sub(popup.height.saturating_sub(2))
+}
+
+fn draw_help_overlay(f: &mut Frame, app: &App, theme: &Theme) {
+    let (area, content_height) = help_layout(f.area());
+    let max_scroll = help_max_scroll(f.area());
+    let scroll = app.help_scroll.min(max_scroll);
+
+    let title = if max_scroll > 0 {
+        " Help (j/k, PgUp/PgDn to scroll, ? to close) "
+    } else {
+        " Help (? to close) "
+    };
+
+    let help = Paragraph::new(help_lines(theme))
+        .wrap(Wrap { trim: false })
+        .scroll((scroll, 0))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(title)
+                .border_style(Style::default().fg(theme.accent)),
+        );
+
+    f.render_widget(Clear, area);
+    f.render_widget(help, area);
+
+    if max_scroll > 0 {
+        let mut state = ScrollbarState::new(content_height as usize)
+            .viewport_content_length(area.height.saturating_sub(2) as usize)
+            .position(scroll as usize);
+        f.render_stateful_widget(
+            Scrollbar::new(ScrollbarOrientation::VerticalRight),
+            area,
+            &mut state,
+        );
+    }
+}
+
+/// Tab headers, in `AppTab` order
+const TAB_TITLES: [&str; 3] = ["[1] Ports", "[2] SSH Tunnels", "[3] Connections"];
+const TABS: [AppTab; 3] = [AppTab::Ports, AppTab::Tunnels, AppTab::Connections];
+
+/// Which tab header is at a screen position inside the tabs block, if any.
+pub fn tab_at(area: Rect, column: u16, row: u16) -> Option<AppTab> {
+    // Hidden on short terminals
+    if area.height == 0 || row != area.y + 1 {
+        return None;
+    }
+    // Tabs pads each title with a space either side and separates them with one column
+    let mut x = area.x + 1;
+    for (i, title) in TAB_TITLES.iter().enumerate() {
+        let width = title.chars().count() as u16 + 2;
+        if (x..x + width).contains(&column) {
+            return Some(TABS[i]);
+        }
+        x += width + 1;
+    }
+    None
+}
+
+/// Visible row of a bordered table with a header at a screen position, if any.
+pub fn table_row_at(area: Rect, column: u16, row: u16) -> Option<usize> {
+    // Borders and the header row
+    let first = area.y + 2;
+    let last = area.y + area.height.saturating_sub(1);
+    let inside = column > area.x && column + 1 < area.x + area.width;
+    (inside && (first..last).contains(&row)).then(|| (row - first) as usize)
+}
+
+fn draw_tabs(f: &mut Frame, app: &App, area: Rect, theme: &Theme) {
+    let titles = TAB_TITLES.to_vec();
+
+    let mut title = vec![Span::styled(
+        " Anchor ",
+        Style::default().fg(theme.accent).add_modifier(Modifier::BOLD),
+    )];
+    if app.read_only {
+        title.push(Span::styled(
+            "[READ-ONLY] ",
+            Style::default().fg(theme.danger).add_modifier(Modifier::BOLD),
+        ));
+    }
+    if app.privileged {
+        title.push(Span::styled(
+            "[PRIVILEGED] ",
+            Style::default().fg(theme.flash).add_modifier(Modifier::BOLD),
+        ));
+    }
+
     let tabs = Tabs::new(titles)
         .block(
             Block::default()
                 .borders(Borders::ALL)
-                .title(" Anchor ")
-                .title_style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)),
+                .title(Line::from(title)),
         )
-        .select(match app.current_tab {
-            AppTab::Ports => 0,
-            AppTab::Tunnels => 1,
-        })
-        .style(Style::default().fg(Color::White))
+        .select(TABS.iter().position(|&tab| tab == app.current_tab))
+        .style(Style::default().fg(theme.text))
         .highlight_style(
             Style::default()
-                .fg(Color::Yellow)
+                .fg(theme.header)
                 .add_modifier(Modifier::BOLD),
         );
 
     f.render_widget(tabs, area);
 }
 
-fn draw_ports_tab(f: &mut Frame, app: &App, area: Rect) {
-    let header_cells = ["Port", "PID", "Process", "Protocol", "State", "Address"]
+fn draw_exposed_banner(f: &mut Frame, app: &App, area: Rect, theme: &Theme) {
+    let banner = Paragraph::new(Line::from(vec![
+        Span::styled(
+            " ⚠ Exposed on all interfaces: ",
+            Style::default().fg(theme.danger).add_modifier(Modifier::BOLD),
+        ),
+        Span::styled(app.exposed.join(", "), Style::default().fg(theme.danger)),
+        Span::styled("  (w: dismiss)", Style::default().fg(theme.muted)),
+    ]));
+
+    f.render_widget(banner, area);
+}
+
+fn draw_ports_tab(f: &mut Frame, app: &App, area: Rect, theme: &Theme) {
+    // Only look the process up while the pane is open
+    let selected = if app.show_port_details {
+        app.selected_process_details()
+    } else {
+        None
+    };
+    let area = match selected {
+        Some((pid, details)) => {
+            let lines = match details {
+                Some(details) => vec![
+                    format!(
+                        "PID {} run by {}, running for {}",
+                        pid,
+                        details.user.as_deref().unwrap_or("?"),
+                        process_age(details.start_time)
+                    ),
+                    format!("Command: {}", details.command_line),
+                    match details.cwd {
+                        Some(cwd) => format!("Directory: {}", cwd.display()),
+                        None => String::from("Directory: not visible to this user"),
+                    },
+                ],
+                None => vec![format!("PID {} has exited since the last scan", pid)],
+            };
+            let text: Vec<Line> = lines.iter().map(|l| Line::from(l.as_str())).collect();
+            // Long command lines wrap; keep at least half the height for the table
+            let height = (wrapped_height(&text, area.width.saturating_sub(2)) + 2)
+                .min(area.height / 2);
+            let chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Min(5), Constraint::Length(height)])
+                .split(area);
+            draw_details(f, &lines, chunks[1], theme);
+            chunks[0]
+        }
+        None => area,
+    };
+
+    let mut headers = vec!["", "Port", "PID", "Process", "User", "Protocol", "State", "Address"];
+    if app.socket_count_column {
+        headers.push("Socks");
+    }
+    if app.connection_count_column {
+        headers.push("Conns");
+    }
+    if app.age_column {
+        headers.push("Age");
+    }
+    if app.socket_stats {
+        headers.push("Queue");
+    }
+    let arrow = if app.sort_ascending { "▲" } else { "▼" };
+    let header_cells = headers.iter().map(|h| {
+        let label = if *h == app.sort_key.column() {
+            format!("{} {}", h, arrow)
+        } else {
+            h.to_string()
+        };
+        Cell::from(label).style(Style::default().fg(theme.header).add_modifier(Modifier::BOLD))
+    });
+    let header = Row::new(header_cells).height(1);
+
+    // Only build rows for the visible window; borders and header take 3 lines
+    let visible = (area.height as usize).saturating_sub(3).max(1);
+    let total = app.port_rows.len();
+    let offset = scroll_offset(&app.port_offset, app.port_selected, total, visible);
+    app.page_rows.set(visible);
+    app.table_area.set(area);
+
+    let rows: Vec<Row> = app.port_rows[offset..total.min(offset + visible)]
+        .iter()
+        .enumerate()
+        .map(|(row, port_row)| {
+            let selected = offset + row == app.port_selected;
+            match port_row {
+                PortRow::Socket(i) => socket_row(
+                    app,
+                    theme,
+                    app.filtered_port(*i),
+                    app.dual_stack_peer(*i),
+                    selected,
+                    app.group_by_process,
+                ),
+                PortRow::Process { pid, sockets } => {
+                    process_row(app, theme, *pid, sockets, selected)
+                }
+            }
+        })
+        .collect();
+
+    let narrowed = !app.filter_text.is_empty()
+        || app.mine_only
+        || app.proto_filter.is_some()
+        || app.state_filter.is_some()
+        || app.scope_filter.is_some();
+    let mut title = if !narrowed {
+        format!(" Ports ({}) ", app.filtered_ports.len())
+    } else {
+        format!(" Ports ({}/{}) ", app.filtered_ports.len(), app.ports.len())
+    };
+    if !app.filter_text.is_empty() {
+        title.push_str(&format!("[filter: {}] ", app.filter_text));
+    }
+    if let Some(protocol) = &app.proto_filter {
+        title.push_str(&format!("[{}] ", protocol));
+    }
+    if let Some(state) = &app.state_filter {
+        title.push_str(&format!("[{}] ", state));
+    }
+    if let Some(scope) = app.scope_filter {
+        title.push_str(&format!("[{}] ", scope.label()));
+    }
+    if app.mine_only {
+        title.push_str(&format!("[mine: {}] ", app.current_user));
+    }
+    if app.group_by_process {
+        title.push_str("[by process] ");
+    }
+    if !app.selected_ports.is_empty() {
+        title.push_str(&format!("[{} marked] ", app.selected_ports.len()));
+    }
+    if app.port_backend != PortBackend::Lsof {
+        title.push_str(&format!("[via {}] ", app.port_backend.name()));
+    }
+    if app.scan_problem.is_some() {
+        title.push_str("⚠ partial scan ");
+    }
+
+    let mut widths = vec![
+        Constraint::Length(1),  // Marked for bulk kill
+        Constraint::Length(8),  // Port
+        Constraint::Length(8),  // PID
+        Constraint::Length(20), // Process
+        Constraint::Length(10), // User
+        Constraint::Length(10), // Protocol
+        Constraint::Length(14), // State
+        Constraint::Min(20),    // Address
+    ];
+    if app.socket_count_column {
+        widths.push(Constraint::Length(7)); // Socks
+    }
+    if app.connection_count_column {
+        widths.push(Constraint::Length(7)); // Conns
+    }
+    if app.age_column {
+        widths.push(Constraint::Length(8)); // Age
+    }
+    if app.socket_stats {
+        widths.push(Constraint::Length(12)); // Queue
+    }
+
+    let table = Table::new(rows, widths)
+    .header(header)
+    .block(Block::default().borders(Borders::ALL).title(title))
+    .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+
+    f.render_widget(table, area);
+    draw_table_scrollbar(f, area, total, visible, offset);
+}
+
+/// First row to draw so `selected` stays in view, moving the previous offset as little as possible.
+fn scroll_offset(
+    offset: &std::cell::Cell<usize>,
+    selected: usize,
+    total: usize,
+    visible: usize,
+) -> usize {
+    let mut first = offset.get();
+    if selected < first {
+        first = selected;
+    } else if selected >= first + visible {
+        first = selected + 1 - visible;
+    }
+    first = first.min(total.saturating_sub(visible));
+    offset.set(first);
+    first
+}
+
+/// Scrollbar over the right border of a table that has more rows than fit.
+fn draw_table_scrollbar(f: &mut Frame, area: Rect, total: usize, visible: usize, offset: usize) {
+    if total <= visible {
+        return;
+    }
+    let mut state = ScrollbarState::new(total - visible)
+        .viewport_content_length(visible)
+        .position(offset);
+    f.render_stateful_widget(
+        Scrollbar::new(ScrollbarOrientation::VerticalRight),
+        area.inner(Margin {
+            vertical: 1,
+            horizontal: 0,
+        }),
+        &mut state,
+    );
+}
+
+/// One socket of the ports table.
+fn socket_row(
+    app: &App,
+    theme: &Theme,
+    port: &PortInfo,
+    dual_stack_peer: Option<&str>,
+    selected: bool,
+    child: bool,
+) -> Row<'static> {
+    let mut style = if selected {
+        Style::default()
+            .bg(theme.selection_bg)
+            .add_modifier(Modifier::BOLD)
+    } else if app.search_hit_port(port) {
+        search_style(app, theme)
+    } else {
+        Style::default()
+    };
+
+    let mut state_style = match port.state.as_str() {
+        "LISTEN" => style.fg(theme.listen),
+        "ESTABLISHED" => style.fg(theme.established),
+        _ => style.fg(theme.inactive),
+    };
+
+    // Briefly flag sockets that just appeared or changed state
+    if app.port_changed_recently(port) {
+        let flash = if app.monochrome {
+            Style::default().add_modifier(Modifier::UNDERLINED)
+        } else {
+            Style::default().fg(theme.flash)
+        };
+        style = style.patch(flash);
+        state_style = state_style.patch(flash);
+    }
+
+    let port_label = if port.port == 0 {
+        String::from("?")
+    } else {
+        port.port.to_string()
+    };
+    let port_style = if app.port_conflicted(port) {
+        conflict_style(app, theme, style)
+    } else {
+        style
+    };
+    let mut cells = vec![
+        Cell::from(if app.port_marked(port) { "✓" } else { "" }).style(style.fg(theme.good)),
+        // Indent sockets under their process header in the grouped view
+        Cell::from(if child {
+            format!("  └ {}", port_label)
+        } else {
+            port_label
+        })
+        .style(port_style),
+        Cell::from(port.pid.to_string()).style(style),
+        Cell::from(match &port.container {
+            Some(label) => format!("{} ({})", port.process_name, label),
+            None => port.process_name.clone(),
+        })
+        .style(style),
+        Cell::from(port.user.clone()).style(user_style(app, theme, &port.user, style)),
+        Cell::from(port.protocol.clone()).style(style),
+        Cell::from(port.state.clone()).style(state_style),
+        Cell::from(Line::from(vec![
+            Span::styled(
+                match dual_stack_peer {
+                    // Expand the selected row to show both sockets
+                    Some(v6) if selected => {
+                        format!("dual-stack: {} + [{}]", port.local_address, v6)
+                    }
+                    Some(_) => String::from("dual-stack"),
+                    None => port.local_address.clone(),
+                },
+                style,
+            ),
+            scope_badge(app, theme, port, dual_stack_peer.is_some(), style),
+        ]))
+        .style(style),
+    ];
+    if app.socket_count_column {
+        // Hundreds of sockets on one process usually means a leak
+        let count_style = match port.socket_count {
+            100.. => style.fg(theme.danger).add_modifier(Modifier::BOLD),
+            20.. => style.fg(theme.warning),
+            _ => style,
+        };
+        cells.push(Cell::from(port.socket_count.to_string()).style(count_style));
+    }
+    if app.connection_count_column {
+        // Only listeners have connections credited to them
+        let listener = port.state == "LISTEN" && port.protocol == "TCP";
+        let count = if listener { port.connection_count.to_string() } else { String::new() };
+        let count_style = if port.connection_count > 0 {
+            style.fg(theme.established)
+        } else {
+            style
+        };
+        cells.push(Cell::from(count).style(count_style));
+    }
+    if app.age_column {
+        cells.push(Cell::from(process_age(port.start_time)).style(style));
+    }
+    if app.socket_stats {
+        // Accept queue depth over backlog limit
+        let queue = match (port.accept_queue, port.backlog) {
+            (Some(queued), Some(backlog)) => format!("{}/{}", queued, backlog),
+            _ => String::new(),
+        };
+        cells.push(Cell::from(queue).style(style));
+    }
+
+    Row::new(cells).height(1)
+}
+
+/// Background for rows matching the search, other than the selected one.
+fn search_style(app: &App, theme: &Theme) -> Style {
+    if app.monochrome {
+        Style::default().add_modifier(Modifier::UNDERLINED)
+    } else {
+        Style::default().bg(theme.search_bg)
+    }
+}
+
+/// A `[v4 all]`-style tag after the address, loud when a socket listens on every interface.
+fn scope_badge(
+    app: &App,
+    theme: &Theme,
+    port: &PortInfo,
+    dual_stack: bool,
+    style: Style,
+) -> Span<'static> {
+    let version = if dual_stack { "v4+v6" } else { port.ip_version.label() };
+    let (scope, badge_style) = match port.scope {
+        BindScope::AllInterfaces if app.monochrome => ("all", style.add_modifier(Modifier::BOLD)),
+        BindScope::AllInterfaces => ("all", style.fg(theme.warning)),
+        BindScope::Loopback => ("lo", style.fg(theme.inactive)),
+        BindScope::Specific => ("", style.fg(theme.inactive)),
+    };
+    let tag = [version, scope]
+        .into_iter()
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    if tag.is_empty() {
+        Span::raw("")
+    } else {
+        Span::styled(format!(" [{}]", tag), badge_style)
+    }
+}
+
+/// Picks out root-owned processes, which usually aren't the user's own servers.
+fn user_style(app: &App, theme: &Theme, user: &str, style: Style) -> Style {
+    if user != "root" || user == app.current_user {
+        style
+    } else if app.monochrome {
+        style.add_modifier(Modifier::ITALIC)
+    } else {
+        style.fg(theme.danger)
+    }
+}
+
+/// Highlight for a port another process is also bound to.
+fn conflict_style(app: &App, theme: &Theme, style: Style) -> Style {
+    if app.monochrome {
+        style.add_modifier(Modifier::REVERSED)
+    } else {
+        style.fg(theme.warning).add_modifier(Modifier::BOLD)
+    }
+}
+
+/// A process header in the grouped view, summarising its sockets.
+fn process_row(
+    app: &App,
+    theme: &Theme,
+    pid: i32,
+    sockets: &[usize],
+    selected: bool,
+) -> Row<'static> {
+    let searched = sockets
+        .iter()
+        .any(|&i| app.search_hit_port(app.filtered_port(i)));
+    let style = if selected {
+        Style::default().bg(theme.selection_bg).add_modifier(Modifier::BOLD)
+    } else if searched {
+        search_style(app, theme).add_modifier(Modifier::BOLD)
+    } else {
+        Style::default().add_modifier(Modifier::BOLD)
+    };
+    let first = app.filtered_port(sockets[0]);
+
+    let mut ports: Vec<String> = Vec::new();
+    for &i in sockets {
+        let port = app.filtered_port(i);
+        let label = format!("{}/{}", port.port, port.protocol);
+        if port.port != 0 && !ports.contains(&label) {
+            ports.push(label);
+        }
+    }
+
+    let conflicted = sockets
+        .iter()
+        .any(|&i| app.port_conflicted(app.filtered_port(i)));
+
+    let marker = if app.expanded_processes.contains(&pid) { "▾" } else { "▸" };
+    let marked = sockets
+        .iter()
+        .filter(|&&i| app.port_marked(app.filtered_port(i)))
+        .count();
+    let mut cells = vec![
+        // Partly marked when only some of the process's ports are
+        Cell::from(match marked {
+            0 => "",
+            n if n == sockets.len() => "✓",
+            _ => "-",
+        })
+        .style(style.fg(theme.good)),
+        Cell::from(marker).style(style),
+        Cell::from(pid.to_string()).style(style),
+        Cell::from(match &first.container {
+            Some(label) => format!("{} ({})", first.process_name, label),
+            None => first.process_name.clone(),
+        })
+        .style(style),
+        Cell::from(first.user.clone()).style(user_style(app, theme, &first.user, style)),
+        Cell::from("").style(style),
+        Cell::from(format!("{} sockets", sockets.len())).style(style.fg(theme.established)),
+        Cell::from(ports.join(", ")).style(if conflicted {
+            conflict_style(app, theme, style)
+        } else {
+            style
+        }),
+    ];
+    if app.socket_count_column {
+        cells.push(Cell::from(first.socket_count.to_string()).style(style));
+    }
+    if app.connection_count_column {
+        // Counts are per port, so an IPv4 and IPv6 listener on one port share theirs
+        let mut listening: Vec<(u16, usize)> = Vec::new();
+        for &i in sockets {
+            let port = app.filtered_port(i);
+            if port.state == "LISTEN"
+                && port.protocol == "TCP"
+                && !listening.iter().any(|(p, _)| *p == port.port)
+            {
+                listening.push((port.port, port.connection_count));
+            }
+        }
+        let total: usize = listening.iter().map(|(_, count)| count).sum();
+        cells.push(Cell::from(total.to_string()).style(style));
+    }
+    if app.age_column {
+        cells.push(Cell::from(process_age(first.start_time)).style(style));
+    }
+    if app.socket_stats {
+        cells.push(Cell::from("").style(style));
+    }
+
+    Row::new(cells).height(1)
+}
+
+fn draw_tunnels_tab(f: &mut Frame, app: &App, area: Rect, theme: &Theme) {
+    let selected = app.selected_tunnel();
+    let area = match selected {
+        Some(tunnel) if app.show_tunnel_details => {
+            let mut details = tunnel.detail_lines();
+            if let Some(error) = &tunnel.last_error {
+                details.push(format!("Last reconnect failed: {}", error));
+            }
+            let chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([
+                    Constraint::Min(5),
+                    Constraint::Length(details.len() as u16 + 2),
+                ])
+                .split(area);
+            draw_details(f, &details, chunks[1], theme);
+            chunks[0]
+        }
+        _ => area,
+    };
+
+    let header_cells = ["Name", "SSH Host", "Kind", "Forward", "Uptime", "Status"]
         .iter()
-        .map(|h| Cell::from(*h).style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)));
+        .map(|h| Cell::from(*h).style(Style::default().fg(theme.header).add_modifier(Modifier::BOLD)));
     let header = Row::new(header_cells).height(1);
 
+    let visible = (area.height as usize).saturating_sub(3).max(1);
+    let total = app.filtered_tunnels.len();
+    let offset = scroll_offset(&app.tunnel_offset, app.tunnel_selected, total, visible);
+    app.page_rows.set(visible);
+    app.table_area.set(area);
+
     let rows: Vec<Row> = app
-        .filtered_ports
+        .filtered_tunnels
         .iter()
+        .map(|&index| &app.tunnel_manager.tunnels[index])
         .enumerate()
-        .map(|(i, port)| {
-            let style = if i == app.port_selected {
+        .skip(offset)
+        .take(visible)
+        .map(|(i, tunnel)| {
+            let style = if i == app.tunnel_selected {
                 Style::default()
-                    .bg(Color::DarkGray)
+                    .bg(theme.selection_bg)
                     .add_modifier(Modifier::BOLD)
+            } else if app.search_hit_tunnel(tunnel) {
+                search_style(app, theme)
             } else {
                 Style::default()
             };
 
-            let state_style = match port.state.as_str() {
-                "LISTEN" => style.fg(Color::Green),
-                "ESTABLISHED" => style.fg(Color::Cyan),
-                _ => style.fg(Color::Gray),
+            let job = app.tunnel_job(&tunnel.name);
+            let (status, status_style) = if job == Some(TunnelJob::Connect) {
+                (String::from("◌ Connecting"), style.fg(theme.warning))
+            } else if job == Some(TunnelJob::Disconnect) {
+                (String::from("◌ Disconnecting"), style.fg(theme.warning))
+            } else if tunnel.reconnecting() && !tunnel.is_connected() {
+                let status = match tunnel.reconnect_attempts {
+                    0 => String::from("◌ Reconnecting"),
+                    n => format!("◌ Reconnecting ({})", n),
+                };
+                (status, style.fg(theme.warning))
+            } else if tunnel.is_connected() {
+                (tunnel.status_string().to_string(), style.fg(theme.good))
+            } else {
+                (tunnel.status_string().to_string(), style.fg(theme.inactive))
+            };
+
+            let (row_color, marker) = tunnel_row_color(app.tunnel_colors, tunnel, theme);
+            let (style, mut name) = match (row_color, app.monochrome) {
+                (Some(_), true) => (style, format!("{}{}", marker, tunnel.name)),
+                (Some(color), false) => (style.fg(color), tunnel.name.clone()),
+                (None, _) => (style, tunnel.name.clone()),
             };
+            if tunnel.identity_file_missing() {
+                name = format!("⚠ {}", name);
+            }
 
             Row::new(vec![
-                Cell::from(port.port.to_string()).style(style),
-                Cell::from(port.pid.to_string()).style(style),
-                Cell::from(port.process_name.clone()).style(style),
-                Cell::from(port.protocol.clone()).style(style),
-                Cell::from(port.state.clone()).style(state_style),
-                Cell::from(port.local_address.clone()).style(style),
+                Cell::from(name).style(style),
+                // Proxied tunnels show their bastion after the host
+                Cell::from(match &tunnel.jump_host {
+                    Some(jump_host) => format!("{} via {}", tunnel.ssh_host, jump_host),
+                    None => tunnel.ssh_host.clone(),
+                })
+                .style(style),
+                Cell::from(tunnel.kind.label()).style(style),
+                Cell::from(tunnel.forward_display()).style(style),
+                // A forward found running at startup has been up for who knows how long
+                Cell::from(match tunnel.connected_at {
+                    _ if !tunnel.is_connected() => String::new(),
+                    Some(at) => format_duration(at.elapsed()),
+                    None => String::from("unknown"),
+                })
+                .style(style),
+                if app.host_resolver.is_unresolved(tunnel) {
+                    Cell::from(format!("{} ⚠ host unresolved", status))
+                        .style(style.fg(theme.danger))
+                } else {
+                    Cell::from(status).style(status_style)
+                },
             ])
             .height(1)
         })
         .collect();
 
-    let title = if app.filter_text.is_empty() {
-        format!(" Ports ({}) ", app.filtered_ports.len())
+    let mut title = if app.tunnel_filter_text.is_empty() {
+        format!(
+            " SSH Tunnels ({}) [profile: {}] ",
+            app.tunnel_manager.tunnels.len(),
+            app.profile
+        )
     } else {
         format!(
-            " Ports ({}/{}) [filter: {}] ",
-            app.filtered_ports.len(),
-            app.ports.len(),
-            app.filter_text
+            " SSH Tunnels ({}/{}) [profile: {}] [filter: {}] ",
+            app.filtered_tunnels.len(),
+            app.tunnel_manager.tunnels.len(),
+            app.profile,
+            app.tunnel_filter_text
         )
     };
+    if app.tunnel_sort != TunnelSort::Saved {
+        title.push_str(&format!("[sort: {}] ", app.tunnel_sort.label()));
+    }
 
     let table = Table::new(
         rows,
         [
-            Constraint::Length(8),  // Port
-            Constraint::Length(8),  // PID
-            Constraint::Length(20), // Process
-            Constraint::Length(10), // Protocol
-            Constraint::Length(14), // State
-            Constraint::Min(20),    // Address
+            Constraint::Length(15), // Name
+            Constraint::Length(25), // SSH Host
+            Constraint::Length(8),  // Kind
+            Constraint::Length(30), // Forward
+            Constraint::Length(8),  // Uptime
+            Constraint::Min(15),    // Status
         ],
     )
     .header(header)
-    .block(Block::default().borders(Borders::ALL).title(title))
-    .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    .block(Block::default().borders(Borders::ALL).title(title));
 
     f.render_widget(table, area);
+    draw_table_scrollbar(f, area, total, visible, offset);
 }
 
-fn draw_tunnels_tab(f: &mut Frame, app: &App, area: Rect) {
-    let header_cells = ["Name", "SSH Host", "Local Port", "Remote Target", "Status"]
+/// Established connections with both ends, apart from the listeners on the Ports tab.
+fn draw_connections_tab(f: &mut Frame, app: &App, area: Rect, theme: &Theme) {
+    let header_cells = ["Local", "Foreign", "Process", "PID"]
         .iter()
-        .map(|h| Cell::from(*h).style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)));
+        .map(|h| Cell::from(*h).style(Style::default().fg(theme.header).add_modifier(Modifier::BOLD)));
     let header = Row::new(header_cells).height(1);
 
+    let visible = (area.height as usize).saturating_sub(3).max(1);
+    let total = app.connections.len();
+    let offset = scroll_offset(&app.connection_offset, app.connection_selected, total, visible);
+    app.page_rows.set(visible);
+    app.table_area.set(area);
+
     let rows: Vec<Row> = app
-        .tunnel_manager
-        .tunnels
-        .iter()
+        .shown_connections()
         .enumerate()
-        .map(|(i, tunnel)| {
-            let style = if i == app.tunnel_selected {
+        .skip(offset)
+        .take(visible)
+        .map(|(i, conn)| {
+            let style = if i == app.connection_selected {
                 Style::default()
-                    .bg(Color::DarkGray)
+                    .bg(theme.selection_bg)
                     .add_modifier(Modifier::BOLD)
+            } else if app.search_hit_port(conn) {
+                search_style(app, theme)
             } else {
                 Style::default()
             };
-
-            let status_style = if tunnel.is_connected() {
-                style.fg(Color::Green)
+            // Bracket IPv6 so the port isn't read as part of the address
+            let local = if conn.local_address.contains(':') {
+                format!("[{}]:{}", conn.local_address, conn.port)
             } else {
-                style.fg(Color::Gray)
+                format!("{}:{}", conn.local_address, conn.port)
             };
-
             Row::new(vec![
-                Cell::from(tunnel.name.clone()).style(style),
-                Cell::from(tunnel.ssh_host.clone()).style(style),
-                Cell::from(tunnel.local_port.to_string()).style(style),
-                Cell::from(tunnel.remote_target.clone()).style(style),
-                Cell::from(tunnel.status_string()).style(status_style),
+                Cell::from(local).style(style),
+                Cell::from(conn.foreign_address.clone()).style(style.fg(theme.established)),
+                Cell::from(conn.process_name.clone()).style(style),
+                Cell::from(conn.pid.to_string()).style(style),
             ])
-            .height(1)
         })
         .collect();
 
-    let title = format!(" SSH Tunnels ({}) ", app.tunnel_manager.tunnels.len());
+    let title = if app.filter_text.is_empty() && !app.mine_only {
+        format!(" Connections ({}) ", total)
+    } else {
+        let established = app.ports.iter().filter(|p| p.state == "ESTABLISHED").count();
+        let mut title = format!(" Connections ({}/{}) ", total, established);
+        if !app.filter_text.is_empty() {
+            title.push_str(&format!("[filter: {}] ", app.filter_text));
+        }
+        if app.mine_only {
+            title.push_str(&format!("[mine: {}] ", app.current_user));
+        }
+        title
+    };
 
     let table = Table::new(
         rows,
         [
-            Constraint::Length(15), // Name
-            Constraint::Length(25), // SSH Host
-            Constraint::Length(12), // Local Port
-            Constraint::Length(20), // Remote Target
-            Constraint::Min(15),    // Status
+            Constraint::Length(28), // Local
+            Constraint::Length(40), // Foreign
+            Constraint::Length(20), // Process
+            Constraint::Min(8),     // PID
         ],
     )
     .header(header)
     .block(Block::default().borders(Borders::ALL).title(title));
 
     f.render_widget(table, area);
+    draw_table_scrollbar(f, area, total, visible, offset);
+}
+
+/// Row color for a tunnel under the configured mode, plus the marker used in monochrome.
+fn tunnel_row_color(
+    mode: TunnelColorMode,
+    tunnel: &TunnelConfig,
+    theme: &Theme,
+) -> (Option<Color>, String) {
+    match mode {
+        TunnelColorMode::None => (None, String::new()),
+        TunnelColorMode::Health => match tunnel.health() {
+            TunnelHealth::Up => (Some(theme.good), String::from("✓ ")),
+            TunnelHealth::Degraded => (Some(theme.warning), String::from("~ ")),
+            TunnelHealth::Down => (Some(theme.danger), String::from("✗ ")),
+        },
+        TunnelColorMode::Group => match &tunnel.group {
+            Some(group) => (Some(group_color(group, theme)), format!("[{}] ", group)),
+            None => (None, String::new()),
+        },
+    }
 }
 
-fn draw_status_bar(f: &mut Frame, app: &App, area: Rect) {
+/// A color picked from the group name, stable across runs.
+fn group_color(group: &str, theme: &Theme) -> Color {
+    // FNV-1a, since std's hasher isn't guaranteed stable between releases
+    let hash = group.bytes().fold(0x811c9dc5u32, |hash, byte| {
+        (hash ^ byte as u32).wrapping_mul(0x01000193)
+    });
+    theme.groups[hash as usize % theme.groups.len()]
+}
+
+/// Detail pane under a table; the first line is highlighted as a heading.
+fn draw_details(f: &mut Frame, details: &[String], area: Rect, theme: &Theme) {
+    let mut lines: Vec<Line> = details.iter().map(|d| Line::from(d.clone())).collect();
+    if let Some(first) = lines.first_mut() {
+        *first = first.clone().style(Style::default().fg(theme.accent));
+    }
+
+    let panel = Paragraph::new(lines)
+        .wrap(Wrap { trim: true })
+        .block(Block::default().borders(Borders::ALL).title(" Details "));
+
+    f.render_widget(panel, area);
+}
+
+/// Frames for the scan indicator, one per 100ms
+const SPINNER: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+
+fn draw_status_bar(f: &mut Frame, app: &App, area: Rect, theme: &Theme) {
+    let mut title = String::from(" Status ");
+    if let Some(elapsed) = app.scanning() {
+        let frame = (elapsed.as_millis() / 100) as usize % SPINNER.len();
+        title.push_str(&format!("[{} scanning] ", SPINNER[frame]));
+    }
+    if app.auto_refresh {
+        title.push_str(&format!("[auto-refresh {}s] ", app.refresh_interval.as_secs()));
+    }
+    match app.conflict_count {
+        0 => {}
+        1 => title.push_str("[1 port conflict] "),
+        n => title.push_str(&format!("[{} port conflicts] ", n)),
+    }
     let status = Paragraph::new(app.status_message.clone())
-        .style(Style::default().fg(Color::White))
-        .block(Block::default().borders(Borders::ALL).title(" Status "));
+        .style(Style::default().fg(theme.text))
+        .block(Block::default().borders(Borders::ALL).title(title));
 
     f.render_widget(status, area);
 }
 
-fn draw_help(f: &mut Frame, app: &App, area: Rect) {
+fn draw_help(f: &mut Frame, app: &App, area: Rect, theme: &Theme) {
     let help_text = match app.current_tab {
         AppTab::Ports => {
-            " ↑/↓:Navigate  K:Kill  r:Refresh  /:Filter  Tab:Switch  q:Quit "
+            " ↑/↓:Navigate  K:Kill  X:Force kill  x:Signal  p:Kill PID  R:Restart  r:Refresh  /:Search  f:Filter  m:Dual-stack  M:Mine  v:By process  s:Sort  S:Snapshot  y:Copy  ?:Help  q:Quit "
         }
         AppTab::Tunnels => {
-            " ↑/↓:Navigate  a:Add  c:Connect  d:Disconnect  x:Delete  Tab:Switch  q:Quit "
+            " ↑/↓:Navigate  /:Search  f:Filter  a:Add  D:Duplicate  c:Connect  d:Disconnect  C:Command  l:Log  t:Test  T:Check  x:Delete  i:Details  s:Sort  y:Copy  E:Export  P:Profile  ?:Help  q:Quit "
+        }
+        AppTab::Connections => {
+            " ↑/↓:Navigate  K:Kill  X:Force kill  r:Refresh  /:Search  f:Filter  M:Mine  y:Copy  ?:Help  q:Quit "
         }
     };
 
-    let help = Paragraph::new(help_text)
-        .style(Style::default().fg(Color::DarkGray))
-        .alignment(Alignment::Center);
+    if !app.read_only {
+        let help = Paragraph::new(help_text)
+            .style(Style::default().fg(theme.muted))
+            .alignment(Alignment::Center);
+        f.render_widget(help, area);
+        return;
+    }
 
-    f.render_widget(help, area);
+    // Don't advertise keys that would only answer "disabled in read-only mode"
+    let blocked: &[&str] = match app.current_tab {
+        AppTab::Ports => &["K", "X", "x", "p", "R"],
+        AppTab::Tunnels => &["c", "d", "x"],
+        AppTab::Connections => &["K", "X"],
+    };
+    let entries: Vec<&str> = help_text
+        .split("  ")
+        .map(str::trim)
+        .filter(|entry| {
+            let key = entry.split(':').next().unwrap_or_default();
+            !blocked.contains(&key)
+        })
+        .collect();
+    let line = Line::from(vec![
+        Span::styled(
+            " READ-ONLY ",
+            Style::default().fg(theme.danger).add_modifier(Modifier::BOLD),
+        ),
+        Span::styled(format!(" {} ", entries.join("  ")), Style::default().fg(theme.muted)),
+    ]);
+    f.render_widget(Paragraph::new(line).alignment(Alignment::Center), area);
 }
 
-fn draw_filter_dialog(f: &mut Frame, app: &App) {
+fn draw_filter_dialog(f: &mut Frame, app: &App, theme: &Theme) {
     let area = centered_rect(50, 3, f.area());
 
-    let filter_text = format!("/{}", app.filter_text);
+    let filter_text = format!("/{}", app.active_filter());
     let input = Paragraph::new(filter_text)
-        .style(Style::default().fg(Color::Yellow))
+        .style(Style::default().fg(theme.warning))
         .block(
             Block::default()
                 .borders(Borders::ALL)
                 .title(" Filter (Enter/Esc to close) ")
-                .border_style(Style::default().fg(Color::Yellow)),
+                .border_style(Style::default().fg(theme.warning)),
+        );
+
+    f.render_widget(Clear, area);
+    f.render_widget(input, area);
+}
+
+fn draw_search_dialog(f: &mut Frame, app: &App, theme: &Theme) {
+    let area = centered_rect(50, 3, f.area());
+
+    let input = Paragraph::new(format!("/{}", app.search_text))
+        .style(Style::default().fg(theme.accent))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(" Search (Enter to keep, Esc to cancel) ")
+                .border_style(Style::default().fg(theme.accent)),
         );
 
     f.render_widget(Clear, area);
     f.render_widget(input, area);
 }
 
-fn draw_input_dialog(f: &mut Frame, app: &App) {
-    let area = centered_rect(60, 3, f.area());
+fn draw_input_dialog(f: &mut Frame, app: &App, theme: &Theme) {
+    let suggestions = app.host_suggestions();
+    let area = centered_rect(60, 3 + suggestions.len() as u16, f.area());
+
+    // Reverse the character under the cursor, or a trailing space at the end
+    let cursor = app.input_cursor_position();
+    let before: String = app.input_buffer.chars().take(cursor).collect();
+    let mut rest = app.input_buffer.chars().skip(cursor);
+    let under = rest.next().map_or(String::from(" "), String::from);
+    let after: String = rest.collect();
+    let mut lines = vec![Line::from(vec![
+        Span::raw(before),
+        Span::styled(under, Style::default().add_modifier(Modifier::REVERSED)),
+        Span::raw(after),
+    ])];
+
+    // Recent hosts beneath the input, for ↑/↓ and Enter to pick from
+    for (i, host) in suggestions.iter().enumerate() {
+        lines.push(if app.host_suggestion == Some(i) {
+            Line::styled(
+                format!("› {}", host),
+                Style::default().fg(theme.accent).add_modifier(Modifier::REVERSED),
+            )
+        } else {
+            Line::styled(format!("  {}", host), Style::default().fg(theme.muted))
+        });
+    }
 
-    let input = Paragraph::new(app.input_buffer.as_str())
-        .style(Style::default().fg(Color::White))
+    let input = Paragraph::new(lines)
+        .style(Style::default().fg(theme.text))
         .block(
             Block::default()
                 .borders(Borders::ALL)
                 .title(format!(" {} ", app.input_prompt))
-                .border_style(Style::default().fg(Color::Cyan)),
+                .border_style(Style::default().fg(theme.accent)),
         );
 
     f.render_widget(Clear, area);
     f.render_widget(input, area);
 }
 
-fn draw_confirm_dialog(f: &mut Frame, app: &App) {
-    let area = centered_rect(60, 5, f.area());
+fn draw_tunnel_form(f: &mut Frame, form: &TunnelForm, editing: bool, theme: &Theme) {
+    let label_width = TUNNEL_FORM_FIELDS.iter().map(|l| l.len()).max().unwrap_or(0);
 
-    let text = vec![
-        Line::from(app.confirm_message.clone()),
-        Line::from(""),
-        Line::from(vec![
-            Span::styled("[Y]es", Style::default().fg(Color::Green)),
-            Span::raw("  "),
-            Span::styled("[N]o", Style::default().fg(Color::Red)),
-        ]),
-    ];
+    let mut text: Vec<Line> = TUNNEL_FORM_FIELDS
+        .iter()
+        .zip(&form.values)
+        .enumerate()
+        .map(|(i, (label, value))| {
+            let focused = i == form.focus;
+            let label_style = if focused {
+                Style::default().fg(theme.accent).add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(theme.inactive)
+            };
+            let cursor = if focused { "▏" } else { "" };
+            let mut spans = vec![
+                Span::styled(format!("{:>width$}: ", label, width = label_width), label_style),
+                Span::styled(format!("{}{}", value, cursor), Style::default().fg(theme.text)),
+            ];
+            if value.is_empty() {
+                spans.push(Span::styled(
+                    TUNNEL_FORM_PLACEHOLDERS[i],
+                    Style::default().fg(theme.muted),
+                ));
+            }
+            Line::from(spans)
+        })
+        .collect();
+    text.push(Line::from(""));
+    text.push(Line::styled(
+        "Tab/Shift+Tab: move  Ctrl+F: free port  Enter: save  Esc: cancel",
+        Style::default().fg(theme.muted),
+    ));
+
+    let area = centered_rect(60, text.len() as u16 + 2, f.area());
+    let dialog = Paragraph::new(text).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(if editing { " Edit Tunnel " } else { " Add Tunnel " })
+            .border_style(Style::default().fg(theme.accent)),
+    );
+
+    f.render_widget(Clear, area);
+    f.render_widget(dialog, area);
+}
+
+fn draw_confirm_dialog(f: &mut Frame, app: &App, theme: &Theme) {
+    // First line is the question, any following lines are detail (e.g. the command line)
+    let mut message_lines = app.confirm_message.lines();
+    let mut text = vec![Line::from(message_lines.next().unwrap_or_default().to_string())];
+    for detail in message_lines {
+        text.push(Line::styled(
+            detail.to_string(),
+            Style::default().fg(theme.muted),
+        ));
+    }
+    text.push(Line::from(""));
+    match &app.confirm_expected {
+        Some(expected) => {
+            text.push(Line::styled(
+                format!(
+                    "{}: type '{}' and press Enter (Esc cancels)",
+                    app.confirm_reason, expected
+                ),
+                Style::default().fg(theme.danger),
+            ));
+            text.push(Line::styled(
+                format!("> {}▏", app.confirm_input),
+                Style::default().fg(theme.text),
+            ));
+        }
+        None => {
+            // Reverse the focused button, which Enter presses
+            let button = |focused: bool, color: Color| {
+                let style = Style::default().fg(color);
+                if focused {
+                    style.add_modifier(Modifier::REVERSED | Modifier::BOLD)
+                } else {
+                    style
+                }
+            };
+            text.push(Line::from(vec![
+                Span::styled(" [Y]es ", button(app.confirm_focus_yes, theme.good)),
+                Span::raw("  "),
+                Span::styled(" [N]o ", button(!app.confirm_focus_yes, theme.danger)),
+            ]));
+            text.push(Line::styled(
+                "←/→ move, Enter chooses, Esc cancels",
+                Style::default().fg(theme.muted),
+            ));
+        }
+    }
+
+    let inner_width = (f.area().width * 60 / 100).saturating_sub(2);
+    let area = centered_rect(60, wrapped_height(&text, inner_width) + 2, f.area());
 
     let dialog = Paragraph::new(text)
         .alignment(Alignment::Center)
+        .wrap(Wrap { trim: true })
         .block(
             Block::default()
                 .borders(Borders::ALL)
                 .title(" Confirm ")
-                .border_style(Style::default().fg(Color::Yellow)),
+                // Typed confirmations guard the riskier actions
+                .border_style(Style::default().fg(if app.confirm_expected.is_some() {
+                    theme.danger
+                } else {
+                    theme.warning
+                })),
         );
 
     f.render_widget(Clear, area);
     f.render_widget(dialog, area);
 }
 
-fn centered_rect(percent_x: u16, height: u16, r: Rect) -> Rect {
-    let popup_layout = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Percentage((100 - height.min(100)) / 2),
-            Constraint::Length(height),
-            Constraint::Percentage((100 - height.min(100)) / 2),
-        ])
-        .split(r);
+/// Number of rows the given lines occupy when wrapped to `width` columns.
+fn wrapped_height(lines: &[Line], width: u16) -> u16 {
+    let width = width.max(1) as usize;
+    lines
+        .iter()
+        .map(|line| line.width().max(1).div_ceil(width) as u16)
+        .sum()
+}
 
-    Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([
-            Constraint::Percentage((100 - percent_x) / 2),
-            Constraint::Percentage(percent_x),
-            Constraint::Percentage((100 - percent_x) / 2),
-        ])
-        .split(popup_layout[1])[1]
+/// A popup `percent_x` wide and `height` tall centered in `r`, shrunk to fit a small terminal.
+fn centered_rect(percent_x: u16, height: u16, r: Rect) -> Rect {
+    let width = (r.width as u32 * percent_x.min(100) as u32 / 100) as u16;
+    let height = height.min(r.height);
+    Rect::new(
+        r.x + (r.width - width) / 2,
+        r.y + (r.height - height) / 2,
+        width,
+        height,
+    )
 }